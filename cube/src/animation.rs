@@ -0,0 +1,392 @@
+use crate::geometry::*;
+use crate::objec::Objects;
+
+/// Keys closer together in time than this are the same key.
+const SAME_TIME: f32 = 1e-3;
+
+/// How far one scrub moves the timeline, in seconds.
+pub const SCRUB_STEP: f32 = 0.1;
+
+/// How a value gets from one key to the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing{
+    /// Holds the key's value until the next key.
+    Step,
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// CSS-style timing curve through (0, 0), the two control points and (1, 1).
+    Bezier(f32, f32, f32, f32),
+}
+
+impl Easing{
+    /// The easings the viewer cycles through, in order.
+    pub const ALL: [Easing; 5] = [Easing::Linear, Easing::EaseInOut, Easing::EaseIn, Easing::EaseOut, Easing::Step];
+
+    /// Progress between two keys, from 0 to 1, at the fraction `t` of the time between them.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+            Easing::Linear => t,
+            Easing::EaseIn => bezier(0.42, 0.0, 1.0, 1.0, t),
+            Easing::EaseOut => bezier(0.0, 0.0, 0.58, 1.0, t),
+            Easing::EaseInOut => bezier(0.42, 0.0, 0.58, 1.0, t),
+            Easing::Bezier(x1, y1, x2, y2) => bezier(x1, y1, x2, y2, t),
+        }
+    }
+
+    /// Name in scene files; Bézier curves keep their control points in a separate field.
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Step => "step",
+            Easing::Linear => "linear",
+            Easing::EaseIn => "ease_in",
+            Easing::EaseOut => "ease_out",
+            Easing::EaseInOut => "ease_in_out",
+            Easing::Bezier(..) => "bezier",
+        }
+    }
+
+    /// Parses a name from `name`; `bezier` takes its control points from `curve`.
+    pub fn parse(name: &str, curve: Option<[f32; 4]>) -> Option<Easing> {
+        match name {
+            "step" => Some(Easing::Step),
+            "linear" => Some(Easing::Linear),
+            "ease_in" => Some(Easing::EaseIn),
+            "ease_out" => Some(Easing::EaseOut),
+            "ease_in_out" => Some(Easing::EaseInOut),
+            "bezier" => curve.filter(|c| (0.0..=1.0).contains(&c[0]) && (0.0..=1.0).contains(&c[2]))
+                .map(|[x1, y1, x2, y2]| Easing::Bezier(x1, y1, x2, y2)),
+            _ => None,
+        }
+    }
+}
+
+/// The y of the curve from (0, 0) to (1, 1) with control points (x1, y1) and (x2, y2)
+/// where its x is `x`. The x control points must lie in 0..=1, so x only grows.
+fn bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let curve = |a: f32, b: f32, s: f32| 3.0 * a * s * (1.0 - s) * (1.0 - s) + 3.0 * b * s * s * (1.0 - s) + s * s * s;
+    // Bisection, as Newton steps stall where the curve is flat.
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..24 {
+        let mid = (low + high) / 2.0;
+        if curve(x1, x2, mid) < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    curve(y1, y2, (low + high) / 2.0)
+}
+
+/// A property of an object that can be keyed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property{
+    Position,
+    /// `angle_x` and `angle_y`, in radians.
+    Angles,
+    Scale,
+    Color,
+}
+
+impl Property{
+    pub const ALL: [Property; 4] = [Property::Position, Property::Angles, Property::Scale, Property::Color];
+
+    /// How many numbers a value of the property has.
+    pub fn components(self) -> usize {
+        match self {
+            Property::Position | Property::Color => 3,
+            Property::Angles => 2,
+            Property::Scale => 1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Property::Position => "position",
+            Property::Angles => "angle",
+            Property::Scale => "scale",
+            Property::Color => "color",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Property> {
+        Property::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// The property's current value on `object`, padded with zeros.
+    pub fn get(self, object: &Objects) -> [f32; 3] {
+        match self {
+            Property::Position => {
+                let p = object.position();
+                [p.x, p.y, p.z]
+            }
+            Property::Angles => {
+                let (x, y) = object.angles();
+                [x, y, 0.0]
+            }
+            Property::Scale => [object.scale_factor(), 0.0, 0.0],
+            Property::Color => object.color().map(|c| c as f32),
+        }
+    }
+
+    pub fn set(self, object: &mut Objects, value: [f32; 3]) {
+        match self {
+            Property::Position => object.set_position(Point3D::new(value[0], value[1], value[2])),
+            Property::Angles => object.set_angles(value[0], value[1]),
+            Property::Scale => object.set_scale_factor(value[0]),
+            Property::Color => object.set_color(value.map(|c| c.round().clamp(0.0, 255.0) as u8)),
+        }
+    }
+}
+
+/// A value at a point in time, and how to get from it to the next key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe{
+    /// Seconds from the start of the timeline.
+    pub time: f32,
+    pub value: [f32; 3],
+    pub easing: Easing,
+}
+
+/// Keys of one property, in time order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Track{
+    keys: Vec<Keyframe>,
+}
+
+impl Track{
+    pub fn keys(&self) -> &[Keyframe] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Time of the last key, or zero without keys.
+    pub fn end(&self) -> f32 {
+        self.keys.last().map_or(0.0, |k| k.time)
+    }
+
+    /// Adds `key`, replacing a key at the same time.
+    pub fn insert(&mut self, key: Keyframe) {
+        match self.keys.iter().position(|k| k.time > key.time - SAME_TIME) {
+            Some(i) if (self.keys[i].time - key.time).abs() < SAME_TIME => self.keys[i] = key,
+            Some(i) => self.keys.insert(i, key),
+            None => self.keys.push(key),
+        }
+    }
+
+    pub fn has_key(&self, time: f32) -> bool {
+        self.keys.iter().any(|k| (k.time - time).abs() < SAME_TIME)
+    }
+
+    /// Removes the key at `time`; false when there is none.
+    pub fn remove(&mut self, time: f32) -> bool {
+        let before = self.keys.len();
+        self.keys.retain(|k| (k.time - time).abs() >= SAME_TIME);
+        self.keys.len() != before
+    }
+
+    /// The value at `time`, held before the first key and after the last.
+    pub fn sample(&self, time: f32) -> Option<[f32; 3]> {
+        let next = self.keys.iter().position(|k| k.time > time);
+        match next {
+            _ if self.keys.is_empty() => None,
+            Some(0) => Some(self.keys[0].value),
+            None => self.keys.last().map(|k| k.value),
+            Some(i) => {
+                let (a, b) = (self.keys[i - 1], self.keys[i]);
+                let t = a.easing.apply((time - a.time) / (b.time - a.time));
+                Some([0, 1, 2].map(|c| a.value[c] + (b.value[c] - a.value[c]) * t))
+            }
+        }
+    }
+}
+
+/// The keyed properties of one object.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Animation{
+    pub position: Track,
+    pub angles: Track,
+    pub scale: Track,
+    pub color: Track,
+}
+
+impl Animation{
+    pub fn track(&self, property: Property) -> &Track {
+        match property {
+            Property::Position => &self.position,
+            Property::Angles => &self.angles,
+            Property::Scale => &self.scale,
+            Property::Color => &self.color,
+        }
+    }
+
+    pub fn track_mut(&mut self, property: Property) -> &mut Track {
+        match property {
+            Property::Position => &mut self.position,
+            Property::Angles => &mut self.angles,
+            Property::Scale => &mut self.scale,
+            Property::Color => &mut self.color,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        Property::ALL.iter().all(|&p| self.track(p).is_empty())
+    }
+
+    /// Whether any property has a key at `time`.
+    pub fn has_key(&self, time: f32) -> bool {
+        Property::ALL.iter().any(|&p| self.track(p).has_key(time))
+    }
+
+    /// Time of the last key of any property.
+    pub fn end(&self) -> f32 {
+        Property::ALL.iter().map(|&p| self.track(p).end()).fold(0.0, f32::max)
+    }
+}
+
+/// Keys every property of `object` at `time` with its current value.
+pub fn key_object(object: &mut Objects, time: f32, easing: Easing) {
+    for property in Property::ALL {
+        let value = property.get(object);
+        object.state_mut().animation.track_mut(property).insert(Keyframe{ time, value, easing });
+    }
+}
+
+/// Poses `object` as its keys have it at `time`. Angles and scale go first, as
+/// changing them can move the object's center.
+pub fn pose_object(object: &mut Objects, time: f32) {
+    for property in [Property::Angles, Property::Scale, Property::Position, Property::Color] {
+        if let Some(value) = object.state().animation.track(property).sample(time) {
+            property.set(object, value);
+        }
+    }
+}
+
+/// The playhead: where in the animation the viewer is, and whether it moves on its own.
+#[derive(Clone, Copy, Debug)]
+pub struct Timeline{
+    /// Seconds from the start.
+    pub time: f32,
+    pub playing: bool,
+    /// Whether playing past the end starts over instead of stopping.
+    pub looping: bool,
+    /// Easing of the keys added next.
+    pub easing: Easing,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline{ time: 0.0, playing: false, looping: true, easing: Easing::Linear }
+    }
+}
+
+impl Timeline{
+    /// Plays on by `dt` seconds of an animation `end` seconds long.
+    pub fn advance(&mut self, dt: f32, end: f32) {
+        if !self.playing {
+            return;
+        }
+        self.time += dt;
+        if self.time >= end {
+            if self.looping && end > 0.0 {
+                self.time %= end;
+            } else {
+                self.time = end;
+                self.playing = false;
+            }
+        }
+    }
+
+    /// Starts or pauses playback; playing from the end of a finished animation starts over.
+    pub fn toggle(&mut self, end: f32) {
+        self.playing = !self.playing;
+        if self.playing && self.time >= end {
+            self.time = 0.0;
+        }
+    }
+
+    /// Moves the playhead by `delta` seconds. It may go past the end, to add keys there.
+    pub fn scrub(&mut self, delta: f32) {
+        self.time = (self.time + delta).max(0.0);
+    }
+
+    /// Switches the easing of new keys to the next of `Easing::ALL`.
+    pub fn cycle_easing(&mut self) {
+        let current = Easing::ALL.iter().position(|&e| e == self.easing);
+        self.easing = Easing::ALL[current.map_or(0, |i| (i + 1) % Easing::ALL.len())];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, x: f32, easing: Easing) -> Keyframe {
+        Keyframe{ time, value: [x, 0.0, 0.0], easing }
+    }
+
+    #[test]
+    fn easings_run_from_zero_to_one() {
+        for easing in Easing::ALL.into_iter().chain([Easing::Bezier(0.2, -0.5, 0.8, 1.5)]) {
+            assert!(easing.apply(0.0).abs() < 1e-4, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-4, "{:?}", easing);
+        }
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-3);
+        assert!(Easing::EaseIn.apply(0.25) < 0.25 && Easing::EaseOut.apply(0.25) > 0.25);
+        assert_eq!(Easing::Step.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn easing_names_round_trip() {
+        for easing in Easing::ALL {
+            assert_eq!(Easing::parse(easing.name(), None), Some(easing));
+        }
+        assert_eq!(Easing::parse("bezier", Some([0.1, 0.2, 0.3, 0.4])), Some(Easing::Bezier(0.1, 0.2, 0.3, 0.4)));
+        assert_eq!(Easing::parse("bezier", Some([1.5, 0.0, 0.3, 1.0])), None);
+        assert_eq!(Easing::parse("bezier", None), None);
+    }
+
+    #[test]
+    fn track_keeps_keys_in_order_and_replaces_same_time() {
+        let mut track = Track::default();
+        track.insert(key(2.0, 20.0, Easing::Linear));
+        track.insert(key(0.0, 0.0, Easing::Linear));
+        track.insert(key(1.0, 10.0, Easing::Linear));
+        track.insert(key(1.0004, 11.0, Easing::Linear));
+        let times: Vec<f32> = track.keys().iter().map(|k| k.time).collect();
+        assert_eq!(times, [0.0, 1.0004, 2.0]);
+        assert!(track.remove(1.0));
+        assert!(!track.remove(1.0));
+        assert_eq!(track.end(), 2.0);
+    }
+
+    #[test]
+    fn track_samples_between_and_beyond_keys() {
+        let mut track = Track::default();
+        assert_eq!(track.sample(1.0), None);
+        track.insert(key(1.0, 0.0, Easing::Linear));
+        track.insert(key(3.0, 10.0, Easing::Step));
+        assert_eq!(track.sample(0.0).unwrap()[0], 0.0);
+        assert_eq!(track.sample(2.0).unwrap()[0], 5.0);
+        assert_eq!(track.sample(9.0).unwrap()[0], 10.0);
+    }
+
+    #[test]
+    fn timeline_loops_or_stops_at_the_end() {
+        let mut timeline = Timeline{ playing: true, ..Timeline::default() };
+        timeline.advance(2.5, 2.0);
+        assert!((timeline.time - 0.5).abs() < 1e-6 && timeline.playing);
+
+        timeline.looping = false;
+        timeline.advance(5.0, 2.0);
+        assert_eq!((timeline.time, timeline.playing), (2.0, false));
+        timeline.toggle(2.0);
+        assert_eq!((timeline.time, timeline.playing), (0.0, true));
+    }
+}
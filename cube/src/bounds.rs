@@ -0,0 +1,386 @@
+use crate::camera::Camera;
+use crate::geometry::*;
+use crate::objec::Objects;
+use crate::operations::*;
+
+/// Closest depth the camera still draws; matches the clamp in `project`.
+pub const NEAR: f32 = 0.01;
+
+/// World-space axis-aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb{
+    pub min: Point3D,
+    pub max: Point3D,
+}
+
+impl Aabb{
+    pub fn new(min: Point3D, max: Point3D) -> Self{
+        Aabb{ min, max }
+    }
+
+    /// Smallest box around `points`, or `None` when there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3D>) -> Option<Self>{
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Aabb::new(first, first), |b, p| Aabb::new(
+            Point3D::new(b.min.x.min(p.x), b.min.y.min(p.y), b.min.z.min(p.z)),
+            Point3D::new(b.max.x.max(p.x), b.max.y.max(p.y), b.max.z.max(p.z)),
+        )))
+    }
+
+    pub fn center(&self) -> Point3D{
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Point3D{
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn contains(&self, p: Point3D) -> bool{
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool{
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+            && self.min.z <= other.max.z && other.min.z <= self.max.z
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb{
+        Aabb::from_points([self.min, self.max, other.min, other.max]).unwrap_or(*self)
+    }
+
+    /// The same box as an `Obb` along the world axes.
+    pub fn obb(&self) -> Obb{
+        let half = self.half_extents();
+        Obb{
+            center: self.center(),
+            axes: [Point3D::new(1.0, 0.0, 0.0), Point3D::new(0.0, 1.0, 0.0), Point3D::new(0.0, 0.0, 1.0)],
+            half: [half.x, half.y, half.z],
+        }
+    }
+
+    /// Corners, numbered so that bit 0 picks max x, bit 1 max y and bit 2 max z.
+    pub fn corners(&self) -> [Point3D; 8]{
+        let (a, b) = (self.min, self.max);
+        std::array::from_fn(|i| Point3D::new(
+            if i & 1 == 0 { a.x } else { b.x },
+            if i & 2 == 0 { a.y } else { b.y },
+            if i & 4 == 0 { a.z } else { b.z },
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingSphere{
+    pub center: Point3D,
+    pub radius: f32,
+}
+
+impl BoundingSphere{
+    pub fn contains(&self, p: Point3D) -> bool{
+        let d = p - self.center;
+        d.dot(d) <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &BoundingSphere) -> bool{
+        let d = other.center - self.center;
+        let reach = self.radius + other.radius;
+        d.dot(d) <= reach * reach
+    }
+}
+
+/// Box that turns with its object: the object's local extents in world space.
+#[derive(Clone, Copy, Debug)]
+pub struct Obb{
+    pub center: Point3D,
+    /// Unit axes of the object's rotated frame.
+    pub axes: [Point3D; 3],
+    /// Half the box size along each of `axes`.
+    pub half: [f32; 3],
+}
+
+impl Obb{
+    pub fn corners(&self) -> [Point3D; 8]{
+        std::array::from_fn(|i| {
+            (0..3).fold(self.center, |p, k| {
+                let sign = if i & (1 << k) == 0 { -1.0 } else { 1.0 };
+                p + self.axes[k] * (sign * self.half[k])
+            })
+        })
+    }
+
+    pub fn aabb(&self) -> Aabb{
+        let reach = |pick: fn(Point3D) -> f32| (0..3).map(|k| pick(self.axes[k]).abs() * self.half[k]).sum::<f32>();
+        let extent = Point3D::new(reach(|p| p.x), reach(|p| p.y), reach(|p| p.z));
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere{
+        let [x, y, z] = self.half;
+        BoundingSphere{ center: self.center, radius: (x * x + y * y + z * z).sqrt() }
+    }
+}
+
+impl Objects{
+    /// The object's local extents, turned and placed the way it is rendered.
+    pub fn obb(&self) -> Obb{
+        match self {
+            Objects::Cube(o) => {
+                let side = (o.e1.x - o.e2.x).abs();
+                let pivot = o.e1 + Point3D::new(side / 2.0, side / 2.0, side / 2.0);
+                let far = Point3D::new(o.e2.x, o.e2.y, o.e1.z + side);
+                pivoted_box(&[o.e1, far], pivot, o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Plane(o) => {
+                let side = (o.e1.x - o.e2.x).abs();
+                let pivot = o.e1 + Point3D::new(side / 2.0, side / 2.0, 0.0);
+                pivoted_box(&[o.e1, Point3D::new(o.e2.x, o.e2.y, o.e1.z)], pivot, o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Triangle(o) => {
+                let pivot = (o.e1 + o.e2 + o.e3) * (1.0 / 3.0);
+                pivoted_box(&[o.e1, o.e2, o.e3], pivot, o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Pyramid(o) => {
+                let side = (o.e1.x - o.e2.x).abs();
+                let pivot = o.e1 + Point3D::new(side / 2.0, side / 2.0, 0.0);
+                pivoted_box(&[o.e1, Point3D::new(o.e2.x, o.e2.y, o.e1.z), o.e3], pivot, o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Sphere(o) => centered_box(o.center, Point3D::new(o.radius, o.radius, o.radius), o.angle_x, o.angle_y, o.scale),
+            Objects::Mesh(o) => {
+                let local = Aabb::from_points(o.vertices.iter().copied())
+                    .unwrap_or(Aabb::new(Point3D::new(0.0, 0.0, 0.0), Point3D::new(0.0, 0.0, 0.0)));
+                rotated_box(local, o.center, o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Cylinder(o) => {
+                centered_box(o.center, Point3D::new(o.radius, o.height / 2.0, o.radius), o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Cone(o) => {
+                centered_box(o.center, Point3D::new(o.radius, o.height / 2.0, o.radius), o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Torus(o) => {
+                let reach = o.major_radius + o.minor_radius;
+                centered_box(o.center, Point3D::new(reach, o.minor_radius, reach), o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Capsule(o) => {
+                centered_box(o.center, Point3D::new(o.radius, o.height / 2.0 + o.radius, o.radius), o.angle_x, o.angle_y, o.scale)
+            }
+            Objects::Disk(o) => centered_box(o.center, Point3D::new(o.radius, 0.0, o.radius), o.angle_x, o.angle_y, o.scale),
+            // As far as any particle gets, whichever way it flies.
+            Objects::Emitter(o) => {
+                let reach = o.reach();
+                centered_box(o.center, Point3D::new(reach, reach, reach), o.angle_x, o.angle_y, o.scale)
+            }
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb{
+        match self {
+            Objects::Sphere(_) => {
+                let sphere = self.bounding_sphere();
+                let r = Point3D::new(sphere.radius, sphere.radius, sphere.radius);
+                Aabb::new(sphere.center - r, sphere.center + r)
+            }
+            _ => self.obb().aabb(),
+        }
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere{
+        match self {
+            Objects::Sphere(o) => BoundingSphere{
+                center: rotate_and_translate(o.center, o.angle_x, o.angle_y, o.scale, o.center),
+                radius: o.radius * o.scale.abs(),
+            },
+            Objects::Mesh(o) => BoundingSphere{
+                center: o.center,
+                radius: o.vertices.iter().map(|v| v.dot(*v)).fold(0.0, f32::max).sqrt() * o.scale.abs(),
+            },
+            _ => self.obb().bounding_sphere(),
+        }
+    }
+}
+
+/// Box around points of an object drawn with `rotate_and_translate` about `pivot`.
+fn pivoted_box(points: &[Point3D], pivot: Point3D, angle_x: f32, angle_y: f32, scale: f32) -> Obb{
+    let local = Aabb::from_points(points.iter().map(|&p| p - pivot)).unwrap_or(Aabb::new(pivot, pivot));
+    rotated_box(local, rotate_and_translate(pivot, angle_x, angle_y, scale, pivot), angle_x, angle_y, scale)
+}
+
+/// Box of half size `half` around the pivot of a `rotate_and_translate` object.
+fn centered_box(pivot: Point3D, half: Point3D, angle_x: f32, angle_y: f32, scale: f32) -> Obb{
+    pivoted_box(&[pivot - half, pivot + half], pivot, angle_x, angle_y, scale)
+}
+
+/// Places `local` (relative to the object's origin) at `origin`, turned and scaled.
+fn rotated_box(local: Aabb, origin: Point3D, angle_x: f32, angle_y: f32, scale: f32) -> Obb{
+    let zero = Point3D::new(0.0, 0.0, 0.0);
+    let rotate = |p: Point3D| rotate_x(rotate_y(p, angle_y, zero), angle_x, zero);
+    let half = local.half_extents() * scale.abs();
+    Obb{
+        center: origin + rotate(local.center()) * scale,
+        axes: [rotate(Point3D::new(1.0, 0.0, 0.0)), rotate(Point3D::new(0.0, 1.0, 0.0)), rotate(Point3D::new(0.0, 0.0, 1.0))],
+        half: [half.x, half.y, half.z],
+    }
+}
+
+/// The part of space a camera shows on a `width`x`height` frame, past `NEAR`.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum{
+    camera: Camera,
+    /// Outward normals of the four side planes, in camera space.
+    sides: [Point3D; 4],
+}
+
+impl Frustum{
+    pub fn new(camera: &Camera, width: u32, height: u32) -> Self{
+        let tan_x = (camera.fov.to_radians() / 2.0).tan();
+        let tan_y = tan_x * height as f32 / width.max(1) as f32;
+        Frustum{
+            camera: *camera,
+            sides: [
+                Point3D::new(1.0, 0.0, -tan_x).normalize(),
+                Point3D::new(-1.0, 0.0, -tan_x).normalize(),
+                Point3D::new(0.0, 1.0, -tan_y).normalize(),
+                Point3D::new(0.0, -1.0, -tan_y).normalize(),
+            ],
+        }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool{
+        let c = self.camera.to_view(sphere.center);
+        c.z + sphere.radius >= NEAR && self.sides.iter().all(|n| n.dot(c) <= sphere.radius)
+    }
+
+    /// False only when every corner lies outside the same plane, so a few boxes
+    /// near the frustum's edges pass although they are not visible.
+    pub fn intersects_obb(&self, obb: &Obb) -> bool{
+        let corners = obb.corners().map(|p| self.camera.to_view(p));
+        corners.iter().any(|c| c.z >= NEAR)
+            && self.sides.iter().all(|n| corners.iter().any(|&c| n.dot(c) <= 0.0))
+    }
+
+    /// Whether any part of `object` may show up on screen.
+    pub fn sees(&self, object: &Objects) -> bool{
+        self.intersects_sphere(&object.bounding_sphere()) && self.intersects_obb(&object.obb())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::objec::{Cube, Plane, Pyramid, Sphere, Tessellation, Triangle};
+    use crate::particles::Emitter;
+    use crate::primitives::{Capsule, Cone, Cylinder, Disk, Torus};
+
+    const EPS: f32 = 1e-3;
+
+    fn p(x: f32, y: f32, z: f32) -> Point3D {
+        Point3D::new(x, y, z)
+    }
+
+    /// One of each kind of object, turned and scaled away from the defaults.
+    fn objects() -> Vec<Objects> {
+        let c = [200, 100, 50];
+        let center = p(1.0, -0.5, 6.0);
+        let mut emitter = Emitter::new(center, 40.0, 1.5, 2.0, c);
+        emitter.time = 3.0;
+        emitter.spread = 1.2;
+        let mut objects = vec![
+            Objects::Cube(Cube::new(p(0.0, 0.0, 5.0), p(1.0, 1.0, 5.0), c)),
+            Objects::Plane(Plane::new(p(-1.0, 0.0, 4.0), p(1.0, 2.0, 4.0), c)),
+            Objects::Triangle(Triangle::new(p(0.0, 0.0, 5.0), p(2.0, 0.5, 5.5), p(0.5, 2.0, 4.0), c)),
+            Objects::Pyramid(Pyramid::new(p(0.0, 0.0, 5.0), p(1.0, 1.0, 5.0), p(0.5, 0.5, 6.5), c)),
+            Objects::Sphere(Sphere::new(center, 1.2, 8, 12, c).with_tessellation(Tessellation::Uv)),
+            Objects::Sphere(Sphere::new(center, 0.7, 8, 12, c).with_tessellation(Tessellation::Icosphere(2))),
+            Objects::Mesh(Mesh::new(
+                vec![p(0.0, 0.0, 4.0), p(3.0, 0.0, 4.5), p(0.0, 1.0, 5.0), p(0.5, 0.5, 7.0)],
+                vec![[0, 1, 2], [0, 1, 3], [1, 2, 3], [0, 2, 3]],
+                c,
+            )),
+            Objects::Cylinder(Cylinder::new(center, 0.5, 2.0, 16, c)),
+            Objects::Cone(Cone::new(center, 0.8, 1.5, 16, c)),
+            Objects::Torus(Torus::new(center, 1.0, 0.3, 16, 8, c)),
+            Objects::Capsule(Capsule::new(center, 0.4, 1.0, 12, 4, c)),
+            Objects::Disk(Disk::new(center, 1.0, 0.3, 16, c)),
+            Objects::Emitter(emitter),
+        ];
+        for object in &mut objects {
+            object.set_angles(0.7, -1.1);
+            object.set_scale_factor(1.5);
+        }
+        objects
+    }
+
+    /// What the object draws: its triangles' corners, or an emitter's particles.
+    fn drawn_points(object: &Objects) -> Vec<Point3D> {
+        match object {
+            Objects::Emitter(o) => o.particles().iter().map(|q| q.position).collect(),
+            _ => object.triangles().iter().flat_map(|t| [t.p1, t.p2, t.p3]).collect(),
+        }
+    }
+
+    fn in_obb(obb: &Obb, q: Point3D) -> bool {
+        let d = q - obb.center;
+        (0..3).all(|k| d.dot(obb.axes[k]).abs() <= obb.half[k] + EPS)
+    }
+
+    fn in_aabb(aabb: &Aabb, q: Point3D) -> bool {
+        let grown = Aabb::new(aabb.min - p(EPS, EPS, EPS), aabb.max + p(EPS, EPS, EPS));
+        grown.contains(q)
+    }
+
+    #[test]
+    fn bounds_hold_everything_each_object_draws() {
+        for object in objects() {
+            let points = drawn_points(&object);
+            assert!(!points.is_empty(), "{} draws nothing", object.kind());
+            let (obb, aabb, sphere) = (object.obb(), object.aabb(), object.bounding_sphere());
+            let sphere = BoundingSphere{ center: sphere.center, radius: sphere.radius + EPS };
+            for q in points {
+                assert!(in_obb(&obb, q), "{} point {:?} outside its OBB {:?}", object.kind(), q, obb);
+                assert!(in_aabb(&aabb, q), "{} point {:?} outside its AABB {:?}", object.kind(), q, aabb);
+                assert!(sphere.contains(q), "{} point {:?} outside its bounding sphere", object.kind(), q);
+            }
+        }
+    }
+
+    #[test]
+    fn emitter_particles_stay_within_reach() {
+        let Some(Objects::Emitter(emitter)) = objects().pop() else { panic!("last object is not an emitter") };
+        let reach = emitter.reach() * emitter.scale.abs() + EPS;
+        for particle in emitter.particles() {
+            let d = particle.position - emitter.origin();
+            assert!(d.dot(d).sqrt() <= reach, "particle {:?} beyond reach {}", particle.position, reach);
+        }
+    }
+
+    #[test]
+    fn frustum_sees_objects_in_front_only() {
+        let frustum = Frustum::new(&Camera::default(), 320, 240);
+        let at = |x: f32, z: f32| Objects::Sphere(Sphere::new(p(x, 0.0, z), 0.5, 8, 12, [255, 255, 255]));
+
+        assert!(frustum.sees(&at(0.0, 5.0)));
+        assert!(!frustum.sees(&at(0.0, -5.0)), "object behind the camera is drawn");
+        assert!(!frustum.sees(&at(50.0, 5.0)), "object off to the right is drawn");
+        assert!(!frustum.sees(&at(-50.0, 5.0)), "object off to the left is drawn");
+        // Poking into the side of the view, its center outside it.
+        assert!(frustum.sees(&at(5.3, 5.0)));
+
+        for object in objects() {
+            assert!(frustum.sees(&object), "{} in front of the camera is culled", object.kind());
+        }
+    }
+
+    #[test]
+    fn frustum_follows_the_camera() {
+        let behind = Objects::Cube(Cube::new(p(0.0, 0.0, -5.0), p(1.0, 1.0, -5.0), [255, 255, 255]));
+        assert!(!Frustum::new(&Camera::default(), 320, 240).sees(&behind));
+        let turned = Camera::new(p(0.0, 0.0, 0.0), 0.0, std::f32::consts::PI, 90.0);
+        assert!(Frustum::new(&turned, 320, 240).sees(&behind));
+        let above = Objects::Cube(Cube::new(p(0.0, -50.0, -5.0), p(1.0, -49.0, -5.0), [255, 255, 255]));
+        assert!(!Frustum::new(&turned, 320, 240).sees(&above), "object above a wide, short view is drawn");
+    }
+}
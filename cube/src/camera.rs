@@ -0,0 +1,61 @@
+use crate::geometry::*;
+use crate::operations::*;
+use crate::picking::Ray;
+
+/// Viewpoint the scene is rendered from. The default sits at the origin looking down +Z.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera{
+    pub position: Point3D,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    /// Horizontal field of view in degrees.
+    pub fov: f32,
+}
+
+impl Camera{
+    pub fn new(position: Point3D, angle_x: f32, angle_y: f32, fov: f32) -> Self{
+        Camera{
+            position,
+            angle_x,
+            angle_y,
+            fov,
+        }
+    }
+
+    /// Turns the camera to face `target`; a target at the camera's own position leaves it as it is.
+    pub fn look_at(&mut self, target: Point3D) {
+        let d = target - self.position;
+        let length = (d.x * d.x + d.y * d.y + d.z * d.z).sqrt();
+        if length < 1e-6 {
+            return;
+        }
+        // Inverse of the direction `ray` turns +Z into.
+        self.angle_x = (-d.y / length).asin();
+        self.angle_y = (-d.x).atan2(d.z);
+    }
+
+    /// Moves a world-space point into camera space, where the camera looks down +Z.
+    pub fn to_view(&self, p: Point3D) -> Point3D {
+        let origin = Point3D::new(0.0, 0.0, 0.0);
+        rotate_x(rotate_y(p - self.position, -self.angle_y, origin), -self.angle_x, origin)
+    }
+
+    pub fn project(&self, p: Point3D, width: u32, height: u32) -> Point {
+        project(self.to_view(p), self.fov, width, height)
+    }
+
+    /// Ray from the camera through pixel (`x`, `y`) of a `width`x`height` frame;
+    /// the inverse of `project`.
+    pub fn ray(&self, x: f32, y: f32, width: u32, height: u32) -> Ray {
+        let origin = Point3D::new(0.0, 0.0, 0.0);
+        let focal = (width as f32 / 2.0) / (self.fov.to_radians() / 2.0).tan();
+        let direction = Point3D::new((x - width as f32 / 2.0) / focal, (y - height as f32 / 2.0) / focal, 1.0);
+        Ray::new(self.position, rotate_y(rotate_x(direction, self.angle_x, origin), self.angle_y, origin))
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(Point3D::new(0.0, 0.0, 0.0), 0.0, 0.0, 90.0)
+    }
+}
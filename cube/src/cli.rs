@@ -0,0 +1,302 @@
+use std::path::Path;
+
+use cube::config::{self, Config};
+use cube::drawing::RenderMode;
+use cube::geometry::Point3D;
+use cube::raytrace::Tracer;
+use cube::recording::DEFAULT_FPS;
+use cube::terminal::Palette;
+use cube::{HEIGHT, WIDTH};
+
+pub const USAGE: &str = "\
+usage: cube [options] [files...]
+       cube render <scene> -o <image> [options]
+       cube record <scene> -o <output> [--turntable | --path <file>] [options]
+
+Files ending in .scene are opened as the scene to edit and save; .gltf/.glb,
+.stl, .ply and .off files are imported into it.
+
+`record` renders the scene's animation frame by frame, optionally orbiting the
+scene or following a camera path, into a .gif, a .y4m video or numbered .png/.ppm
+files (a run of # in the name marks the digits, e.g. frames/shot_####.png).
+
+options:
+  --width <px>          window or image width (default 640)
+  --height <px>         window or image height (default 480)
+  --title <text>        window title
+  --terminal            view in this terminal instead of a window, e.g. over SSH,
+                        with 24-bit color when COLORTERM says the terminal has it,
+                        else ASCII; the controls are the same, quit with Ctrl+Q
+  --ascii               like --terminal, but always in ASCII
+  --scene <file>        scene file to open and save
+  --fov <degrees>       horizontal field of view, overrides the scene camera
+  --mode <mode>         shaded, flat or wireframe (default shaded)
+  --raytrace            ray trace instead of rasterizing, with shadows, reflection
+                        and refraction (toggle in the viewer with F10)
+  --samples <n>         rays per pixel along each side when ray tracing (default 2)
+  --bounces <n>         reflections and refractions followed per ray (default 5)
+  --light <x,y,z>       light position, overrides the scene light
+  --move-speed <units>  distance a held key moves per second (default 0.6)
+  --turn-speed <degrees> angle a held key turns per second (default 70)
+  --scale-speed <amount> scale change per second a key is held (default 0.3)
+  --grid <units>        grid spacing to snap positions to (default 0.25)
+  --snap-angle <degrees> increment to snap angles to (default 15)
+  --snap                start with snapping on (toggle with X)
+  --config <file>       settings and key bindings (default cube.conf, if present)
+  --time <seconds>      point of the scene's animation to render, open or record from
+  --turntable           record one orbit around all objects
+  --orbit <index>       record one orbit around this object
+  --path <file>         record following a camera path of `point time=<s>
+                        position=x,y,z target=x,y,z` lines
+  --fps <n>             frames per second of a recording (default 30)
+  --frames <n>          frames to record (default: the path's or the animation's
+                        length, else 4 seconds)
+  -o, --output <file>   image to write with `render` (.png or .ppm), or the
+                        recording to write with `record`
+  -h, --help            show this help";
+
+pub struct Options{
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+    pub scene: Option<String>,
+    pub files: Vec<String>,
+    pub fov: Option<f32>,
+    pub mode: RenderMode,
+    /// How to draw the viewer in the terminal, instead of opening a window.
+    pub terminal: Option<Palette>,
+    /// Ray tracing settings, when ray tracing instead of rasterizing.
+    pub tracer: Option<Tracer>,
+    pub light: Option<Point3D>,
+    /// Animation time to pose the scene at.
+    pub time: Option<f32>,
+    pub output: Option<String>,
+    pub shot: ShotOption,
+    pub fps: u32,
+    pub frames: Option<u32>,
+    /// Settings from the settings file, with the speed and snap options applied.
+    pub config: Config,
+}
+
+/// Camera motion asked for with `record`.
+pub enum ShotOption{
+    Still,
+    /// Orbit all objects, or the one at this index.
+    Turntable(Option<usize>),
+    /// Follow the camera path in this file.
+    Path(String),
+}
+
+pub enum Command{
+    /// Open the interactive viewer.
+    View(Options),
+    /// Render the scene to `output` without a window.
+    Render(Options),
+    /// Render the scene's frames over time to `output` without a window.
+    Record(Options),
+    Help,
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let args: Vec<String> = args.into_iter().collect();
+    // The settings file comes first, so the options below can override it.
+    let config = match args.iter().position(|a| a == "--config") {
+        Some(i) => {
+            let path = args.get(i + 1).ok_or("--config needs a value")?;
+            Config::load(path).map_err(|err| format!("{}: {}", path, err))?
+        }
+        None if Path::new(config::DEFAULT_PATH).exists() => {
+            Config::load(config::DEFAULT_PATH).map_err(|err| format!("{}: {}", config::DEFAULT_PATH, err))?
+        }
+        None => Config::default(),
+    };
+
+    let mut args = args.into_iter().peekable();
+    let subcommand = args.next_if(|a| a == "render" || a == "record");
+
+    let mut options = Options{
+        width: WIDTH,
+        height: HEIGHT,
+        title: String::from("3D Rendering"),
+        scene: None,
+        files: Vec::new(),
+        fov: None,
+        mode: RenderMode::Shaded,
+        terminal: None,
+        tracer: None,
+        light: None,
+        time: None,
+        output: None,
+        shot: ShotOption::Still,
+        fps: DEFAULT_FPS,
+        frames: None,
+        config,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--width" => options.width = parse_size(&value("--width")?)?,
+            "--height" => options.height = parse_size(&value("--height")?)?,
+            "--title" => options.title = value("--title")?,
+            "--terminal" => { options.terminal.get_or_insert_with(Palette::detect); }
+            "--ascii" => options.terminal = Some(Palette::Ascii),
+            "--scene" => options.scene = Some(value("--scene")?),
+            "--fov" => {
+                let fov = value("--fov")?;
+                match fov.parse::<f32>() {
+                    Ok(f) if f > 0.0 && f < 180.0 => options.fov = Some(f),
+                    _ => return Err(format!("bad field of view '{}', expected degrees between 0 and 180", fov)),
+                }
+            }
+            "--mode" => {
+                let mode = value("--mode")?;
+                options.mode = RenderMode::parse(&mode).ok_or_else(|| format!("unknown render mode '{}'", mode))?;
+            }
+            "--raytrace" => { options.tracer.get_or_insert_with(Tracer::default); }
+            "--samples" => options.tracer.get_or_insert_with(Tracer::default).samples = parse_count("--samples", &value("--samples")?, 16)?,
+            "--bounces" => {
+                let bounces = value("--bounces")?;
+                options.tracer.get_or_insert_with(Tracer::default).bounces = match bounces.parse::<u32>() {
+                    Ok(n) if n <= 32 => n,
+                    _ => return Err(format!("bad --bounces '{}', expected 0-32", bounces)),
+                };
+            }
+            "--light" => {
+                let light = value("--light")?;
+                let coords = light.split(',').map(|c| c.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>();
+                match coords.as_deref() {
+                    Ok(&[x, y, z]) if [x, y, z].iter().all(|c| c.is_finite()) => options.light = Some(Point3D::new(x, y, z)),
+                    _ => return Err(format!("bad --light '{}', expected x,y,z", light)),
+                }
+            }
+            "--move-speed" => options.config.speeds.translate = parse_step("--move-speed", &value("--move-speed")?)?,
+            "--turn-speed" => options.config.speeds.rotate = parse_step("--turn-speed", &value("--turn-speed")?)?.to_radians(),
+            "--scale-speed" => options.config.speeds.scale = parse_step("--scale-speed", &value("--scale-speed")?)?,
+            "--grid" => options.config.snap.grid = parse_step("--grid", &value("--grid")?)?,
+            "--snap-angle" => options.config.snap.angle = parse_step("--snap-angle", &value("--snap-angle")?)?.to_radians(),
+            "--snap" => options.config.snap.enabled = true,
+            "--config" => { value("--config")?; }
+            "--time" => {
+                let time = value("--time")?;
+                match time.parse::<f32>() {
+                    Ok(t) if t >= 0.0 && t.is_finite() => options.time = Some(t),
+                    _ => return Err(format!("bad time '{}', expected seconds from 0", time)),
+                }
+            }
+            "--turntable" | "--orbit" | "--path" if !matches!(options.shot, ShotOption::Still) => {
+                return Err("only one of --turntable, --orbit and --path can be given".into());
+            }
+            "--turntable" => options.shot = ShotOption::Turntable(None),
+            "--orbit" => {
+                let index = value("--orbit")?;
+                let index = index.parse::<usize>().map_err(|_| format!("bad object index '{}'", index))?;
+                options.shot = ShotOption::Turntable(Some(index));
+            }
+            "--path" => options.shot = ShotOption::Path(value("--path")?),
+            "--fps" => options.fps = parse_count("--fps", &value("--fps")?, 1000)?,
+            "--frames" => options.frames = Some(parse_count("--frames", &value("--frames")?, 100_000)?),
+            "-o" | "--output" => options.output = Some(value("--output")?),
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            _ if arg.ends_with(".scene") && options.scene.is_none() => options.scene = Some(arg),
+            _ => options.files.push(arg),
+        }
+    }
+
+    let Some(subcommand) = subcommand else {
+        return Ok(Command::View(options));
+    };
+    if options.scene.is_none() && options.files.is_empty() {
+        return Err(format!("{} needs a scene file", subcommand));
+    }
+    if options.output.is_none() {
+        return Err(format!("{} needs an output file (-o)", subcommand));
+    }
+    match subcommand.as_str() {
+        "render" => Ok(Command::Render(options)),
+        _ => Ok(Command::Record(options)),
+    }
+}
+
+fn parse_count(name: &str, text: &str, max: u32) -> Result<u32, String> {
+    match text.parse::<u32>() {
+        Ok(n) if (1..=max).contains(&n) => Ok(n),
+        _ => Err(format!("bad {} '{}', expected 1-{}", name, text, max)),
+    }
+}
+
+fn parse_step(name: &str, text: &str) -> Result<f32, String> {
+    match text.parse::<f32>() {
+        Ok(n) if n > 0.0 && n.is_finite() => Ok(n),
+        _ => Err(format!("bad {} '{}', expected a positive number", name, text)),
+    }
+}
+
+fn parse_size(text: &str) -> Result<u32, String> {
+    match text.parse::<u32>() {
+        Ok(n) if (1..=8192).contains(&n) => Ok(n),
+        _ => Err(format!("bad size '{}', expected 1-8192 pixels", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &str) -> Result<Command, String> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    fn options(args: &str) -> Options {
+        match run(args) {
+            Ok(Command::View(options) | Command::Render(options) | Command::Record(options)) => options,
+            Ok(Command::Help) => panic!("{:?} asked for help", args),
+            Err(err) => panic!("{:?} failed: {}", args, err),
+        }
+    }
+
+    #[test]
+    fn render_takes_scene_output_and_overrides() {
+        let Ok(Command::Render(options)) = run("render shot.scene -o out.png --width 320 --fov 60 --light 1,-2,3.5") else {
+            panic!("expected a render command");
+        };
+        assert_eq!(options.scene.as_deref(), Some("shot.scene"));
+        assert_eq!(options.output.as_deref(), Some("out.png"));
+        assert_eq!(options.width, 320);
+        assert_eq!(options.fov, Some(60.0));
+        let light = options.light.unwrap();
+        assert_eq!((light.x, light.y, light.z), (1.0, -2.0, 3.5));
+    }
+
+    #[test]
+    fn view_sorts_scene_from_imports() {
+        let options = options("model.stl edit.scene other.scene part.glb");
+        assert_eq!(options.scene.as_deref(), Some("edit.scene"));
+        assert_eq!(options.files, ["model.stl", "other.scene", "part.glb"]);
+    }
+
+    #[test]
+    fn record_shots() {
+        assert!(matches!(options("record a.scene -o a.gif --orbit 2").shot, ShotOption::Turntable(Some(2))));
+        assert!(matches!(options("record a.scene -o a.gif --path cam.txt").shot, ShotOption::Path(_)));
+        assert!(run("record a.scene -o a.gif --turntable --orbit 1").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        for args in [
+            "--light 1,abc,2,3", "--light 1,2", "--light 1,2,3,4", "--light 1,NaN,2", "--light",
+            "--width 0", "--width 9000", "--fov 180", "--mode glossy", "--bounces 33",
+            "--time -1", "--frames 0", "--frobnicate", "render a.scene", "render -o out.png",
+        ] {
+            assert!(run(args).is_err(), "{:?} should fail", args);
+        }
+        assert_eq!(run("--light 1,abc,2,3").err().unwrap(), "bad --light '1,abc,2,3', expected x,y,z");
+    }
+
+    #[test]
+    fn help_wins() {
+        assert!(matches!(run("render --help"), Ok(Command::Help)));
+    }
+}
@@ -0,0 +1,543 @@
+use crate::bounds::{Aabb, BoundingSphere, Obb};
+use crate::geometry::*;
+use crate::objec::Objects;
+use crate::picking::Ray;
+
+/// Lengths below this count as zero.
+const EPSILON: f32 = 1e-6;
+
+/// What geometric queries run against, in world space.
+#[derive(Clone, Debug)]
+pub enum Volume {
+    Sphere(BoundingSphere),
+    Aabb(Aabb),
+    Obb(Obb),
+    /// A triangle surface: shapes wholly inside a closed mesh do not touch it.
+    Mesh(Vec<Triangle3D>),
+}
+
+/// Where a ray first meets a volume.
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    /// Along the ray, in lengths of its direction; 0 when it starts inside a solid.
+    pub distance: f32,
+    pub point: Point3D,
+    /// Unit surface normal, facing back along the ray.
+    pub normal: Point3D,
+}
+
+/// How the first of two overlapping volumes has to move to stop overlapping:
+/// `depth` along the unit `normal`, which points away from the second.
+#[derive(Clone, Copy, Debug)]
+pub struct Penetration {
+    pub normal: Point3D,
+    pub depth: f32,
+}
+
+impl Volume {
+    /// How `object` is queried, with its rotation, scale and position applied:
+    /// spheres as spheres, cubes and planes as their boxes, and everything else
+    /// as the triangles it is drawn with.
+    pub fn of(object: &Objects) -> Volume {
+        match object {
+            Objects::Sphere(_) => Volume::Sphere(object.bounding_sphere()),
+            Objects::Cube(_) | Objects::Plane(_) => Volume::Obb(object.obb()),
+            _ => Volume::Mesh(object.triangles()),
+        }
+    }
+
+    /// Point of the volume nearest `p`: `p` itself inside a solid, or on the
+    /// surface of a mesh. `None` for a mesh without triangles.
+    pub fn closest_point(&self, p: Point3D) -> Option<Point3D> {
+        self.pieces().iter()
+            .map(|piece| piece.closest_point(p))
+            .min_by(|a, b| (*a - p).dot(*a - p).total_cmp(&(*b - p).dot(*b - p)))
+    }
+
+    /// How far `p` is from the volume; 0 inside a solid.
+    pub fn distance(&self, p: Point3D) -> Option<f32> {
+        self.closest_point(p).map(|q| (q - p).dot(q - p).sqrt())
+    }
+
+    fn pieces(&self) -> Vec<Piece> {
+        match self {
+            Volume::Sphere(s) => vec![Piece::Sphere(*s)],
+            Volume::Aabb(b) => vec![Piece::Box(b.obb())],
+            Volume::Obb(b) => vec![Piece::Box(*b)],
+            Volume::Mesh(triangles) => triangles.iter().map(|t| Piece::Triangle([t.p1, t.p2, t.p3])).collect(),
+        }
+    }
+}
+
+/// First place `ray` meets `volume`.
+pub fn raycast(ray: &Ray, volume: &Volume) -> Option<Hit> {
+    if ray.direction.dot(ray.direction) == 0.0 {
+        return None;
+    }
+    volume.pieces().iter()
+        .filter_map(|piece| piece.raycast(ray))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Index of the object nearest along `ray` and where it is hit.
+pub fn raycast_objects(ray: &Ray, objects: &[Objects]) -> Option<(usize, Hit)> {
+    objects.iter().enumerate()
+        .filter(|(_, object)| ray.hits_sphere(&object.bounding_sphere()).is_some())
+        .filter_map(|(i, object)| raycast(ray, &Volume::of(object)).map(|hit| (i, hit)))
+        .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance))
+}
+
+/// Whether the volumes touch or overlap.
+pub fn overlaps(a: &Volume, b: &Volume) -> bool {
+    candidates(a, b, penetration_of).next().is_some()
+}
+
+/// How deep the volumes overlap, or `None` when they do not. With a mesh, the
+/// deepest overlap of any one of its triangles.
+pub fn penetration(a: &Volume, b: &Volume) -> Option<Penetration> {
+    candidates(a, b, penetration_of).max_by(|p, q| p.depth.total_cmp(&q.depth))
+}
+
+/// Index pairs of the objects that touch or overlap, in scene order.
+pub fn overlapping_pairs(objects: &[Objects]) -> Vec<(usize, usize)> {
+    let spheres: Vec<BoundingSphere> = objects.iter().map(Objects::bounding_sphere).collect();
+    let volumes: Vec<Volume> = objects.iter().map(Volume::of).collect();
+    let mut pairs = Vec::new();
+    for i in 0..objects.len() {
+        for j in i + 1..objects.len() {
+            if spheres[i].intersects(&spheres[j]) && overlaps(&volumes[i], &volumes[j]) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Results of `test` for the pieces of `a` and `b` whose boxes meet.
+fn candidates<T>(a: &Volume, b: &Volume, test: impl Fn(&Piece, &Piece) -> Option<T>) -> impl Iterator<Item = T> {
+    let boxed = |volume: &Volume| volume.pieces().into_iter().map(|p| (p.aabb(), p)).collect::<Vec<_>>();
+    let (a, b) = (boxed(a), boxed(b));
+    (0..a.len() * b.len()).filter_map(move |n| {
+        let ((box_a, p), (box_b, q)) = (&a[n / b.len()], &b[n % b.len()]);
+        if box_a.intersects(box_b) { test(p, q) } else { None }
+    })
+}
+
+/// Convex part of a volume.
+#[derive(Clone, Copy, Debug)]
+enum Piece {
+    Sphere(BoundingSphere),
+    Box(Obb),
+    Triangle([Point3D; 3]),
+}
+
+impl Piece {
+    fn aabb(&self) -> Aabb {
+        match self {
+            Piece::Sphere(s) => {
+                let r = Point3D::new(s.radius, s.radius, s.radius);
+                Aabb::new(s.center - r, s.center + r)
+            }
+            Piece::Box(b) => b.aabb(),
+            Piece::Triangle([a, b, c]) => Aabb::from_points([*a, *b, *c]).unwrap_or(Aabb::new(*a, *a)),
+        }
+    }
+
+    fn closest_point(&self, p: Point3D) -> Point3D {
+        match self {
+            Piece::Sphere(s) => {
+                let d = p - s.center;
+                let distance = d.dot(d).sqrt();
+                if distance <= s.radius { p } else { s.center + d * (s.radius / distance) }
+            }
+            Piece::Box(b) => {
+                let d = p - b.center;
+                (0..3).fold(b.center, |q, k| q + b.axes[k] * d.dot(b.axes[k]).clamp(-b.half[k], b.half[k]))
+            }
+            Piece::Triangle(t) => closest_on_triangle(p, t),
+        }
+    }
+
+    fn raycast(&self, ray: &Ray) -> Option<Hit> {
+        let back = ray.direction.normalize() * -1.0;
+        match self {
+            Piece::Sphere(s) => {
+                let t = ray.hits_sphere(s)?;
+                let point = ray.at(t);
+                let out = point - s.center;
+                let normal = if t == 0.0 || out.dot(out) < EPSILON * EPSILON { back } else { out.normalize() };
+                Some(Hit{ distance: t, point, normal })
+            }
+            Piece::Box(b) => {
+                let from = ray.origin - b.center;
+                let (mut near, mut far) = (f32::NEG_INFINITY, f32::INFINITY);
+                let mut normal = back;
+                for k in 0..3 {
+                    let o = from.dot(b.axes[k]);
+                    let d = ray.direction.dot(b.axes[k]);
+                    if d.abs() < EPSILON {
+                        if o.abs() > b.half[k] {
+                            return None;
+                        }
+                        continue;
+                    }
+                    let (t1, t2) = ((-b.half[k] - o) / d, (b.half[k] - o) / d);
+                    if t1.min(t2) > near {
+                        near = t1.min(t2);
+                        normal = b.axes[k] * -d.signum();
+                    }
+                    far = far.min(t1.max(t2));
+                }
+                if near > far || far < 0.0 {
+                    return None;
+                }
+                if near <= 0.0 {
+                    return Some(Hit{ distance: 0.0, point: ray.origin, normal: back });
+                }
+                Some(Hit{ distance: near, point: ray.at(near), normal })
+            }
+            Piece::Triangle([a, b, c]) => {
+                let t = ray.hits_triangle(&Triangle3D::new(*a, *b, *c, [0, 0, 0]))?;
+                let face = (*b - *a).cross(*c - *a).normalize();
+                let normal = if face.dot(ray.direction) > 0.0 { face * -1.0 } else { face };
+                Some(Hit{ distance: t, point: ray.at(t), normal })
+            }
+        }
+    }
+
+    /// Corners of a box or triangle.
+    fn vertices(&self) -> Vec<Point3D> {
+        match self {
+            Piece::Sphere(s) => vec![s.center],
+            Piece::Box(b) => b.corners().to_vec(),
+            Piece::Triangle(t) => t.to_vec(),
+        }
+    }
+
+    /// Directions the piece is flat across: a box's face normals, or a triangle's
+    /// normal and the normals of its edges within its plane.
+    fn faces(&self) -> Vec<Point3D> {
+        match self {
+            Piece::Sphere(_) => Vec::new(),
+            Piece::Box(b) => b.axes.to_vec(),
+            Piece::Triangle(t) => {
+                let normal = (t[1] - t[0]).cross(t[2] - t[0]);
+                let mut faces = vec![normal];
+                faces.extend(self.edges().into_iter().map(|e| normal.cross(e)));
+                faces
+            }
+        }
+    }
+
+    fn edges(&self) -> Vec<Point3D> {
+        match self {
+            Piece::Sphere(_) => Vec::new(),
+            Piece::Box(b) => b.axes.to_vec(),
+            Piece::Triangle([a, b, c]) => vec![*b - *a, *c - *b, *a - *c],
+        }
+    }
+}
+
+fn penetration_of(a: &Piece, b: &Piece) -> Option<Penetration> {
+    match (a, b) {
+        (Piece::Sphere(a), Piece::Sphere(b)) => {
+            let d = a.center - b.center;
+            let distance = d.dot(d).sqrt();
+            let depth = a.radius + b.radius - distance;
+            // Concentric spheres part along any direction; up is as good as any.
+            let normal = if distance > EPSILON { d * (1.0 / distance) } else { Point3D::new(0.0, -1.0, 0.0) };
+            (depth >= 0.0).then_some(Penetration{ normal, depth })
+        }
+        (Piece::Sphere(s), _) => sphere_penetration(s, b),
+        (_, Piece::Sphere(s)) => sphere_penetration(s, a).map(|p| Penetration{ normal: p.normal * -1.0, ..p }),
+        _ => separating_axes(a, b),
+    }
+}
+
+/// Penetration of `sphere` into the box or triangle `piece`.
+fn sphere_penetration(sphere: &BoundingSphere, piece: &Piece) -> Option<Penetration> {
+    let closest = piece.closest_point(sphere.center);
+    let out = sphere.center - closest;
+    let distance = out.dot(out).sqrt();
+    if distance > EPSILON {
+        let depth = sphere.radius - distance;
+        return (depth >= 0.0).then(|| Penetration{ normal: out * (1.0 / distance), depth });
+    }
+    match piece {
+        // The center is inside the box: leave through the nearest face.
+        Piece::Box(b) => {
+            let d = sphere.center - b.center;
+            let k = (0..3).min_by(|&i, &j| (b.half[i] - d.dot(b.axes[i]).abs()).total_cmp(&(b.half[j] - d.dot(b.axes[j]).abs()))).unwrap_or(0);
+            let local = d.dot(b.axes[k]);
+            let normal = if local < 0.0 { b.axes[k] * -1.0 } else { b.axes[k] };
+            Some(Penetration{ normal, depth: sphere.radius + b.half[k] - local.abs() })
+        }
+        // The center is on the triangle: leave through either side.
+        _ => {
+            let normal = piece.faces().first().filter(|n| n.dot(**n) > EPSILON * EPSILON)
+                .map_or(Point3D::new(0.0, -1.0, 0.0), |n| n.normalize());
+            Some(Penetration{ normal, depth: sphere.radius })
+        }
+    }
+}
+
+/// Separating axis test between two boxes or triangles, over their face
+/// directions and the crossings of their edges.
+fn separating_axes(a: &Piece, b: &Piece) -> Option<Penetration> {
+    let mut axes: Vec<Point3D> = a.faces().into_iter().chain(b.faces()).collect();
+    for ea in a.edges() {
+        for eb in b.edges() {
+            axes.push(ea.cross(eb));
+        }
+    }
+    let (va, vb) = (a.vertices(), b.vertices());
+    let span = |points: &[Point3D], axis: Point3D| points.iter()
+        .map(|p| p.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), x| (lo.min(x), hi.max(x)));
+
+    let mut best: Option<Penetration> = None;
+    for axis in axes {
+        // Parallel edges and degenerate triangles give no axis of their own.
+        if axis.dot(axis) < EPSILON * EPSILON {
+            continue;
+        }
+        let axis = axis.normalize();
+        let ((a_lo, a_hi), (b_lo, b_hi)) = (span(&va, axis), span(&vb, axis));
+        let (forward, backward) = (b_hi - a_lo, a_hi - b_lo);
+        if forward < 0.0 || backward < 0.0 {
+            return None;
+        }
+        let found = if forward < backward {
+            Penetration{ normal: axis, depth: forward }
+        } else {
+            Penetration{ normal: axis * -1.0, depth: backward }
+        };
+        if best.is_none_or(|b| found.depth < b.depth) {
+            best = Some(found);
+        }
+    }
+    best
+}
+
+/// Nearest point of triangle `t` to `p`, by which of its regions `p` falls in.
+fn closest_on_triangle(p: Point3D, t: &[Point3D; 3]) -> Point3D {
+    let [a, b, c] = *t;
+    let (ab, ac) = (b - a, c - a);
+    let ap = p - a;
+    let (d1, d2) = (ab.dot(ap), ac.dot(ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(bp), ac.dot(bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(cp), ac.dot(cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let total = va + vb + vc;
+    if total.abs() < EPSILON * EPSILON {
+        return a;
+    }
+    a + ab * (vb / total) + ac * (vc / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objec::{Cube, Sphere};
+
+    fn p(x: f32, y: f32, z: f32) -> Point3D {
+        Point3D::new(x, y, z)
+    }
+
+    fn close(a: Point3D, b: Point3D) -> bool {
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4 && (a.z - b.z).abs() < 1e-4
+    }
+
+    fn sphere(center: Point3D, radius: f32) -> Volume {
+        Volume::Sphere(BoundingSphere{ center, radius })
+    }
+
+    fn unit_box(center: Point3D) -> Volume {
+        Volume::Obb(Obb{ center, axes: [p(1.0, 0.0, 0.0), p(0.0, 1.0, 0.0), p(0.0, 0.0, 1.0)], half: [0.5; 3] })
+    }
+
+    fn triangle(a: Point3D, b: Point3D, c: Point3D) -> Volume {
+        Volume::Mesh(vec![Triangle3D::new(a, b, c, [0, 0, 0])])
+    }
+
+    /// Whether moving `a` by `penetration` leaves it just touching `b`.
+    fn separates(a: &Volume, b: &Volume, penetration: Penetration) -> bool {
+        let moved = |offset: Point3D| match a {
+            Volume::Mesh(t) => Volume::Mesh(t.iter().map(|t| Triangle3D::new(t.p1 + offset, t.p2 + offset, t.p3 + offset, t.color)).collect()),
+            Volume::Obb(o) => Volume::Obb(Obb{ center: o.center + offset, ..*o }),
+            Volume::Sphere(s) => Volume::Sphere(BoundingSphere{ center: s.center + offset, ..*s }),
+            Volume::Aabb(b) => Volume::Aabb(Aabb::new(b.min + offset, b.max + offset)),
+        };
+        let out = penetration.normal * (penetration.depth + 1e-3);
+        penetration_of_volumes(&moved(out), b).is_none()
+            && penetration_of_volumes(&moved(out * 0.9), b).is_some()
+    }
+
+    fn penetration_of_volumes(a: &Volume, b: &Volume) -> Option<Penetration> {
+        penetration(a, b).filter(|p| p.depth > 1e-5)
+    }
+
+    #[test]
+    fn raycast_sphere_from_outside_and_inside() {
+        let ball = sphere(p(0.0, 0.0, 5.0), 1.0);
+        let hit = raycast(&Ray::new(p(0.0, 0.0, 0.0), p(0.0, 0.0, 2.0)), &ball).unwrap();
+        assert!((hit.distance - 2.0).abs() < 1e-5, "distance in direction lengths");
+        assert!(close(hit.point, p(0.0, 0.0, 4.0)) && close(hit.normal, p(0.0, 0.0, -1.0)));
+        let inside = raycast(&Ray::new(p(0.0, 0.2, 5.0), p(1.0, 0.0, 0.0)), &ball).unwrap();
+        assert_eq!(inside.distance, 0.0);
+        assert!(close(inside.point, p(0.0, 0.2, 5.0)) && close(inside.normal, p(-1.0, 0.0, 0.0)));
+        assert!(raycast(&Ray::new(p(0.0, 0.0, 0.0), p(0.0, 0.0, -1.0)), &ball).is_none(), "behind the ray");
+        assert!(raycast(&Ray::new(p(0.0, 2.0, 0.0), p(0.0, 0.0, 1.0)), &ball).is_none(), "passing by");
+        assert!(raycast(&Ray::new(p(0.0, 0.0, 0.0), p(0.0, 0.0, 0.0)), &ball).is_none(), "no direction");
+    }
+
+    #[test]
+    fn raycast_box_faces_inside_and_parallel_rays() {
+        let block = unit_box(p(0.0, 0.0, 3.0));
+        let hit = raycast(&Ray::new(p(0.2, 0.1, 0.0), p(0.0, 0.0, 1.0)), &block).unwrap();
+        assert!((hit.distance - 2.5).abs() < 1e-5 && close(hit.normal, p(0.0, 0.0, -1.0)));
+        let side = raycast(&Ray::new(p(-3.0, 0.0, 3.2), p(1.0, 0.0, 0.0)), &block).unwrap();
+        assert!((side.distance - 2.5).abs() < 1e-5 && close(side.normal, p(-1.0, 0.0, 0.0)));
+
+        let inside = raycast(&Ray::new(p(0.1, 0.1, 3.0), p(0.0, 1.0, 0.0)), &block).unwrap();
+        assert_eq!(inside.distance, 0.0);
+        assert!(close(inside.normal, p(0.0, -1.0, 0.0)));
+
+        // Parallel to the X and Y faces: a hit only from within their slabs.
+        assert!(raycast(&Ray::new(p(0.6, 0.0, 0.0), p(0.0, 0.0, 1.0)), &block).is_none());
+        assert!(raycast(&Ray::new(p(0.0, -0.7, 0.0), p(0.0, 0.0, 1.0)), &block).is_none());
+        assert!(raycast(&Ray::new(p(0.49, -0.49, 0.0), p(0.0, 0.0, 1.0)), &block).is_some());
+        assert!(raycast(&Ray::new(p(0.0, 0.0, 5.0), p(0.0, 0.0, 1.0)), &block).is_none(), "box behind the ray");
+
+        // A turned box is hit on its turned face.
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let turned = Volume::Obb(Obb{ center: p(0.0, 0.0, 3.0), axes: [p(s, 0.0, s), p(0.0, 1.0, 0.0), p(-s, 0.0, s)], half: [0.5; 3] });
+        let hit = raycast(&Ray::new(p(0.0, 0.0, 0.0), p(0.0, 0.0, 1.0)), &turned).unwrap();
+        assert!((hit.distance - (3.0 - 0.5 / s)).abs() < 1e-4, "hit the edge at {}", hit.distance);
+    }
+
+    #[test]
+    fn raycast_triangle_from_either_side() {
+        let (a, b, c) = (p(0.0, 0.0, 2.0), p(1.0, 0.0, 2.0), p(0.0, 1.0, 2.0));
+        for mesh in [triangle(a, b, c), triangle(a, c, b)] {
+            let hit = raycast(&Ray::new(p(0.25, 0.25, 0.0), p(0.0, 0.0, 1.0)), &mesh).unwrap();
+            assert!((hit.distance - 2.0).abs() < 1e-5 && close(hit.normal, p(0.0, 0.0, -1.0)));
+            let back = raycast(&Ray::new(p(0.25, 0.25, 4.0), p(0.0, 0.0, -1.0)), &mesh).unwrap();
+            assert!(close(back.normal, p(0.0, 0.0, 1.0)));
+            assert!(raycast(&Ray::new(p(0.75, 0.75, 0.0), p(0.0, 0.0, 1.0)), &mesh).is_none(), "past the long edge");
+        }
+    }
+
+    #[test]
+    fn penetration_between_spheres_and_boxes() {
+        let found = penetration(&sphere(p(1.5, 0.0, 0.0), 1.0), &sphere(p(0.0, 0.0, 0.0), 1.0)).unwrap();
+        assert!(close(found.normal, p(1.0, 0.0, 0.0)) && (found.depth - 0.5).abs() < 1e-5);
+        assert!(penetration(&sphere(p(2.5, 0.0, 0.0), 1.0), &sphere(p(0.0, 0.0, 0.0), 1.0)).is_none());
+
+        let found = penetration(&sphere(p(0.1, -0.8, 0.0), 0.5), &unit_box(p(0.0, 0.0, 0.0))).unwrap();
+        assert!(close(found.normal, p(0.0, -1.0, 0.0)) && (found.depth - 0.2).abs() < 1e-5);
+        // Box first: the normal points away from the sphere.
+        let found = penetration(&unit_box(p(0.0, 0.0, 0.0)), &sphere(p(0.1, -0.8, 0.0), 0.5)).unwrap();
+        assert!(close(found.normal, p(0.0, 1.0, 0.0)));
+        // Center inside the box: out through the nearest face.
+        let found = penetration(&sphere(p(0.0, 0.0, 0.3), 0.1), &unit_box(p(0.0, 0.0, 0.0))).unwrap();
+        assert!(close(found.normal, p(0.0, 0.0, 1.0)) && (found.depth - 0.3).abs() < 1e-5);
+        // Against the corner region, along the diagonal.
+        let corner = p(0.5, 0.5, 0.5);
+        let found = penetration(&sphere(corner + p(0.1, 0.1, 0.1), 0.5), &unit_box(p(0.0, 0.0, 0.0))).unwrap();
+        assert!(close(found.normal, p(1.0, 1.0, 1.0).normalize()));
+        assert!((found.depth - (0.5 - 0.1 * 3f32.sqrt())).abs() < 1e-5);
+    }
+
+    #[test]
+    fn penetration_between_boxes_uses_the_least_overlap() {
+        let below = unit_box(p(0.0, 0.0, 0.0));
+        let above = unit_box(p(0.3, -0.9, 0.1));
+        let found = penetration(&above, &below).unwrap();
+        assert!(close(found.normal, p(0.0, -1.0, 0.0)) && (found.depth - 0.1).abs() < 1e-5);
+        assert!(separates(&above, &below, found));
+        let beside = unit_box(p(0.95, 0.0, 0.0));
+        let found = penetration(&below, &beside).unwrap();
+        assert!(close(found.normal, p(-1.0, 0.0, 0.0)) && (found.depth - 0.05).abs() < 1e-5);
+        assert!(penetration(&unit_box(p(1.1, 0.0, 0.0)), &below).is_none());
+        // Touching counts as overlapping, without depth.
+        assert!(overlaps(&unit_box(p(1.0, 0.0, 0.0)), &below));
+    }
+
+    #[test]
+    fn penetration_between_triangles() {
+        let flat = triangle(p(-1.0, 0.0, -1.0), p(2.0, 0.0, -1.0), p(-1.0, 0.0, 2.0));
+        // Poking 0.2 down (+Y) through the flat triangle's plane, well inside it,
+        // so it comes out by going back up.
+        let poking = triangle(p(0.0, 0.2, 0.0), p(0.4, -1.0, 0.0), p(0.0, -1.0, 0.4));
+        let found = penetration(&poking, &flat).unwrap();
+        assert!(close(found.normal, p(0.0, -1.0, 0.0)), "normal {:?}", found.normal);
+        assert!((found.depth - 0.2).abs() < 1e-5, "depth {}", found.depth);
+        assert!(separates(&poking, &flat, found));
+
+        let above = triangle(p(0.0, -0.5, 0.0), p(0.4, -1.0, 0.0), p(0.0, -1.0, 0.4));
+        assert!(penetration(&above, &flat).is_none());
+        let aside = triangle(p(5.0, 0.2, 0.0), p(5.4, -1.0, 0.0), p(5.0, -1.0, 0.4));
+        assert!(penetration(&aside, &flat).is_none());
+    }
+
+    #[test]
+    fn closest_on_triangle_in_each_region() {
+        let t = [p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(0.0, 1.0, 0.0)];
+        let cases = [
+            (p(-1.0, -1.0, 0.5), p(0.0, 0.0, 0.0)),
+            (p(2.0, -0.5, 0.0), p(1.0, 0.0, 0.0)),
+            (p(-0.5, 2.0, -1.0), p(0.0, 1.0, 0.0)),
+            (p(0.5, -1.0, 0.0), p(0.5, 0.0, 0.0)),
+            (p(-1.0, 0.25, 2.0), p(0.0, 0.25, 0.0)),
+            (p(1.0, 1.0, 0.0), p(0.5, 0.5, 0.0)),
+            (p(0.2, 0.3, 3.0), p(0.2, 0.3, 0.0)),
+        ];
+        for (point, expected) in cases {
+            let found = closest_on_triangle(point, &t);
+            assert!(close(found, expected), "{:?} went to {:?}, not {:?}", point, found, expected);
+        }
+        // A degenerate triangle still gives a point on it.
+        let line = [p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(2.0, 0.0, 0.0)];
+        assert!(close(closest_on_triangle(p(1.5, 1.0, 0.0), &line), p(1.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn overlapping_pairs_in_scene_order() {
+        let at = |x: f32| {
+            let mut ball = Objects::Sphere(Sphere::new(p(0.0, 0.0, 0.0), 0.5, 8, 12, [0, 0, 0]));
+            ball.set_position(p(x, 0.0, 4.0));
+            ball
+        };
+        let mut block = Objects::Cube(Cube::new(p(0.0, 0.0, 0.0), p(1.0, 1.0, 0.0), [0, 0, 0]));
+        block.set_angles(0.0, 0.0);
+        let center = block.obb().center;
+        block.move_world(p(10.0, 0.0, 4.0) - center);
+
+        let objects = vec![at(0.0), at(5.0), at(0.8), block, at(10.9), at(20.0)];
+        assert_eq!(overlapping_pairs(&objects), [(0, 2), (3, 4)]);
+        assert!(overlapping_pairs(&objects[..2]).is_empty());
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::geometry::*;
+use crate::physics::Physics;
+use crate::scene::Fields;
+use crate::transform::{Snap, Speeds};
+
+/// Settings file read at startup when no `--config` is given.
+pub const DEFAULT_PATH: &str = "cube.conf";
+
+/// The built-in settings, in the settings file format. A settings file only
+/// needs the lines it changes; a `bind` line replaces every default input of its action,
+/// and `bind <action> none` leaves it unbound.
+pub const DEFAULT_CONFIG: &str = "\
+# where new objects appear and the offset of pasted and duplicated copies
+spawn p1=-0.7,-0.7,2 p2=0.7,0.7,4 p3=0,0,4
+paste offset=0.2,0.2,0
+color cube=100,200,50 plane=100,0,50 triangle=200,200,50 pyramid=100,200,200
+color sphere=100,100,0 cylinder=200,120,60 cone=220,180,40 torus=60,160,220
+color capsule=180,80,200 disk=120,200,160 polyhedron=220,140,90 emitter=255,170,60
+# how fast held keys move, turn and scale the selection, per second; turn and angle are in degrees
+speed move=0.6 turn=70 scale=0.3
+snap grid=0.25 angle=15
+# starting light depth and how far one wheel notch moves it
+light depth=0.6 wheel=0.1
+# gravity in units per second squared (+Y is down), bounciness and friction of contacts,
+# and the height of the floor, or ground=off
+physics gravity=9.8 bounce=0.3 friction=0.5 ground=1
+
+bind pick mouse_left
+bind undo ctrl+z
+bind redo ctrl+shift+z ctrl+y
+bind copy ctrl+c
+bind paste ctrl+v
+bind duplicate insert
+bind delete delete back
+bind order_up pageup
+bind order_down pagedown
+bind move_up w
+bind move_down s
+bind move_left a
+bind move_right d
+bind move_near q
+bind move_far e
+bind turn_up up
+bind turn_down down
+bind turn_left left
+bind turn_right right
+bind grow r
+bind shrink f
+bind cycle_color m
+bind spawn_cube c
+bind spawn_plane p
+bind spawn_triangle t
+bind spawn_pyramid y
+bind spawn_sphere o
+bind spawn_cylinder i
+bind spawn_cone k
+bind spawn_torus u
+bind spawn_capsule j
+bind spawn_disk n
+bind spawn_polyhedron v
+bind spawn_emitter z
+bind select_0 numpad0
+bind select_1 numpad1
+bind select_2 numpad2
+bind select_3 numpad3
+bind select_4 numpad4
+bind select_5 numpad5
+bind select_6 numpad6
+bind select_7 numpad7
+bind select_8 numpad8
+bind select_9 numpad9
+bind deselect escape
+bind gizmo_translate 1
+bind gizmo_rotate 2
+bind gizmo_scale 3
+bind enter_value return numpadenter
+bind toggle_bounds b
+bind toggle_snap x
+bind toggle_grid g
+bind toggle_axes l
+bind toggle_orientation f2
+bind toggle_hud h
+bind save f5
+bind load f9
+bind export_gltf f7
+bind play_pause space
+bind scrub_back comma
+bind scrub_forward period
+bind timeline_start home
+bind timeline_end end
+bind toggle_loop f4
+bind key_selection f6
+bind unkey_selection shift+f6
+bind key_light ctrl+f6
+bind cycle_easing f8
+bind record_turntable f12
+bind toggle_physics f3
+bind reset_physics shift+f3
+bind toggle_pinned ctrl+f3
+bind toggle_raytrace f10
+bind quit ctrl+q
+";
+
+/// Something the viewer does in response to a key or mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action{
+    /// Select the object under the cursor, or drag the gizmo handle there.
+    Pick,
+    Undo,
+    Redo,
+    Copy,
+    Paste,
+    Duplicate,
+    Delete,
+    OrderUp,
+    OrderDown,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveNear,
+    MoveFar,
+    TurnUp,
+    TurnDown,
+    TurnLeft,
+    TurnRight,
+    Grow,
+    Shrink,
+    CycleColor,
+    SpawnCube,
+    SpawnPlane,
+    SpawnTriangle,
+    SpawnPyramid,
+    SpawnSphere,
+    SpawnCylinder,
+    SpawnCone,
+    SpawnTorus,
+    SpawnCapsule,
+    SpawnDisk,
+    SpawnPolyhedron,
+    SpawnEmitter,
+    /// Select the object with this index.
+    Select(usize),
+    Deselect,
+    GizmoTranslate,
+    GizmoRotate,
+    GizmoScale,
+    /// Start typing an exact position, rotation or scale.
+    EnterValue,
+    ToggleBounds,
+    ToggleSnap,
+    ToggleGrid,
+    ToggleAxes,
+    ToggleOrientation,
+    ToggleHud,
+    Save,
+    Load,
+    ExportGltf,
+    PlayPause,
+    ScrubBack,
+    ScrubForward,
+    TimelineStart,
+    TimelineEnd,
+    ToggleLoop,
+    /// Key the selected objects at the playhead.
+    KeySelection,
+    UnkeySelection,
+    /// Key the light position at the playhead.
+    KeyLight,
+    /// Switch the easing of new keys.
+    CycleEasing,
+    /// Record an orbit around the selection to a GIF named after the scene file.
+    RecordTurntable,
+    /// Start or pause the physics simulation.
+    TogglePhysics,
+    /// Put the objects back where they were when the simulation was started.
+    ResetPhysics,
+    /// Pin the selection in place for the simulation, or let it move again.
+    TogglePinned,
+    /// Switch between the rasterizer and the progressive ray tracer.
+    ToggleRayTrace,
+    /// Close the viewer; mostly for the terminal, which has no close button.
+    Quit,
+}
+
+/// Names of the actions in settings files.
+const ACTIONS: [(&str, Action); 74] = [
+    ("pick", Action::Pick), ("undo", Action::Undo), ("redo", Action::Redo),
+    ("copy", Action::Copy), ("paste", Action::Paste), ("duplicate", Action::Duplicate),
+    ("delete", Action::Delete), ("order_up", Action::OrderUp), ("order_down", Action::OrderDown),
+    ("move_up", Action::MoveUp), ("move_down", Action::MoveDown), ("move_left", Action::MoveLeft),
+    ("move_right", Action::MoveRight), ("move_near", Action::MoveNear), ("move_far", Action::MoveFar),
+    ("turn_up", Action::TurnUp), ("turn_down", Action::TurnDown), ("turn_left", Action::TurnLeft),
+    ("turn_right", Action::TurnRight), ("grow", Action::Grow), ("shrink", Action::Shrink),
+    ("cycle_color", Action::CycleColor), ("spawn_cube", Action::SpawnCube), ("spawn_plane", Action::SpawnPlane),
+    ("spawn_triangle", Action::SpawnTriangle), ("spawn_pyramid", Action::SpawnPyramid),
+    ("spawn_sphere", Action::SpawnSphere), ("spawn_cylinder", Action::SpawnCylinder),
+    ("spawn_cone", Action::SpawnCone), ("spawn_torus", Action::SpawnTorus),
+    ("spawn_capsule", Action::SpawnCapsule), ("spawn_disk", Action::SpawnDisk),
+    ("spawn_polyhedron", Action::SpawnPolyhedron), ("spawn_emitter", Action::SpawnEmitter),
+    ("select_0", Action::Select(0)), ("select_1", Action::Select(1)), ("select_2", Action::Select(2)),
+    ("select_3", Action::Select(3)), ("select_4", Action::Select(4)), ("select_5", Action::Select(5)),
+    ("select_6", Action::Select(6)), ("select_7", Action::Select(7)), ("select_8", Action::Select(8)),
+    ("select_9", Action::Select(9)), ("deselect", Action::Deselect),
+    ("gizmo_translate", Action::GizmoTranslate), ("gizmo_rotate", Action::GizmoRotate),
+    ("gizmo_scale", Action::GizmoScale), ("enter_value", Action::EnterValue),
+    ("toggle_bounds", Action::ToggleBounds), ("toggle_snap", Action::ToggleSnap),
+    ("toggle_grid", Action::ToggleGrid), ("toggle_axes", Action::ToggleAxes),
+    ("toggle_orientation", Action::ToggleOrientation), ("toggle_hud", Action::ToggleHud),
+    ("save", Action::Save), ("load", Action::Load), ("export_gltf", Action::ExportGltf),
+    ("play_pause", Action::PlayPause), ("scrub_back", Action::ScrubBack), ("scrub_forward", Action::ScrubForward),
+    ("timeline_start", Action::TimelineStart), ("timeline_end", Action::TimelineEnd), ("toggle_loop", Action::ToggleLoop),
+    ("key_selection", Action::KeySelection), ("unkey_selection", Action::UnkeySelection),
+    ("key_light", Action::KeyLight), ("cycle_easing", Action::CycleEasing),
+    ("record_turntable", Action::RecordTurntable), ("toggle_physics", Action::TogglePhysics),
+    ("reset_physics", Action::ResetPhysics), ("toggle_pinned", Action::TogglePinned),
+    ("toggle_raytrace", Action::ToggleRayTrace), ("quit", Action::Quit),
+];
+
+impl Action{
+    pub fn parse(name: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(n, _)| *n == name).map(|&(_, action)| action)
+    }
+
+    /// Whether the action keeps going for as long as its input is held,
+    /// rather than happening once per press.
+    pub fn is_held(self) -> bool {
+        matches!(self,
+            Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight | Action::MoveNear | Action::MoveFar
+            | Action::TurnUp | Action::TurnDown | Action::TurnLeft | Action::TurnRight | Action::Grow | Action::Shrink)
+    }
+}
+
+/// A key or mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Input{
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// An input together with the modifiers that must be held for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Binding{
+    pub input: Input,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Binding{
+    /// Parses `ctrl+shift+z`, `f5` or `mouse_left`; names are case-insensitive.
+    pub fn parse(text: &str) -> Result<Binding, String> {
+        let lower = text.to_ascii_lowercase();
+        let mut parts: Vec<&str> = lower.split('+').collect();
+        let name = parts.pop().unwrap_or_default();
+        let mut binding = Binding{ input: parse_input(name).ok_or_else(|| format!("unknown key '{}'", name))?, ctrl: false, shift: false };
+        for modifier in parts {
+            match modifier {
+                "ctrl" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, text)),
+            }
+        }
+        Ok(binding)
+    }
+}
+
+/// Which action each input triggers.
+#[derive(Clone, Debug, Default)]
+pub struct Bindings(Vec<(Binding, Action)>);
+
+impl Bindings{
+    /// The action for `input` with the held modifiers. An exact match wins; otherwise
+    /// Shift and then Ctrl are let through, so Shift and Ctrl still make speeds finer or coarser.
+    pub fn action(&self, input: Input, ctrl: bool, shift: bool) -> Option<Action> {
+        [(ctrl, shift), (ctrl, false), (false, false)].iter().find_map(|&(ctrl, shift)| {
+            let wanted = Binding{ input, ctrl, shift };
+            self.0.iter().find(|(b, _)| *b == wanted).map(|&(_, action)| action)
+        })
+    }
+
+    /// Binds `binding` to `action`, taking it from any action it was bound to before.
+    pub fn bind(&mut self, binding: Binding, action: Action) {
+        self.0.retain(|(b, _)| *b != binding);
+        self.0.push((binding, action));
+    }
+
+    pub fn unbind_action(&mut self, action: Action) {
+        self.0.retain(|&(_, a)| a != action);
+    }
+}
+
+/// Controls and viewer defaults, from `DEFAULT_CONFIG` and a settings file.
+#[derive(Clone, Debug)]
+pub struct Config{
+    pub bindings: Bindings,
+    /// Corners and apex that new objects are built from.
+    pub spawn: [Point3D; 3],
+    /// How far pasted and duplicated copies land from their originals.
+    pub paste_offset: Point3D,
+    /// Colors of new objects, by the action that spawns them.
+    pub colors: HashMap<Action, [u8; 3]>,
+    pub speeds: Speeds,
+    pub snap: Snap,
+    /// Starting depth of the light for scenes that do not set one.
+    pub light_depth: f32,
+    /// Light depth change per mouse wheel notch.
+    pub wheel_step: f32,
+    pub physics: Physics,
+}
+
+#[derive(Debug)]
+pub enum ConfigError{
+    Io(std::io::Error),
+    Parse{ line: usize, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "i/o error: {}", err),
+            ConfigError::Parse{ line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut config = Config{
+            bindings: Bindings::default(),
+            spawn: [Point3D::new(0.0, 0.0, 0.0); 3],
+            paste_offset: Point3D::new(0.0, 0.0, 0.0),
+            colors: HashMap::new(),
+            speeds: Speeds::default(),
+            snap: Snap::default(),
+            light_depth: 0.0,
+            wheel_step: 0.0,
+            physics: Physics::default(),
+        };
+        config.apply(DEFAULT_CONFIG).expect("built-in settings parse");
+        config
+    }
+}
+
+impl Config{
+    /// The defaults with the settings in the file at `path` applied over them.
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.apply(&fs::read_to_string(path)?)?;
+        Ok(config)
+    }
+
+    /// Color of objects spawned by `action`.
+    pub fn color(&self, action: Action) -> [u8; 3] {
+        self.colors.get(&action).copied().unwrap_or(crate::import::DEFAULT_COLOR)
+    }
+
+    /// Applies the settings in `text` over the current ones.
+    pub fn apply(&mut self, text: &str) -> Result<(), ConfigError> {
+        // Actions bound in this text lose the inputs they had before it.
+        let mut rebound = HashSet::new();
+
+        for (n, raw) in text.lines().enumerate() {
+            let line = n + 1;
+            let err = |message: String| ConfigError::Parse{ line, message };
+            let content = raw.split('#').next().unwrap_or("").trim();
+            let mut tokens = content.split_whitespace();
+            let kind = match tokens.next() {
+                Some(k) => k,
+                None => continue,
+            };
+
+            if kind == "bind" {
+                let name = tokens.next().ok_or_else(|| err("bind needs an action".into()))?;
+                let action = Action::parse(name).ok_or_else(|| err(format!("unknown action '{}'", name)))?;
+                if rebound.insert(action) {
+                    self.bindings.unbind_action(action);
+                }
+                for token in tokens.filter(|&t| t != "none") {
+                    self.bindings.bind(Binding::parse(token).map_err(err)?, action);
+                }
+                continue;
+            }
+
+            let fields = Fields::parse(tokens).map_err(err)?;
+            match kind {
+                "spawn" => {
+                    for (i, key) in ["p1", "p2", "p3"].iter().enumerate() {
+                        if let Some(n) = fields.numbers(key, 3).map_err(err)? {
+                            self.spawn[i] = Point3D::new(n[0], n[1], n[2]);
+                        }
+                    }
+                }
+                "paste" => self.paste_offset = fields.point("offset").map_err(err)?,
+                "color" => {
+                    for &key in fields.0.keys() {
+                        let action = Action::parse(&format!("spawn_{}", key)).ok_or_else(|| err(format!("unknown object type '{}'", key)))?;
+                        let color = fields.color_or(key, crate::import::DEFAULT_COLOR).map_err(err)?;
+                        self.colors.insert(action, color);
+                    }
+                }
+                "speed" => {
+                    self.speeds.translate = positive(&fields, "move", self.speeds.translate).map_err(err)?;
+                    self.speeds.rotate = positive(&fields, "turn", self.speeds.rotate.to_degrees()).map_err(err)?.to_radians();
+                    self.speeds.scale = positive(&fields, "scale", self.speeds.scale).map_err(err)?;
+                }
+                "snap" => {
+                    self.snap.grid = positive(&fields, "grid", self.snap.grid).map_err(err)?;
+                    self.snap.angle = positive(&fields, "angle", self.snap.angle.to_degrees()).map_err(err)?.to_radians();
+                }
+                "light" => {
+                    self.light_depth = fields.float_or("depth", self.light_depth).map_err(err)?;
+                    self.wheel_step = positive(&fields, "wheel", self.wheel_step).map_err(err)?;
+                }
+                "physics" => {
+                    let physics = &mut self.physics;
+                    physics.gravity = fields.float_or("gravity", physics.gravity).map_err(err)?;
+                    physics.restitution = fields.float_or("bounce", physics.restitution).map_err(err)?;
+                    physics.friction = fields.float_or("friction", physics.friction).map_err(err)?;
+                    if !(0.0..=1.0).contains(&physics.restitution) || physics.friction < 0.0 {
+                        return Err(err("bounce must be from 0 to 1 and friction not negative".into()));
+                    }
+                    physics.ground = match fields.0.get("ground") {
+                        Some(&"off") => None,
+                        Some(_) => Some(fields.float_or("ground", 0.0).map_err(err)?),
+                        None => physics.ground,
+                    };
+                }
+                _ => return Err(err(format!("unknown setting '{}'", kind))),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn positive(fields: &Fields, key: &str, default: f32) -> Result<f32, String> {
+    let value = fields.float_or(key, default)?;
+    if value <= 0.0 {
+        return Err(format!("{} must be positive", key));
+    }
+    Ok(value)
+}
+
+fn parse_input(name: &str) -> Option<Input> {
+    use VirtualKeyCode as K;
+
+    let mouse = match name {
+        "mouse_left" => Some(MouseButton::Left),
+        "mouse_right" => Some(MouseButton::Right),
+        "mouse_middle" => Some(MouseButton::Middle),
+        _ => None,
+    };
+    if let Some(button) = mouse {
+        return Some(Input::Mouse(button));
+    }
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        K::A, K::B, K::C, K::D, K::E, K::F, K::G, K::H, K::I, K::J, K::K, K::L, K::M,
+        K::N, K::O, K::P, K::Q, K::R, K::S, K::T, K::U, K::V, K::W, K::X, K::Y, K::Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] = [K::Key0, K::Key1, K::Key2, K::Key3, K::Key4, K::Key5, K::Key6, K::Key7, K::Key8, K::Key9];
+    const NUMPAD: [VirtualKeyCode; 10] = [
+        K::Numpad0, K::Numpad1, K::Numpad2, K::Numpad3, K::Numpad4,
+        K::Numpad5, K::Numpad6, K::Numpad7, K::Numpad8, K::Numpad9,
+    ];
+    const FUNCTION: [VirtualKeyCode; 12] = [K::F1, K::F2, K::F3, K::F4, K::F5, K::F6, K::F7, K::F8, K::F9, K::F10, K::F11, K::F12];
+
+    let key = match name.as_bytes() {
+        [c @ b'a'..=b'z'] => LETTERS[(c - b'a') as usize],
+        [c @ b'0'..=b'9'] => DIGITS[(c - b'0') as usize],
+        _ => {
+            if let Some(n) = name.strip_prefix("numpad").and_then(|n| n.parse::<usize>().ok()) {
+                *NUMPAD.get(n)?
+            } else if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+                *FUNCTION.get(n.checked_sub(1)?)?
+            } else {
+                match name {
+                    "up" => K::Up,
+                    "down" => K::Down,
+                    "left" => K::Left,
+                    "right" => K::Right,
+                    "pageup" => K::PageUp,
+                    "pagedown" => K::PageDown,
+                    "home" => K::Home,
+                    "end" => K::End,
+                    "insert" => K::Insert,
+                    "delete" => K::Delete,
+                    "back" | "backspace" => K::Back,
+                    "return" | "enter" => K::Return,
+                    "numpadenter" => K::NumpadEnter,
+                    "escape" | "esc" => K::Escape,
+                    "space" => K::Space,
+                    "tab" => K::Tab,
+                    "minus" => K::Minus,
+                    "equals" => K::Equals,
+                    "comma" => K::Comma,
+                    "period" => K::Period,
+                    "slash" => K::Slash,
+                    "semicolon" => K::Semicolon,
+                    "apostrophe" => K::Apostrophe,
+                    "lbracket" => K::LBracket,
+                    "rbracket" => K::RBracket,
+                    "numpadadd" => K::NumpadAdd,
+                    "numpadsubtract" => K::NumpadSubtract,
+                    _ => return None,
+                }
+            }
+        }
+    };
+    Some(Input::Key(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VirtualKeyCode as K;
+
+    fn key(code: VirtualKeyCode) -> Input {
+        Input::Key(code)
+    }
+
+    fn parse_error(text: &str) -> (usize, String) {
+        match Config::default().apply(text) {
+            Err(ConfigError::Parse{ line, message }) => (line, message),
+            other => panic!("'{}' gave {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn default_config_parses() {
+        let config = Config::default();
+        let b = &config.bindings;
+        assert_eq!(b.action(Input::Mouse(MouseButton::Left), false, false), Some(Action::Pick));
+        assert_eq!(b.action(key(K::Z), true, false), Some(Action::Undo));
+        assert_eq!(b.action(key(K::Z), true, true), Some(Action::Redo));
+        assert_eq!(b.action(key(K::Y), true, false), Some(Action::Redo));
+        assert_eq!(b.action(key(K::F6), false, true), Some(Action::UnkeySelection));
+        assert_eq!(b.action(key(K::Numpad7), false, false), Some(Action::Select(7)));
+        for (name, action) in ACTIONS {
+            assert!(b.0.iter().any(|&(_, a)| a == action), "{} has no default binding", name);
+        }
+
+        assert_eq!(config.color(Action::SpawnCube), [100, 200, 50]);
+        assert_eq!(config.paste_offset.x, 0.2);
+        assert_eq!((config.snap.grid, config.light_depth, config.wheel_step), (0.25, 0.6, 0.1));
+        assert!((config.snap.angle - 15f32.to_radians()).abs() < 1e-6);
+        assert_eq!(config.physics.ground, Some(1.0));
+    }
+
+    #[test]
+    fn rebinding_replaces_the_default_inputs() {
+        let mut config = Config::default();
+        config.apply("bind undo u\nbind undo ctrl+u\nbind spawn_torus none").unwrap();
+        let b = &config.bindings;
+        // Ctrl+Z now falls through to plain Z.
+        assert_eq!(b.action(key(K::Z), true, false), Some(Action::SpawnEmitter), "old undo input still bound");
+        // Lines for the same action in one text add up.
+        assert_eq!(b.action(key(K::U), false, false), Some(Action::Undo));
+        assert_eq!(b.action(key(K::U), true, false), Some(Action::Undo));
+        assert!(!b.0.iter().any(|&(_, a)| a == Action::SpawnTorus));
+
+        // A later text starts the action over again.
+        config.apply("bind undo ctrl+z").unwrap();
+        assert_eq!(config.bindings.action(key(K::U), false, false), None);
+        assert_eq!(config.bindings.action(key(K::Z), true, false), Some(Action::Undo));
+
+        // An input taken by another action leaves the one it had.
+        config.apply("bind quit w").unwrap();
+        assert_eq!(config.bindings.action(key(K::W), false, false), Some(Action::Quit));
+        assert!(!config.bindings.0.iter().any(|&(_, a)| a == Action::MoveUp));
+    }
+
+    #[test]
+    fn shift_then_ctrl_fall_through() {
+        let b = Config::default().bindings;
+        // No Shift+W or Ctrl+W binding: held modifiers still reach plain W.
+        assert_eq!(b.action(key(K::W), false, true), Some(Action::MoveUp));
+        assert_eq!(b.action(key(K::W), true, false), Some(Action::MoveUp));
+        assert_eq!(b.action(key(K::W), true, true), Some(Action::MoveUp));
+        // Shift is dropped before Ctrl: Ctrl+Shift+C finds Ctrl+C, not C.
+        assert_eq!(b.action(key(K::C), true, true), Some(Action::Copy));
+        // An exact match wins over the plain key.
+        assert_eq!(b.action(key(K::F3), false, true), Some(Action::ResetPhysics));
+        assert_eq!(b.action(key(K::F3), true, false), Some(Action::TogglePinned));
+        // Ctrl is never dropped while Shift is kept.
+        let mut b = Bindings::default();
+        b.bind(Binding::parse("shift+a").unwrap(), Action::Quit);
+        assert_eq!(b.action(key(K::A), true, true), None);
+    }
+
+    #[test]
+    fn errors_carry_the_line_number() {
+        let (line, message) = parse_error("# comment\n\nbind jump space");
+        assert_eq!((line, message.as_str()), (3, "unknown action 'jump'"));
+        let (line, message) = parse_error("bind undo z\nbind redo ctrl+banana");
+        assert_eq!((line, message.as_str()), (2, "unknown key 'banana'"));
+        let (line, message) = parse_error("bind redo alt+z");
+        assert_eq!((line, message.as_str()), (1, "unknown modifier 'alt' in 'alt+z'"));
+        assert_eq!(parse_error("snap grid=0.5\nsnap grid=-1").0, 2);
+        assert_eq!(parse_error("\n\n\nwarp speed=9").1, "unknown setting 'warp'");
+        assert_eq!(parse_error("bind").1, "bind needs an action");
+        let error = Config::default().apply("x\ncolor cube=1,2,3 blob=1,2,3").unwrap_err();
+        assert_eq!(error.to_string(), "line 1: unknown setting 'x'");
+    }
+}
@@ -1,30 +1,155 @@
+use crate::camera::Camera;
 use crate::geometry::*;
 use crate::lighting::*;
 use crate::operations::*;
-use crate::HEIGHT;
-use crate::WIDTH;
 
-pub fn triangle_3d_fill(triangle: Triangle3D, selected: bool, light_dir: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]) {
-    let intensity = compute_light(&triangle, light_dir);
-    let color_gray = shaded_color(intensity, triangle.color, selected);
+/// Color and depth buffers the rasterizer draws into.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA, row by row.
+    pub color: Vec<u8>,
+    pub depth: Vec<f32>,
+    /// Per pixel, one more than the index of the object drawn there (0 for none).
+    /// Only kept after `enable_ids`, since most frames never need it.
+    pub ids: Option<Vec<u32>>,
+    /// ID written by `set_pixel` for whatever is being drawn now.
+    pub object_id: u32,
+    /// Triangles drawn since the last `clear`.
+    pub triangles: u32,
+}
+
+impl Frame {
+    pub fn new(width: u32, height: u32) -> Self {
+        Frame {
+            width,
+            height,
+            color: vec![0; (width * height * 4) as usize],
+            depth: vec![f32::INFINITY; (width * height) as usize],
+            ids: None,
+            object_id: 0,
+            triangles: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.color.fill(0);
+        self.depth.fill(f32::INFINITY);
+        self.triangles = 0;
+        if let Some(ids) = &mut self.ids {
+            ids.fill(0);
+        }
+    }
+
+    /// Starts recording which object covers each pixel.
+    pub fn enable_ids(&mut self) {
+        self.ids.get_or_insert_with(|| vec![0; (self.width * self.height) as usize]);
+    }
+
+    /// Index of the object visible at (`x`, `y`), if IDs are recorded and one is drawn there.
+    pub fn object_at(&self, x: u32, y: u32) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let id = self.ids.as_ref()?[(y * self.width + x) as usize];
+        id.checked_sub(1).map(|i| i as usize)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    /// Colors and, where normals are known, lighting interpolated across each face.
+    Shaded,
+    /// One color per face.
+    Flat,
+    /// Triangle edges only.
+    Wireframe,
+}
+
+impl RenderMode {
+    pub fn parse(name: &str) -> Option<RenderMode> {
+        match name {
+            "shaded" => Some(RenderMode::Shaded),
+            "flat" => Some(RenderMode::Flat),
+            "wireframe" => Some(RenderMode::Wireframe),
+            _ => None,
+        }
+    }
+}
+
+/// Settings shared by every draw call of one frame.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub light: Point3D,
+    pub camera: Camera,
+    pub mode: RenderMode,
+}
+
+pub fn triangle_3d_fill(triangle: Triangle3D, selected: bool, view: &View, frame: &mut Frame) {
+    frame.triangles += 1;
+    let intensities = match (triangle.normals, view.mode) {
+        (Some(normals), RenderMode::Shaded) => {
+            let points = [triangle.p1, triangle.p2, triangle.p3];
+            [0, 1, 2].map(|i| vertex_light(points[i], normals[i], view.light))
+        }
+        _ => [compute_light(&triangle, view.light); 3],
+    };
+    let mut colors = triangle.colors.unwrap_or([triangle.color; 3]);
+    if view.mode == RenderMode::Flat {
+        let average = |i: usize| ((colors[0][i] as u32 + colors[1][i] as u32 + colors[2][i] as u32) / 3) as u8;
+        colors = [[average(0), average(1), average(2)]; 3];
+    }
+    let shaded = [0, 1, 2].map(|i| shaded_color(intensities[i], colors[i], selected));
 
-    let p1 = triangle.p1;
-    let p2 = triangle.p2;
-    let p3 = triangle.p3;
+    let camera = &view.camera;
+    let p1 = camera.to_view(triangle.p1);
+    let p2 = camera.to_view(triangle.p2);
+    let p3 = camera.to_view(triangle.p3);
 
     let t = Triangle::new(
-        project(p1),
-        project(p2),
-        project(p3),
+        project(p1, camera.fov, frame.width, frame.height),
+        project(p2, camera.fov, frame.width, frame.height),
+        project(p3, camera.fov, frame.width, frame.height),
     );
 
-    triangle_fill_z(t, [p1.z, p2.z, p3.z], framebuffer, zbuffer, color_gray);
+    if view.mode == RenderMode::Wireframe {
+        triangle_outline(t, [p1.z, p2.z, p3.z], frame, shaded[0]);
+    } else {
+        triangle_fill_z(t, [p1.z, p2.z, p3.z], frame, shaded);
+    }
+}
+
+/// One end of a horizontal span: screen x, depth and the color interpolated along the edge.
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+    pub x: f64,
+    pub z: f32,
+    pub color: [f32; 4],
 }
 
-pub fn triangle_fill_z(t: Triangle, z_values: [f32; 3], buffer: &mut [u8], zbuffer: &mut [f32], color: [u8; 4]) {
-    let mut points = [(t.p1, z_values[0]), (t.p2, z_values[1]), (t.p3, z_values[2])];
+impl Span {
+    fn new(x: u32, z: f32, color: [u8; 4]) -> Self {
+        Span { x: x as f64, z, color: color.map(|c| c as f32) }
+    }
+
+    fn lerp(self, other: Span, t: f64) -> Span {
+        let tf = t as f32;
+        Span {
+            x: self.x + t * (other.x - self.x),
+            z: self.z + tf * (other.z - self.z),
+            color: [0, 1, 2, 3].map(|i| self.color[i] + tf * (other.color[i] - self.color[i])),
+        }
+    }
+}
+
+pub fn triangle_fill_z(t: Triangle, z_values: [f32; 3], frame: &mut Frame, colors: [[u8; 4]; 3]) {
+    let mut points = [
+        (t.p1, Span::new(t.p1.x, z_values[0], colors[0])),
+        (t.p2, Span::new(t.p2.x, z_values[1], colors[1])),
+        (t.p3, Span::new(t.p3.x, z_values[2], colors[2])),
+    ];
     points.sort_by_key(|(p, _)| p.y);
-    let ((v0, z0), (v1, z1), (v2, z2)) = (points[0], points[1], points[2]);
+    let ((v0, s0), (v1, s1), (v2, s2)) = (points[0], points[1], points[2]);
 
     let first_diff = v1.y.saturating_sub(v0.y);
     let second_diff = v2.y.saturating_sub(v1.y);
@@ -38,14 +163,9 @@ pub fn triangle_fill_z(t: Triangle, z_values: [f32; 3], buffer: &mut [u8], zbuff
             let y_percent1 = i as f64 / dy1;
             let y_percent2 = i as f64 / dy2;
 
-            let x1 = v0.x as f64 + y_percent1 * (v1.x as f64 - v0.x as f64);
-            let x2 = v0.x as f64 + y_percent2 * (v2.x as f64 - v0.x as f64);
-            let z1 = z0 + y_percent1 as f32 * (z1 - z0);
-            let z2 = z0 + y_percent2 as f32 * (z2 - z0);
-
             let y = v0.y + i;
-            if y < HEIGHT {
-                draw_scanline_z(x1, x2, y, z1, z2, buffer, zbuffer, color);
+            if y < frame.height {
+                draw_scanline_z(s0.lerp(s1, y_percent1), s0.lerp(s2, y_percent2), y, frame);
             }
         }
     }
@@ -59,71 +179,83 @@ pub fn triangle_fill_z(t: Triangle, z_values: [f32; 3], buffer: &mut [u8], zbuff
             let y_percent1 = i as f64 / dy1;
             let y_percent2 = (v1.y + i - v0.y) as f64 / dy2;
 
-            let x1 = v1.x as f64 + y_percent1 * (v2.x as f64 - v1.x as f64);
-            let x2 = v0.x as f64 + y_percent2 * (v2.x as f64 - v0.x as f64);
-            let z1 = z1 + y_percent1 as f32 * (z2 - z1);
-            let z2 = z0 + y_percent2 as f32 * (z2 - z0);
-
             let y = v1.y + i;
-            if y < HEIGHT {
-                draw_scanline_z(x1, x2, y, z1, z2, buffer, zbuffer, color);
+            if y < frame.height {
+                draw_scanline_z(s1.lerp(s2, y_percent1), s0.lerp(s2, y_percent2), y, frame);
             }
         }
     }
 }
 
-pub fn draw_scanline_z(x1: f64, x2: f64, y: u32, z1: f32, z2: f32, buffer: &mut [u8], zbuffer: &mut [f32], color: [u8; 4]) {
-    let (x_start, x_end, z_start, z_end) = if x1 < x2 {
-        (x1, x2, z1, z2)
-    } else {
-        (x2, x1, z2, z1)
-    };
+pub fn draw_scanline_z(a: Span, b: Span, y: u32, frame: &mut Frame) {
+    let (start, end) = if a.x < b.x { (a, b) } else { (b, a) };
 
-    let dx = x_end - x_start;
+    let dx = end.x - start.x;
     if dx == 0.0 { return; }
 
     for i in 0..=(dx as u32) {
-        let x = (x_start + i as f64).clamp(0.0, (WIDTH - 1) as f64) as u32;
-        let z = z_start + (i as f32 / dx as f32) * (z_end - z_start);
-        set_pixel(buffer, zbuffer, x, y, z, color);
+        let x = (start.x + i as f64).clamp(0.0, (frame.width - 1) as f64) as u32;
+        let span = start.lerp(end, i as f64 / dx);
+        set_pixel(frame, x, y, span.z, span.color.map(|c| c as u8));
+    }
+}
+
+pub fn set_pixel(frame: &mut Frame, x: u32, y: u32, z: f32, color: [u8; 4]) {
+    if x >= frame.width || y >= frame.height {
+        return;
+    }
+    let i = (y * frame.width + x) as usize;
+    if z < frame.depth[i] {
+        frame.depth[i] = z;
+        let pixel_i = i * 4;
+        frame.color[pixel_i..pixel_i + 4].copy_from_slice(&color);
+        if let Some(ids) = &mut frame.ids {
+            ids[i] = frame.object_id;
+        }
     }
 }
 
-pub fn set_pixel(buffer: &mut [u8], zbuffer: &mut [f32], x: u32, y: u32, z: f32, color: [u8; 4]) {
-    if x >= WIDTH || y >= HEIGHT {
+/// Adds `color` onto the pixel unless something nearer covers it. Depth is left
+/// as it was, so glowing things behind one another all show.
+pub fn add_pixel(frame: &mut Frame, x: u32, y: u32, z: f32, color: [u8; 4]) {
+    if x >= frame.width || y >= frame.height {
         return;
     }
-    let i = (y * WIDTH + x) as usize;
-    if z < zbuffer[i] {
-        zbuffer[i] = z;
+    let i = (y * frame.width + x) as usize;
+    if z < frame.depth[i] {
         let pixel_i = i * 4;
-        buffer[pixel_i..pixel_i + 4].copy_from_slice(&color);
+        for (channel, add) in frame.color[pixel_i..pixel_i + 3].iter_mut().zip(color) {
+            *channel = channel.saturating_add(add);
+        }
+        frame.color[pixel_i + 3] = 255;
+        if let Some(ids) = &mut frame.ids {
+            ids[i] = frame.object_id;
+        }
     }
 }
-/*
-pub fn line(x_s: u32, y_s: u32, x_e: u32, y_e: u32, z_s: f32, z_e: f32, buffer: &mut [u8], zbuffer: &mut [f32], color: [u8; 4]) {
-    let mut x = x_s as f64;
-    let mut y = y_s as f64;
-    let dx = x_s.abs_diff(x_e) as f64;
-    let dy = y_s.abs_diff(y_e) as f64;
-    let max = dx.max(dy);
-    let diff_x = (x_e as i32 - x_s as i32) as f64 / max;
-    let diff_y = (y_e as i32 - y_s as i32) as f64 / max;
+
+pub fn line(start: Point, end: Point, z_s: f32, z_e: f32, frame: &mut Frame, color: [u8; 4]) {
+    let mut x = start.x as f64;
+    let mut y = start.y as f64;
+    let dx = start.x.abs_diff(end.x) as f64;
+    let dy = start.y.abs_diff(end.y) as f64;
+    let max = dx.max(dy).max(1.0);
+    let diff_x = (end.x as f64 - start.x as f64) / max;
+    let diff_y = (end.y as f64 - start.y as f64) / max;
     let diff_z = (z_e - z_s) / max as f32;
     let mut z = z_s;
 
-    for _ in 0..max as i32 {
-        set_pixel(buffer, zbuffer, x as u32, y as u32, z, color);
+    for _ in 0..=max as i32 {
+        // Nudge edges forward so they win against faces at the same depth.
+        set_pixel(frame, x as u32, y as u32, z - 1e-4, color);
         x += diff_x;
         y += diff_y;
         z += diff_z;
     }
 }
 
-
-pub fn triangle_outline(t: Triangle, buffer: &mut [u8], color: [u8; 4]){
-    line(t.p1.x, t.p1.y, t.p2.x, t.p2.y, buffer, color);
-    line(t.p2.x, t.p2.y, t.p3.x, t.p3.y, buffer, color);
-    line(t.p1.x, t.p1.y, t.p3.x, t.p3.y, buffer, color);
+pub fn triangle_outline(t: Triangle, z_values: [f32; 3], frame: &mut Frame, color: [u8; 4]){
+    line(t.p1, t.p2, z_values[0], z_values[1], frame, color);
+    line(t.p2, t.p3, z_values[1], z_values[2], frame, color);
+    line(t.p1, t.p3, z_values[0], z_values[2], frame, color);
 }
-    */
@@ -1,4 +1,4 @@
-use std::ops::Mul;
+use std::ops::{Add, Mul, Sub};
 
 #[derive(Clone,Copy,Debug)]
 pub struct Point{
@@ -32,16 +32,55 @@ impl Mul<f32> for Point3D {
     }
 }
 
+impl Add for Point3D {
+    type Output = Point3D;
+
+    fn add(self, rhs: Point3D) -> Point3D {
+        Point3D {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for Point3D {
+    type Output = Point3D;
+
+    fn sub(self, rhs: Point3D) -> Point3D {
+        Point3D {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
 pub struct Triangle3D {
     pub p1: Point3D,
     pub p2: Point3D,
     pub p3: Point3D,
     pub color: [u8; 3],
+    /// Per-vertex colors, interpolated across the face instead of `color` when present.
+    pub colors: Option<[[u8; 3]; 3]>,
+    /// Per-vertex normals for smooth shading; the face normal is used when absent.
+    pub normals: Option<[Point3D; 3]>,
 }
 
 impl Triangle3D {
     pub fn new(p1: Point3D, p2: Point3D, p3: Point3D, color: [u8; 3]) -> Self{
-        Self {p1, p2, p3, color}
+        Self {p1, p2, p3, color, colors: None, normals: None}
+    }
+
+    pub fn with_colors(mut self, colors: [[u8; 3]; 3]) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    pub fn with_normals(mut self, normals: [Point3D; 3]) -> Self {
+        self.normals = Some(normals);
+        self
     }
 }
 
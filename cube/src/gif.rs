@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::drawing::Frame;
+
+/// Colors per frame; each frame carries its own palette.
+const PALETTE_SIZE: usize = 256;
+
+/// Largest LZW code GIF allows.
+const MAX_CODE: u16 = 4095;
+
+/// Writes frames as an animated GIF that loops forever.
+pub struct GifEncoder<W: Write> {
+    out: W,
+    width: u16,
+    height: u16,
+    /// Time each frame is shown, in hundredths of a second.
+    delay: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// Writes the header for `width`x`height` frames shown `delay` hundredths of a second each.
+    pub fn new(mut out: W, width: u32, height: u32, delay: u16) -> io::Result<Self> {
+        let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(w), Ok(h)) => (w, h),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "GIF frames are at most 65535 pixels wide and high")),
+        };
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        // No global color table, background color 0, square pixels.
+        out.write_all(&[0, 0, 0])?;
+        // Netscape extension: repeat forever.
+        out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(GifEncoder{ out, width, height, delay })
+    }
+
+    /// Quantizes `frame` to its own palette and appends it.
+    pub fn add_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        if frame.width != self.width as u32 || frame.height != self.height as u32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size differs from the GIF's"));
+        }
+        let (palette, indices) = quantize(frame);
+
+        // Graphic control extension with the frame delay.
+        self.out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        self.out.write_all(&self.delay.to_le_bytes())?;
+        self.out.write_all(&[0x00, 0x00])?;
+
+        // Image descriptor with a local color table of 2^8 entries.
+        self.out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.out.write_all(&self.width.to_le_bytes())?;
+        self.out.write_all(&self.height.to_le_bytes())?;
+        self.out.write_all(&[0x87])?;
+        for i in 0..PALETTE_SIZE {
+            self.out.write_all(&palette.get(i).copied().unwrap_or([0, 0, 0]))?;
+        }
+
+        self.out.write_all(&[8])?;
+        for block in lzw_encode(&indices, 8).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0])
+    }
+
+    /// Writes the trailer and hands back the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[0x3b])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reduces the frame's colors to at most 256 by median cut over a 5-bit-per-channel
+/// histogram, returning the palette and each pixel's index into it.
+pub fn quantize(frame: &Frame) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let bin = |p: &[u8]| ((p[0] as usize >> 3) << 10) | ((p[1] as usize >> 3) << 5) | (p[2] as usize >> 3);
+    let mut counts = vec![0u32; 1 << 15];
+    for pixel in frame.color.chunks_exact(4) {
+        counts[bin(pixel)] += 1;
+    }
+
+    let mut boxes = vec![(0..counts.len()).filter(|&b| counts[b] > 0).collect::<Vec<usize>>()];
+    while boxes.len() < PALETTE_SIZE {
+        // Split the box with the most pixels that still has more than one color.
+        let weight = |bins: &Vec<usize>| bins.iter().map(|&b| counts[b] as u64).sum::<u64>();
+        let Some(i) = (0..boxes.len()).filter(|&i| boxes[i].len() > 1).max_by_key(|&i| weight(&boxes[i])) else {
+            break;
+        };
+        let mut bins = boxes.swap_remove(i);
+        let channel = |b: usize, c: usize| (b >> (10 - 5 * c)) & 31;
+        let range = |c: usize| {
+            let (low, high) = bins.iter().fold((31, 0), |(lo, hi), &b| (lo.min(channel(b, c)), hi.max(channel(b, c))));
+            high - low
+        };
+        let widest = (0..3).max_by_key(|&c| range(c)).unwrap_or(0);
+        bins.sort_unstable_by_key(|&b| channel(b, widest));
+
+        // Cut where half the pixels fall on either side, leaving both halves some colors.
+        let half = weight(&bins) / 2;
+        let mut seen = 0;
+        let mut cut = bins.len() / 2;
+        for (k, &b) in bins.iter().enumerate() {
+            seen += counts[b] as u64;
+            if seen >= half {
+                cut = k + 1;
+                break;
+            }
+        }
+        let upper = bins.split_off(cut.clamp(1, bins.len() - 1));
+        boxes.push(bins);
+        boxes.push(upper);
+    }
+
+    let mut palette = Vec::with_capacity(boxes.len());
+    let mut lookup = vec![0u8; counts.len()];
+    for (index, bins) in boxes.iter().enumerate() {
+        let mut sum = [0u64; 3];
+        let mut total = 0u64;
+        for &b in bins {
+            let n = counts[b] as u64;
+            for (c, s) in sum.iter_mut().enumerate() {
+                // Middle of the bin, so dark colors do not drift darker.
+                *s += n * ((((b >> (10 - 5 * c)) & 31) << 3) as u64 + 4);
+            }
+            total += n;
+            lookup[b] = index as u8;
+        }
+        palette.push(sum.map(|s| (s / total.max(1)).min(255) as u8));
+    }
+
+    let indices = frame.color.chunks_exact(4).map(|p| lookup[bin(p)]).collect();
+    (palette, indices)
+}
+
+/// Packs LZW codes least significant bit first, as GIF expects.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// GIF-flavored LZW: variable code sizes up to 12 bits, starting over with a
+/// clear code once the table is full.
+pub fn lzw_encode(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = BitWriter{ bytes: Vec::new(), buffer: 0, bits: 0 };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut size = min_code_size + 1;
+
+    out.write(clear, size);
+    let mut pixels = indices.iter();
+    let Some(&first) = pixels.next() else {
+        out.write(end, size);
+        return out.finish();
+    };
+    let mut prefix = first as u16;
+    for &pixel in pixels {
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+        out.write(prefix, size);
+        if next <= MAX_CODE {
+            table.insert((prefix, pixel), next);
+            next += 1;
+            // The decoder adds each code one step later, so it widens at one past the power of two.
+            if next > (1 << size) && size < 12 {
+                size += 1;
+            }
+        } else {
+            out.write(clear, size);
+            table.clear();
+            next = end + 1;
+            size = min_code_size + 1;
+        }
+        prefix = pixel as u16;
+    }
+    out.write(prefix, size);
+    out.write(end, size);
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference GIF LZW decoder, to check the encoder against.
+    fn lzw_decode(data: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> { (0..clear).map(|i| vec![i as u8]).chain([Vec::new(), Vec::new()]).collect() };
+        let mut table = reset();
+        let mut size = min_code_size + 1;
+        let (mut buffer, mut bits, mut bytes) = (0u32, 0u32, data.iter());
+        let mut previous: Option<usize> = None;
+        let mut out = Vec::new();
+        loop {
+            while bits < size {
+                buffer |= (*bytes.next().expect("data ended before the end code") as u32) << bits;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << size) - 1)) as usize;
+            buffer >>= size;
+            bits -= size;
+
+            if code == clear {
+                table = reset();
+                size = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match (table.get(code), previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(p)) if code == table.len() => {
+                    let mut entry = table[p].clone();
+                    entry.push(table[p][0]);
+                    entry
+                }
+                _ => panic!("code {} not in the table", code),
+            };
+            if let Some(p) = previous.filter(|_| table.len() <= MAX_CODE as usize) {
+                let mut added = table[p].clone();
+                added.push(entry[0]);
+                table.push(added);
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+            out.extend(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn lzw_matches_the_spec_sample() {
+        // The 10x10 sample image from the GIF89a walkthroughs.
+        let rows: [&[u8]; 4] = [&[1, 1, 1, 1, 1, 2, 2, 2, 2, 2], &[1, 1, 1, 0, 0, 0, 0, 2, 2, 2], &[2, 2, 2, 0, 0, 0, 0, 1, 1, 1], &[2, 2, 2, 2, 2, 1, 1, 1, 1, 1]];
+        let indices: Vec<u8> = [0, 0, 0, 1, 1, 2, 2, 3, 3, 3].iter().flat_map(|&r| rows[r].iter().copied()).collect();
+        let expected = [0x8c, 0x2d, 0x99, 0x87, 0x2a, 0x1c, 0xdc, 0x33, 0xa0, 0x02, 0x75, 0xec, 0x95, 0xfa, 0xa8, 0xde, 0x60, 0x8c, 0x04, 0x91, 0x4c, 0x01];
+        assert_eq!(lzw_encode(&indices, 2), expected);
+    }
+
+    #[test]
+    fn lzw_round_trips_through_table_resets() {
+        // A noisy sequence long enough to fill the 12-bit table several times.
+        let mut state = 12345u32;
+        let noise: Vec<u8> = (0..60_000).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        }).collect();
+        assert_eq!(lzw_decode(&lzw_encode(&noise, 8), 8), noise);
+
+        let runs: Vec<u8> = (0..20_000u32).map(|i| ((i / 7) % 4) as u8).collect();
+        assert_eq!(lzw_decode(&lzw_encode(&runs, 2), 2), runs);
+    }
+
+    #[test]
+    fn lzw_of_nothing_is_clear_then_end() {
+        assert_eq!(lzw_decode(&lzw_encode(&[], 8), 8), Vec::<u8>::new());
+        assert_eq!(lzw_decode(&lzw_encode(&[7], 8), 8), vec![7]);
+    }
+
+    #[test]
+    fn quantize_keeps_few_colors_distinct() {
+        let mut frame = Frame::new(4, 1);
+        for (pixel, color) in frame.color.chunks_exact_mut(4).zip([[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 0, 0]]) {
+            pixel[..3].copy_from_slice(&color);
+        }
+        let (palette, indices) = quantize(&frame);
+        assert!(palette.len() <= PALETTE_SIZE);
+        assert_eq!(indices[0], indices[3]);
+        assert!(indices[0] != indices[1] && indices[1] != indices[2]);
+        let red = palette[indices[0] as usize];
+        assert!(red[0] > 240 && red[1] < 16 && red[2] < 16);
+    }
+
+    #[test]
+    fn encoder_writes_header_frames_and_trailer() {
+        let frame = Frame::new(3, 2);
+        let mut gif = GifEncoder::new(Vec::new(), 3, 2, 4).unwrap();
+        gif.add_frame(&frame).unwrap();
+        assert!(gif.add_frame(&Frame::new(2, 2)).is_err());
+        let bytes = gif.finish().unwrap();
+        assert!(bytes.starts_with(b"GIF89a\x03\x00\x02\x00"));
+        assert_eq!(bytes.last(), Some(&0x3b));
+        assert!(GifEncoder::new(Vec::new(), 70_000, 2, 4).is_err());
+    }
+}
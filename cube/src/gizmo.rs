@@ -0,0 +1,436 @@
+use std::f32::consts::PI;
+
+use crate::camera::Camera;
+use crate::drawing::*;
+use crate::geometry::*;
+use crate::objec::Transformable;
+use crate::operations::*;
+use crate::picking::Ray;
+use crate::render::clip_segment;
+use crate::scene::Scene;
+use crate::transform::Snap;
+
+/// Gizmo length on screen, in pixels.
+const GIZMO_PIXELS: f32 = 80.0;
+
+/// How close, in pixels, the cursor must be to grab a handle.
+const GRAB_PIXELS: f32 = 6.0;
+
+const RING_SEGMENTS: usize = 48;
+
+/// Smallest scale a scale drag leaves an object at, so it cannot vanish or turn inside out.
+const MIN_SCALE: f32 = 0.01;
+
+const AXIS_COLORS: [[u8; 4]; 3] = [[230, 60, 60, 255], [60, 210, 60, 255], [70, 110, 255, 255]];
+const CENTER_COLOR: [u8; 4] = [220, 220, 220, 255];
+const ACTIVE_COLOR: [u8; 4] = [255, 220, 0, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// Grabbable part of a gizmo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handle {
+    /// Arrow, or box when scaling, along axis 0, 1 or 2.
+    Axis(usize),
+    /// Square for moving in the plane across the given axis.
+    Plane(usize),
+    /// Ring turning about the given axis.
+    Ring(usize),
+    /// Box at the origin for uniform scaling.
+    Center,
+}
+
+/// Transform handles around the selection, sized to stay the same on screen.
+///
+/// Objects only carry two rotation angles and one uniform scale, so the rotate
+/// gizmo has rings for `angle_x` (the world X axis) and `angle_y` (the object's
+/// own Y axis, tilted by `angle_x`), and every scale handle scales uniformly,
+/// driven by dragging along its axis.
+#[derive(Clone, Copy, Debug)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub origin: Point3D,
+    pub axes: [Point3D; 3],
+    /// Arrow length in world units.
+    pub size: f32,
+}
+
+impl Gizmo {
+    /// Gizmo for the selected objects of `scene` on a `width` pixel wide frame,
+    /// or `None` when nothing is selected or the selection is behind the camera.
+    pub fn new(scene: &Scene, mode: GizmoMode, width: u32) -> Option<Gizmo> {
+        let selection = scene.selection();
+        let first = scene.objects.get(*selection.first()?)?;
+        let sum = selection.iter().fold(Point3D::new(0.0, 0.0, 0.0), |sum, &i| sum + scene.objects[i].aabb().center());
+        let origin = sum * (1.0 / selection.len() as f32);
+
+        let depth = scene.camera.to_view(origin).z;
+        if depth <= 0.0 {
+            return None;
+        }
+        let focal = (width as f32 / 2.0) / (scene.camera.fov.to_radians() / 2.0).tan();
+
+        let axes = match mode {
+            GizmoMode::Rotate => {
+                // `angle_y` turns about the object's Y axis, which `angle_x` tilts.
+                let angle_x = first.angles().0;
+                let origin = Point3D::new(0.0, 0.0, 0.0);
+                [Point3D::new(1.0, 0.0, 0.0), rotate_x(Point3D::new(0.0, 1.0, 0.0), angle_x, origin), Point3D::new(0.0, 0.0, 1.0)]
+            }
+            _ => [Point3D::new(1.0, 0.0, 0.0), Point3D::new(0.0, 1.0, 0.0), Point3D::new(0.0, 0.0, 1.0)],
+        };
+        Some(Gizmo { mode, origin, axes, size: depth * GIZMO_PIXELS / focal })
+    }
+
+    /// World-space line segments making up each handle.
+    pub fn handles(&self) -> Vec<(Handle, Vec<(Point3D, Point3D)>)> {
+        let (o, s) = (self.origin, self.size);
+        let axis = |k: usize| self.axes[k];
+        let mut handles = Vec::new();
+        match self.mode {
+            GizmoMode::Translate => {
+                for k in 0..3 {
+                    let (u, v) = (axis((k + 1) % 3), axis((k + 2) % 3));
+                    let tip = o + axis(k) * s;
+                    let back = tip - axis(k) * (0.15 * s);
+                    handles.push((Handle::Axis(k), vec![
+                        (o, tip),
+                        (tip, back + u * (0.06 * s)), (tip, back - u * (0.06 * s)),
+                        (tip, back + v * (0.06 * s)), (tip, back - v * (0.06 * s)),
+                    ]));
+                    let square = [(0.25, 0.25), (0.45, 0.25), (0.45, 0.45), (0.25, 0.45)].map(|(a, b)| o + u * (a * s) + v * (b * s));
+                    handles.push((Handle::Plane(k), (0..4).map(|i| (square[i], square[(i + 1) % 4])).collect()));
+                }
+            }
+            GizmoMode::Rotate => {
+                for k in 0..2 {
+                    let (b1, b2) = basis(axis(k));
+                    let point = |i: usize| {
+                        let t = 2.0 * PI * i as f32 / RING_SEGMENTS as f32;
+                        o + b1 * (t.cos() * s) + b2 * (t.sin() * s)
+                    };
+                    handles.push((Handle::Ring(k), (0..RING_SEGMENTS).map(|i| (point(i), point(i + 1))).collect()));
+                }
+            }
+            GizmoMode::Scale => {
+                for k in 0..3 {
+                    let tip = o + axis(k) * s;
+                    let mut segments = vec![(o, tip)];
+                    segments.extend(box_edges(tip, 0.06 * s));
+                    handles.push((Handle::Axis(k), segments));
+                }
+                handles.push((Handle::Center, box_edges(o, 0.08 * s)));
+            }
+        }
+        handles
+    }
+
+    /// Draws the gizmo over everything else, with `active` highlighted.
+    pub fn draw(&self, camera: &Camera, frame: &mut Frame, active: Option<Handle>) {
+        for (handle, segments) in self.handles() {
+            let color = match handle {
+                _ if Some(handle) == active => ACTIVE_COLOR,
+                Handle::Axis(k) | Handle::Plane(k) | Handle::Ring(k) => AXIS_COLORS[k],
+                Handle::Center => CENTER_COLOR,
+            };
+            for (a, b) in segments {
+                if let Some((start, end)) = clip_segment(camera.to_view(a), camera.to_view(b), camera.fov, frame.width, frame.height) {
+                    // A depth in front of everything keeps the gizmo visible inside objects.
+                    line(start.0, end.0, -1.0, -1.0, frame, color);
+                }
+            }
+        }
+    }
+
+    /// Handle drawn closest to pixel (`x`, `y`), if any is within grabbing distance.
+    pub fn handle_at(&self, camera: &Camera, x: f32, y: f32, width: u32, height: u32) -> Option<Handle> {
+        self.handles().into_iter()
+            .filter_map(|(handle, segments)| {
+                segments.iter()
+                    .filter_map(|&(a, b)| clip_segment(camera.to_view(a), camera.to_view(b), camera.fov, width, height))
+                    .map(|(start, end)| segment_distance((x, y), start.0, end.0))
+                    .min_by(|a, b| a.total_cmp(b))
+                    .map(|d| (handle, d))
+            })
+            .filter(|&(_, d)| d <= GRAB_PIXELS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    /// Where `ray` meets the handle's constraint: the nearest point on its axis, or
+    /// the hit in its plane.
+    fn grab_point(&self, handle: Handle, ray: &Ray) -> Option<Point3D> {
+        match handle {
+            Handle::Axis(k) => {
+                let t = closest_on_line(self.origin, self.axes[k], ray)?;
+                Some(self.origin + self.axes[k] * t)
+            }
+            Handle::Plane(k) | Handle::Ring(k) => hit_plane(self.origin, self.axes[k], ray),
+            Handle::Center => {
+                // Scale by pulling away from the center in the plane facing the camera.
+                let facing = ray.direction.normalize() * -1.0;
+                hit_plane(self.origin, facing, ray)
+            }
+        }
+    }
+}
+
+/// A handle being dragged: turns cursor movement into transforms of the selection.
+#[derive(Clone, Copy, Debug)]
+pub struct Drag {
+    pub gizmo: Gizmo,
+    pub handle: Handle,
+    snap: Snap,
+    last: Point3D,
+    /// Movement and turn not yet applied because they are short of an increment.
+    carry: Point3D,
+    carry_turn: f32,
+}
+
+impl Drag {
+    pub fn begin(gizmo: Gizmo, handle: Handle, ray: &Ray) -> Option<Drag> {
+        let last = gizmo.grab_point(handle, ray)?;
+        Some(Drag { gizmo, handle, snap: Snap::default(), last, carry: Point3D::new(0.0, 0.0, 0.0), carry_turn: 0.0 })
+    }
+
+    /// Moves and turns in whole grid and angle increments while `snap` is enabled.
+    pub fn with_snap(mut self, snap: Snap) -> Drag {
+        self.snap = snap;
+        self
+    }
+
+    /// Applies the movement since the last update to every selected object.
+    pub fn update(&mut self, scene: &mut Scene, ray: &Ray) {
+        let point = match self.gizmo.grab_point(self.handle, ray) {
+            Some(point) => point,
+            None => return,
+        };
+        let (o, size) = (self.gizmo.origin, self.gizmo.size);
+        let selected = scene.objects.iter_mut().filter(|o| o.is_selected());
+
+        match (self.gizmo.mode, self.handle) {
+            (GizmoMode::Translate, _) => {
+                let total = self.carry + (point - self.last);
+                let grid = self.snap.grid;
+                let ((x, cx), (y, cy), (z, cz)) =
+                    (self.snap.quantize(total.x, grid), self.snap.quantize(total.y, grid), self.snap.quantize(total.z, grid));
+                self.carry = Point3D::new(cx, cy, cz);
+                let delta = Point3D::new(x, y, z);
+                selected.for_each(|object| {
+                    // Land on the grid along the axes that moved, wherever the object started.
+                    let moved = object.position() + delta;
+                    let snapped = self.snap.position(moved);
+                    let pick = |d: f32, snapped: f32, moved: f32| if d != 0.0 { snapped } else { moved };
+                    object.set_position(Point3D::new(pick(x, snapped.x, moved.x), pick(y, snapped.y, moved.y), pick(z, snapped.z, moved.z)));
+                });
+            }
+            (GizmoMode::Rotate, Handle::Ring(k)) => {
+                let (b1, b2) = basis(self.gizmo.axes[k]);
+                let angle = |p: Point3D| (p - o).dot(b2).atan2((p - o).dot(b1));
+                let mut turn = angle(point) - angle(self.last);
+                if turn > PI {
+                    turn -= 2.0 * PI;
+                } else if turn < -PI {
+                    turn += 2.0 * PI;
+                }
+                let (turn, carry) = self.snap.quantize(self.carry_turn + turn, self.snap.angle);
+                self.carry_turn = carry;
+                if turn != 0.0 {
+                    selected.for_each(|object| {
+                        // `rotate_y` turns the opposite way to a right-handed turn about +Y.
+                        let (ax, ay) = object.angles();
+                        if k == 0 {
+                            object.set_angles(self.snap.angle(ax + turn), ay);
+                        } else {
+                            object.set_angles(ax, self.snap.angle(ay - turn));
+                        }
+                    });
+                }
+            }
+            (GizmoMode::Scale, handle) => {
+                let reach = |p: Point3D| match handle {
+                    Handle::Axis(k) => (p - o).dot(self.gizmo.axes[k]),
+                    _ => (p - o).dot(p - o).sqrt(),
+                };
+                let factor = (reach(point) - reach(self.last)) / size;
+                selected.for_each(|object| {
+                    let scale = object.scale_factor();
+                    object.scale((scale * (1.0 + factor)).max(MIN_SCALE) - scale);
+                });
+            }
+            _ => {}
+        }
+        self.last = point;
+    }
+}
+
+/// Two unit vectors completing `axis` to a right-handed frame.
+fn basis(axis: Point3D) -> (Point3D, Point3D) {
+    let axis = axis.normalize();
+    let helper = if axis.x.abs() < 0.9 { Point3D::new(1.0, 0.0, 0.0) } else { Point3D::new(0.0, 1.0, 0.0) };
+    let b1 = axis.cross(helper).normalize();
+    (b1, axis.cross(b1))
+}
+
+fn box_edges(center: Point3D, half: f32) -> Vec<(Point3D, Point3D)> {
+    let corner = |i: usize| center + Point3D::new(
+        if i & 1 == 0 { -half } else { half },
+        if i & 2 == 0 { -half } else { half },
+        if i & 4 == 0 { -half } else { half },
+    );
+    [(0, 1), (2, 3), (4, 5), (6, 7), (0, 2), (1, 3), (4, 6), (5, 7), (0, 4), (1, 5), (2, 6), (3, 7)]
+        .iter().map(|&(a, b)| (corner(a), corner(b))).collect()
+}
+
+/// Parameter of the point on the line `origin + t * axis` closest to `ray`.
+fn closest_on_line(origin: Point3D, axis: Point3D, ray: &Ray) -> Option<f32> {
+    let w = ray.origin - origin;
+    let (a, b, c) = (axis.dot(axis), axis.dot(ray.direction), ray.direction.dot(ray.direction));
+    let (d, e) = (axis.dot(w), ray.direction.dot(w));
+    let denom = a * c - b * b;
+    // A ray looking straight down the axis cannot move along it.
+    if denom.abs() < 1e-6 * a * c {
+        return None;
+    }
+    Some((c * d - b * e) / denom)
+}
+
+fn hit_plane(origin: Point3D, normal: Point3D, ray: &Ray) -> Option<Point3D> {
+    let facing = ray.direction.dot(normal);
+    if facing.abs() < 1e-6 {
+        return None;
+    }
+    let t = (origin - ray.origin).dot(normal) / facing;
+    (t > 0.0).then(|| ray.at(t))
+}
+
+fn segment_distance(p: (f32, f32), a: Point, b: Point) -> f32 {
+    let (ax, ay, bx, by) = (a.x as f32, a.y as f32, b.x as f32, b.y as f32);
+    let (dx, dy) = (bx - ax, by - ay);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 { 0.0 } else { (((p.0 - ax) * dx + (p.1 - ay) * dy) / length).clamp(0.0, 1.0) };
+    let (cx, cy) = (ax + dx * t - p.0, ay + dy * t - p.1);
+    (cx * cx + cy * cy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objec::{Cube, Objects};
+
+    fn p(x: f32, y: f32, z: f32) -> Point3D {
+        Point3D::new(x, y, z)
+    }
+
+    fn close(a: Point3D, b: Point3D) -> bool {
+        (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs() < 1e-4
+    }
+
+    /// A scene with one selected unit cube centered at (0, 0, 5).
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        scene.objects.push(Objects::Cube(Cube::new(p(-0.5, -0.5, 4.5), p(0.5, 0.5, 4.5), [255, 0, 0])));
+        scene.select(0, false);
+        scene
+    }
+
+    #[test]
+    fn closest_on_line_meets_crossing_rays() {
+        // A ray straight down -Y through (2, 5, 0) crosses the X axis at x = 2.
+        let ray = Ray::new(p(2.0, 5.0, 0.0), p(0.0, -1.0, 0.0));
+        assert!((closest_on_line(p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), &ray).unwrap() - 2.0).abs() < 1e-5);
+        // Skew lines: the X axis and a ray along Z at y = 1, x = -3.
+        let ray = Ray::new(p(-3.0, 1.0, -4.0), p(0.0, 0.0, 2.0));
+        assert!((closest_on_line(p(1.0, 0.0, 0.0), p(2.0, 0.0, 0.0), &ray).unwrap() + 2.0).abs() < 1e-5);
+        // Looking straight down the axis gives nothing to slide along.
+        let ray = Ray::new(p(0.0, 0.0, -5.0), p(0.0, 0.0, 1.0));
+        assert!(closest_on_line(p(0.0, 0.0, 0.0), p(0.0, 0.0, 1.0), &ray).is_none());
+    }
+
+    #[test]
+    fn hit_plane_only_in_front_of_the_ray() {
+        let ray = Ray::new(p(1.0, 2.0, 0.0), p(0.0, 0.0, 1.0));
+        assert!(close(hit_plane(p(0.0, 0.0, 5.0), p(0.0, 0.0, -1.0), &ray).unwrap(), p(1.0, 2.0, 5.0)));
+        let slanted = Ray::new(p(0.0, 0.0, 0.0), p(1.0, 0.0, 1.0));
+        assert!(close(hit_plane(p(0.0, 0.0, 3.0), p(0.0, 0.0, 1.0), &slanted).unwrap(), p(3.0, 0.0, 3.0)));
+        assert!(hit_plane(p(0.0, 0.0, -5.0), p(0.0, 0.0, 1.0), &ray).is_none(), "plane behind the ray");
+        assert!(hit_plane(p(0.0, 5.0, 0.0), p(0.0, 1.0, 0.0), &ray).is_none(), "ray parallel to the plane");
+    }
+
+    #[test]
+    fn handle_at_picks_the_handle_under_the_cursor() {
+        let scene = scene();
+        let (width, height) = (320, 240);
+        let gizmo = Gizmo::new(&scene, GizmoMode::Translate, width).unwrap();
+        let pixel = |q: Point3D| {
+            let at = scene.camera.project(q, width, height);
+            (at.x as f32, at.y as f32)
+        };
+        for k in 0..2 {
+            let (x, y) = pixel(gizmo.origin + gizmo.axes[k] * (0.6 * gizmo.size));
+            assert_eq!(gizmo.handle_at(&scene.camera, x, y, width, height), Some(Handle::Axis(k)));
+        }
+        let (x, y) = pixel(gizmo.origin + (gizmo.axes[0] + gizmo.axes[1]) * (0.35 * gizmo.size) + gizmo.axes[0] * (0.1 * gizmo.size));
+        assert_eq!(gizmo.handle_at(&scene.camera, x, y, width, height), Some(Handle::Plane(2)));
+        assert_eq!(gizmo.handle_at(&scene.camera, 5.0, 5.0, width, height), None);
+
+        let gizmo = Gizmo::new(&scene, GizmoMode::Scale, width).unwrap();
+        // The corner of the center box away from the axes, which all start at the origin.
+        let (x, y) = pixel(gizmo.origin - p(0.08, 0.08, 0.08) * gizmo.size);
+        assert_eq!(gizmo.handle_at(&scene.camera, x, y, width, height), Some(Handle::Center));
+    }
+
+    #[test]
+    fn scale_drag_stops_at_a_small_positive_scale() {
+        let mut scene = scene();
+        let gizmo = Gizmo::new(&scene, GizmoMode::Scale, 320).unwrap();
+        let along_x = |x: f32| Ray::new(p(x, -5.0, 5.0), p(0.0, 1.0, 0.0));
+        let mut drag = Drag::begin(gizmo, Handle::Axis(0), &along_x(gizmo.size)).unwrap();
+        // Pulling far past the center would shrink through zero and turn the object inside out.
+        drag.update(&mut scene, &along_x(-10.0 * gizmo.size));
+        assert!((scene.objects[0].scale_factor() - MIN_SCALE).abs() < 1e-6);
+        drag.update(&mut scene, &along_x(-20.0 * gizmo.size));
+        assert!(scene.objects[0].scale_factor() > 0.0);
+
+        let mut drag = Drag::begin(gizmo, Handle::Axis(0), &along_x(0.0)).unwrap();
+        drag.update(&mut scene, &along_x(gizmo.size));
+        assert!((scene.objects[0].scale_factor() - 2.0 * MIN_SCALE).abs() < 1e-6);
+    }
+
+    #[test]
+    fn snapped_drags_land_on_the_grid() {
+        let mut scene = scene();
+        scene.objects[0].move_world(p(0.1, 0.13, 0.0));
+        let start = scene.objects[0].position();
+        let snap = Snap { enabled: true, ..Snap::default() };
+        let gizmo = Gizmo::new(&scene, GizmoMode::Translate, 320).unwrap();
+        let along_x = |x: f32| Ray::new(p(gizmo.origin.x + x, -5.0, gizmo.origin.z), p(0.0, 1.0, 0.0));
+        let mut drag = Drag::begin(gizmo, Handle::Axis(0), &along_x(0.0)).unwrap().with_snap(snap);
+        drag.update(&mut scene, &along_x(0.1));
+        assert_eq!(scene.objects[0].position().x, start.x, "moved short of a grid step");
+        drag.update(&mut scene, &along_x(0.6));
+        let moved = scene.objects[0].position();
+        assert!(((moved.x / snap.grid) - (moved.x / snap.grid).round()).abs() < 1e-4, "x = {} is off the grid", moved.x);
+        assert!(moved.x > start.x);
+        assert_eq!((moved.y, moved.z), (start.y, start.z), "axes the drag did not move on stay put");
+
+        let gizmo = Gizmo::new(&scene, GizmoMode::Rotate, 320).unwrap();
+        let (b1, b2) = basis(gizmo.axes[0]);
+        let toward = |t: f32| {
+            let target = gizmo.origin + b1 * t.cos() + b2 * t.sin();
+            Ray::new(target - gizmo.axes[0] * 5.0, gizmo.axes[0])
+        };
+        scene.objects[0].set_angles(0.1, 0.0);
+        let mut drag = Drag::begin(gizmo, Handle::Ring(0), &toward(0.0)).unwrap().with_snap(snap);
+        drag.update(&mut scene, &toward(0.5));
+        let (ax, ay) = scene.objects[0].angles();
+        assert!(((ax / snap.angle) - (ax / snap.angle).round()).abs() < 1e-4, "angle_x = {} is off the increments", ax);
+        assert!(ax > 0.1);
+        assert_eq!(ay, 0.0);
+    }
+}
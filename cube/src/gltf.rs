@@ -0,0 +1,778 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::geometry::*;
+use crate::json::Json;
+use crate::mesh::Mesh;
+use crate::objec::Objects;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const FLOAT: usize = 5126;
+const ARRAY_BUFFER: usize = 34962;
+const ELEMENT_ARRAY_BUFFER: usize = 34963;
+/// Most node visits one import may make, so shared children cannot blow up the walk.
+const MAX_NODE_VISITS: usize = 10_000;
+/// Deepest node hierarchy followed.
+const MAX_NODE_DEPTH: usize = 64;
+/// Largest accessor filled with zeros when it has no bufferView to bound its size.
+const MAX_ZEROED_COUNT: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum GltfError{
+    Io(std::io::Error),
+    Json(String),
+    Invalid(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(err) => write!(f, "i/o error: {}", err),
+            GltfError::Json(msg) => write!(f, "invalid JSON: {}", msg),
+            GltfError::Invalid(msg) => write!(f, "invalid glTF: {}", msg),
+            GltfError::Unsupported(msg) => write!(f, "unsupported glTF feature: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<std::io::Error> for GltfError {
+    fn from(err: std::io::Error) -> Self {
+        GltfError::Io(err)
+    }
+}
+
+fn invalid<T>(msg: impl Into<String>) -> Result<T, GltfError> {
+    Err(GltfError::Invalid(msg.into()))
+}
+
+/// glTF is +Y up and looks down -Z; the viewer has +Y down and looks down +Z.
+/// A half turn about X maps one onto the other and is its own inverse.
+fn from_gltf_axes(p: [f32; 3]) -> Point3D {
+    Point3D::new(p[0], -p[1], -p[2])
+}
+
+fn to_gltf_axes(p: Point3D) -> [f32; 3] {
+    [p.x, -p.y, -p.z]
+}
+
+// ---------------------------------------------------------------- import
+
+/// Loads a `.gltf` (with external or embedded buffers) or `.glb` file.
+///
+/// Every mesh primitive reachable from the default scene becomes a `Mesh` with the
+/// node hierarchy's transforms baked into its vertices.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Objects>, GltfError> {
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    let (doc, bin) = if data.len() >= 4 && u32::from_le_bytes([data[0], data[1], data[2], data[3]]) == GLB_MAGIC {
+        split_glb(&data)?
+    } else {
+        let text = std::str::from_utf8(&data).map_err(|_| GltfError::Json("file is not UTF-8".into()))?;
+        (Json::parse(text).map_err(GltfError::Json)?, None)
+    };
+
+    let buffers = load_buffers(&doc, bin, base)?;
+    Document { json: &doc, buffers }.scene_objects()
+}
+
+fn split_glb(data: &[u8]) -> Result<(Json, Option<Vec<u8>>), GltfError> {
+    let word = |at: usize| -> Result<u32, GltfError> {
+        match data.get(at..at + 4) {
+            Some(b) => Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            None => invalid("GLB ended inside a header"),
+        }
+    };
+    if word(4)? != 2 {
+        return Err(GltfError::Unsupported(format!("GLB version {}", word(4)?)));
+    }
+    let length = (word(8)? as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut pos = 12;
+    while pos + 8 <= length {
+        let chunk_len = word(pos)? as usize;
+        let chunk_type = word(pos + 4)?;
+        let chunk = match data.get(pos + 8..pos + 8 + chunk_len) {
+            Some(c) => c,
+            None => return invalid("GLB chunk runs past end of file"),
+        };
+        match chunk_type {
+            CHUNK_JSON => {
+                let text = std::str::from_utf8(chunk).map_err(|_| GltfError::Json("JSON chunk is not UTF-8".into()))?;
+                json = Some(Json::parse(text.trim_end_matches(['\0', ' '])).map_err(GltfError::Json)?);
+            }
+            CHUNK_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+            _ => {}
+        }
+        pos += 8 + chunk_len.next_multiple_of(4);
+    }
+
+    match json {
+        Some(json) => Ok((json, bin)),
+        None => invalid("GLB has no JSON chunk"),
+    }
+}
+
+fn load_buffers(doc: &Json, mut bin: Option<Vec<u8>>, base: &Path) -> Result<Vec<Vec<u8>>, GltfError> {
+    let mut buffers = Vec::new();
+    for (i, buffer) in doc.get("buffers").and_then(Json::as_array).unwrap_or(&[]).iter().enumerate() {
+        let data = match buffer.get("uri").and_then(Json::as_str) {
+            Some(uri) if uri.starts_with("data:") => {
+                let (_, payload) = match uri.split_once(";base64,") {
+                    Some(parts) => parts,
+                    None => return Err(GltfError::Unsupported("data URI without base64".into())),
+                };
+                match base64_decode(payload) {
+                    Some(bytes) => bytes,
+                    None => return invalid(format!("buffer {} has malformed base64", i)),
+                }
+            }
+            Some(uri) => {
+                if uri.contains("://") {
+                    return Err(GltfError::Unsupported(format!("remote buffer '{}'", uri)));
+                }
+                fs::read(base.join(percent_decode(uri)))?
+            }
+            None if i == 0 && bin.is_some() => bin.take().unwrap_or_default(),
+            None => return invalid(format!("buffer {} has no data", i)),
+        };
+        let length = buffer.get("byteLength").and_then(Json::as_usize).unwrap_or(data.len());
+        if data.len() < length {
+            return invalid(format!("buffer {} is shorter than its byteLength", i));
+        }
+        buffers.push(data);
+    }
+    Ok(buffers)
+}
+
+/// State of the walk down the node hierarchy.
+struct Walk {
+    /// Nodes from the root to the one being visited.
+    path: Vec<usize>,
+    visits: usize,
+    objects: Vec<Objects>,
+}
+
+struct Document<'a> {
+    json: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Document<'_> {
+    fn item(&self, list: &str, index: usize) -> Result<&Json, GltfError> {
+        match self.json.get(list).and_then(Json::as_array).and_then(|items| items.get(index)) {
+            Some(item) => Ok(item),
+            None => invalid(format!("{}[{}] does not exist", list, index)),
+        }
+    }
+
+    fn scene_objects(&self) -> Result<Vec<Objects>, GltfError> {
+        let roots: Vec<usize> = match self.json.get("scenes").and_then(Json::as_array) {
+            Some(scenes) if !scenes.is_empty() => {
+                let index = self.json.get("scene").and_then(Json::as_usize).unwrap_or(0);
+                let scene = scenes.get(index).ok_or_else(|| GltfError::Invalid(format!("scene {} does not exist", index)))?;
+                scene.get("nodes").and_then(Json::as_array).unwrap_or(&[]).iter().filter_map(Json::as_usize).collect()
+            }
+            _ => {
+                // No scenes: every node that is nobody's child is a root.
+                let nodes = self.json.get("nodes").and_then(Json::as_array).unwrap_or(&[]);
+                let children: Vec<usize> = nodes.iter()
+                    .flat_map(|n| n.get("children").and_then(Json::as_array).unwrap_or(&[]).iter().filter_map(Json::as_usize))
+                    .collect();
+                (0..nodes.len()).filter(|i| !children.contains(i)).collect()
+            }
+        };
+
+        let mut walk = Walk{ path: Vec::new(), visits: 0, objects: Vec::new() };
+        for root in roots {
+            self.visit_node(root, IDENTITY, &mut walk)?;
+        }
+        Ok(walk.objects)
+    }
+
+    fn visit_node(&self, index: usize, parent: Mat4, walk: &mut Walk) -> Result<(), GltfError> {
+        if walk.path.contains(&index) {
+            return invalid(format!("node {} is its own ancestor", index));
+        }
+        if walk.path.len() >= MAX_NODE_DEPTH {
+            return invalid("node hierarchy is too deep");
+        }
+        walk.visits += 1;
+        if walk.visits > MAX_NODE_VISITS {
+            return invalid(format!("node hierarchy reaches more than {} nodes", MAX_NODE_VISITS));
+        }
+        let node = self.item("nodes", index)?;
+        let world = mat_mul(&parent, &node_matrix(node)?);
+
+        if let Some(mesh) = node.get("mesh").and_then(Json::as_usize) {
+            for primitive in self.item("meshes", mesh)?.get("primitives").and_then(Json::as_array).unwrap_or(&[]) {
+                if let Some(mesh) = self.primitive_mesh(primitive, &world)? {
+                    walk.objects.push(Objects::Mesh(mesh));
+                }
+            }
+        }
+        walk.path.push(index);
+        for child in node.get("children").and_then(Json::as_array).unwrap_or(&[]).iter().filter_map(Json::as_usize) {
+            self.visit_node(child, world, walk)?;
+        }
+        walk.path.pop();
+        Ok(())
+    }
+
+    fn primitive_mesh(&self, primitive: &Json, world: &Mat4) -> Result<Option<Mesh>, GltfError> {
+        let mode = primitive.get("mode").and_then(Json::as_usize).unwrap_or(4);
+        if !(4..=6).contains(&mode) {
+            // Points and lines have nothing to fill.
+            return Ok(None);
+        }
+        let attributes = primitive.get("attributes");
+        let position = match attributes.and_then(|a| a.get("POSITION")).and_then(Json::as_usize) {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        let positions = self.read_accessor(position)?;
+        let vertices: Vec<Point3D> = positions.iter()
+            .map(|p| from_gltf_axes(mat_transform(world, [p[0], p[1], p[2]])))
+            .collect();
+
+        let base_color = match primitive.get("material").and_then(Json::as_usize) {
+            Some(material) => self.item("materials", material)?
+                .get("pbrMetallicRoughness").and_then(|pbr| pbr.get("baseColorFactor")).and_then(Json::as_f32_vec)
+                .filter(|factor| factor.len() >= 3)
+                .unwrap_or_else(|| vec![1.0; 4]),
+            None => vec![1.0; 4],
+        };
+        let to_byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let color = [to_byte(base_color[0]), to_byte(base_color[1]), to_byte(base_color[2])];
+
+        let indices: Vec<usize> = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(accessor) => self.read_accessor(accessor)?.iter().map(|v| v[0] as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+        let mut faces = Vec::new();
+        match mode {
+            4 => faces.extend(indices.chunks_exact(3).map(|c| [c[0], c[1], c[2]])),
+            5 => {
+                for i in 0..indices.len().saturating_sub(2) {
+                    if i % 2 == 0 {
+                        faces.push([indices[i], indices[i + 1], indices[i + 2]]);
+                    } else {
+                        faces.push([indices[i + 1], indices[i], indices[i + 2]]);
+                    }
+                }
+            }
+            _ => {
+                for i in 1..indices.len().saturating_sub(1) {
+                    faces.push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+        }
+        if faces.iter().flatten().any(|&i| i >= vertices.len()) {
+            return invalid("primitive index out of range");
+        }
+
+        let mut mesh = Mesh::new(vertices, faces, color);
+        if let Some(accessor) = attributes.and_then(|a| a.get("COLOR_0")).and_then(Json::as_usize) {
+            let colors: Vec<[u8; 3]> = self.read_accessor(accessor)?.iter()
+                .map(|c| [to_byte(c[0] * base_color[0]), to_byte(c[1] * base_color[1]), to_byte(c[2] * base_color[2])])
+                .collect();
+            if colors.len() != mesh.vertices.len() {
+                return invalid("COLOR_0 and POSITION counts differ");
+            }
+            mesh = mesh.with_colors(colors);
+        }
+        Ok(Some(mesh))
+    }
+
+    /// Reads an accessor as float tuples, normalizing integer components when flagged.
+    fn read_accessor(&self, index: usize) -> Result<Vec<[f32; 4]>, GltfError> {
+        let accessor = self.item("accessors", index)?;
+        if accessor.get("sparse").is_some() {
+            return Err(GltfError::Unsupported("sparse accessors".into()));
+        }
+        let count = accessor.get("count").and_then(Json::as_usize).unwrap_or(0);
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            other => return Err(GltfError::Unsupported(format!("accessor type {:?}", other))),
+        };
+        let component_type = accessor.get("componentType").and_then(Json::as_usize).unwrap_or(0);
+        let component_size = match component_type {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | FLOAT => 4,
+            other => return invalid(format!("component type {}", other)),
+        };
+        let normalized = accessor.get("normalized") == Some(&Json::Bool(true));
+
+        let view_index = match accessor.get("bufferView").and_then(Json::as_usize) {
+            Some(v) => v,
+            // No view means all zeros.
+            None if count > MAX_ZEROED_COUNT => return invalid(format!("accessor {} has too many elements", index)),
+            None => return Ok(vec![[0.0; 4]; count]),
+        };
+        let view = self.item("bufferViews", view_index)?;
+        let buffer_index = view.get("buffer").and_then(Json::as_usize).unwrap_or(0);
+        let buffer = match self.buffers.get(buffer_index) {
+            Some(b) => b,
+            None => return invalid(format!("buffer {} does not exist", buffer_index)),
+        };
+        let view_offset = view.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let view_length = view.get("byteLength").and_then(Json::as_usize).unwrap_or(0);
+        let view_data = match buffer.get(view_offset..view_offset.saturating_add(view_length)) {
+            Some(d) => d,
+            None => return invalid(format!("bufferView {} runs past its buffer", view_index)),
+        };
+        let element_size = components * component_size;
+        let stride = view.get("byteStride").and_then(Json::as_usize).unwrap_or(element_size).max(element_size);
+        let offset = accessor.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let end = match count.checked_sub(1) {
+            Some(last) => last.checked_mul(stride).and_then(|n| n.checked_add(offset)).and_then(|n| n.checked_add(element_size)),
+            None => Some(0),
+        };
+        if end.is_none_or(|end| end > view_data.len()) {
+            return invalid(format!("accessor {} runs past its bufferView", index));
+        }
+
+        let mut out = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut value = [0.0, 0.0, 0.0, 1.0];
+            for (c, slot) in value.iter_mut().enumerate().take(components) {
+                let at = offset + i * stride + c * component_size;
+                let b = &view_data[at..at + component_size];
+                *slot = match component_type {
+                    5120 => if normalized { (b[0] as i8 as f32 / 127.0).max(-1.0) } else { b[0] as i8 as f32 },
+                    5121 => if normalized { b[0] as f32 / 255.0 } else { b[0] as f32 },
+                    5122 => {
+                        let v = i16::from_le_bytes([b[0], b[1]]) as f32;
+                        if normalized { (v / 32767.0).max(-1.0) } else { v }
+                    }
+                    5123 => {
+                        let v = u16::from_le_bytes([b[0], b[1]]) as f32;
+                        if normalized { v / 65535.0 } else { v }
+                    }
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                };
+            }
+            if value.iter().any(|v| !v.is_finite()) {
+                return invalid(format!("accessor {} has a non-finite value", index));
+            }
+            out.push(value);
+        }
+        Ok(out)
+    }
+}
+
+// ---------------------------------------------------------------- export
+
+/// Writes the scene as `.glb`, or as `.gltf` plus a `.bin` next to it.
+///
+/// Each object becomes one node whose translation is the object's pivot, with its
+/// current rotation and scale baked into the mesh.
+pub fn save(path: impl AsRef<Path>, scene: &[Objects]) -> Result<(), GltfError> {
+    let path = path.as_ref();
+    let binary = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("glb"));
+
+    let mut bin = Vec::new();
+    let mut nodes = Vec::new();
+    let mut meshes = Vec::new();
+    let mut materials = Vec::new();
+    let mut accessors = Vec::new();
+    let mut views = Vec::new();
+
+    for (index, object) in scene.iter().enumerate() {
+        let triangles = object.triangles();
+        if triangles.is_empty() {
+            continue;
+        }
+        let points: Vec<Point3D> = triangles.iter().flat_map(|t| [t.p1, t.p2, t.p3]).collect();
+        let pivot = points.iter().fold(Point3D::new(0.0, 0.0, 0.0), |sum, p| sum + *p) * (1.0 / points.len() as f32);
+
+        let positions: Vec<[f32; 3]> = points.iter().map(|p| to_gltf_axes(*p - pivot)).collect();
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for p in &positions {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let position_accessor = accessors.len();
+        views.push(push_view(&mut bin, positions.iter().flatten().flat_map(|v| v.to_le_bytes()), ARRAY_BUFFER));
+        accessors.push(Json::Object(vec![
+            ("bufferView".into(), (views.len() - 1).into()),
+            ("componentType".into(), FLOAT.into()),
+            ("count".into(), positions.len().into()),
+            ("type".into(), "VEC3".into()),
+            ("min".into(), float_array(&min)),
+            ("max".into(), float_array(&max)),
+        ]));
+
+        let mut attributes = vec![("POSITION".to_string(), position_accessor.into())];
+        let vertex_colors = triangles.iter().any(|t| t.colors.is_some());
+        if vertex_colors {
+            let colors: Vec<f32> = triangles.iter()
+                .flat_map(|t| t.colors.unwrap_or([t.color; 3]))
+                .flat_map(|c| c.map(|v| v as f32 / 255.0))
+                .collect();
+            views.push(push_view(&mut bin, colors.iter().flat_map(|v| v.to_le_bytes()), ARRAY_BUFFER));
+            attributes.push(("COLOR_0".into(), accessors.len().into()));
+            accessors.push(Json::Object(vec![
+                ("bufferView".into(), (views.len() - 1).into()),
+                ("componentType".into(), FLOAT.into()),
+                ("count".into(), positions.len().into()),
+                ("type".into(), "VEC3".into()),
+            ]));
+        }
+
+        let indices: Vec<u32> = (0..positions.len() as u32).collect();
+        views.push(push_view(&mut bin, indices.iter().flat_map(|v| v.to_le_bytes()), ELEMENT_ARRAY_BUFFER));
+        let index_accessor = accessors.len();
+        accessors.push(Json::Object(vec![
+            ("bufferView".into(), (views.len() - 1).into()),
+            ("componentType".into(), 5125usize.into()),
+            ("count".into(), indices.len().into()),
+            ("type".into(), "SCALAR".into()),
+        ]));
+
+        // COLOR_0 is multiplied by the base color, so keep it neutral when present.
+        let color = if vertex_colors { [255; 3] } else { triangles[0].color };
+        materials.push(Json::Object(vec![
+            ("pbrMetallicRoughness".into(), Json::Object(vec![
+                ("baseColorFactor".into(), float_array(&[color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0, 1.0])),
+                ("metallicFactor".into(), 0.0.into()),
+            ])),
+            // The viewer does not cull back faces.
+            ("doubleSided".into(), Json::Bool(true)),
+        ]));
+        meshes.push(Json::Object(vec![
+            ("primitives".into(), Json::Array(vec![Json::Object(vec![
+                ("attributes".into(), Json::Object(attributes)),
+                ("indices".into(), index_accessor.into()),
+                ("material".into(), (materials.len() - 1).into()),
+            ])])),
+        ]));
+        nodes.push(Json::Object(vec![
+            ("name".into(), object_name(object, index).as_str().into()),
+            ("mesh".into(), (meshes.len() - 1).into()),
+            ("translation".into(), float_array(&to_gltf_axes(pivot))),
+        ]));
+    }
+
+    let bin_name = path.with_extension("bin");
+    let mut buffer = vec![("byteLength".to_string(), bin.len().into())];
+    if !binary {
+        let name = bin_name.file_name().and_then(|n| n.to_str()).unwrap_or("scene.bin");
+        buffer.push(("uri".into(), name.into()));
+    }
+
+    let doc = Json::Object(vec![
+        ("asset".into(), Json::Object(vec![
+            ("version".into(), "2.0".into()),
+            ("generator".into(), "cube".into()),
+        ])),
+        ("scene".into(), 0usize.into()),
+        ("scenes".into(), Json::Array(vec![Json::Object(vec![
+            ("nodes".into(), Json::Array((0..nodes.len()).map(Json::from).collect())),
+        ])])),
+        ("nodes".into(), Json::Array(nodes)),
+        ("meshes".into(), Json::Array(meshes)),
+        ("materials".into(), Json::Array(materials)),
+        ("accessors".into(), Json::Array(accessors)),
+        ("bufferViews".into(), Json::Array(views)),
+        ("buffers".into(), Json::Array(vec![Json::Object(buffer)])),
+    ]);
+
+    if binary {
+        let mut json = doc.to_string().into_bytes();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut out = Vec::with_capacity(total);
+        out.extend(GLB_MAGIC.to_le_bytes());
+        out.extend(2u32.to_le_bytes());
+        out.extend((total as u32).to_le_bytes());
+        out.extend((json.len() as u32).to_le_bytes());
+        out.extend(CHUNK_JSON.to_le_bytes());
+        out.extend(json);
+        out.extend((bin.len() as u32).to_le_bytes());
+        out.extend(CHUNK_BIN.to_le_bytes());
+        out.extend(bin);
+        fs::write(path, out)?;
+    } else {
+        fs::write(&bin_name, &bin)?;
+        fs::write(path, doc.to_string())?;
+    }
+    Ok(())
+}
+
+fn object_name(object: &Objects, index: usize) -> String {
+    format!("{}.{}", object.kind(), index)
+}
+
+/// Appends 4-byte aligned data to the binary buffer and returns its bufferView.
+fn push_view(bin: &mut Vec<u8>, data: impl Iterator<Item = u8>, target: usize) -> Json {
+    let offset = bin.len();
+    bin.extend(data);
+    let length = bin.len() - offset;
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+    Json::Object(vec![
+        ("buffer".into(), 0usize.into()),
+        ("byteOffset".into(), offset.into()),
+        ("byteLength".into(), length.into()),
+        ("target".into(), target.into()),
+    ])
+}
+
+fn float_array(values: &[f32]) -> Json {
+    Json::Array(values.iter().map(|&v| Json::Number(v as f64)).collect())
+}
+
+// ---------------------------------------------------------------- math
+
+/// Column-major 4x4 matrix, as glTF stores them.
+type Mat4 = [f32; 16];
+
+const IDENTITY: Mat4 = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+fn mat_mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+fn mat_transform(m: &Mat4, p: [f32; 3]) -> [f32; 3] {
+    let w = m[3] * p[0] + m[7] * p[1] + m[11] * p[2] + m[15];
+    let w = if w.abs() > f32::EPSILON { w } else { 1.0 };
+    [
+        (m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12]) / w,
+        (m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13]) / w,
+        (m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14]) / w,
+    ]
+}
+
+fn node_matrix(node: &Json) -> Result<Mat4, GltfError> {
+    if let Some(matrix) = node.get("matrix") {
+        return match matrix.as_f32_vec() {
+            Some(m) if m.len() == 16 && m.iter().all(|v| v.is_finite()) => Ok(m.try_into().unwrap_or(IDENTITY)),
+            _ => invalid("node matrix must have 16 finite numbers"),
+        };
+    }
+
+    let read = |key: &str, default: &[f32]| -> Result<Vec<f32>, GltfError> {
+        match node.get(key) {
+            None => Ok(default.to_vec()),
+            Some(v) => match v.as_f32_vec() {
+                Some(values) if values.len() == default.len() => Ok(values),
+                _ => invalid(format!("node {} has the wrong length", key)),
+            },
+        }
+    };
+    let t = read("translation", &[0.0, 0.0, 0.0])?;
+    let r = read("rotation", &[0.0, 0.0, 0.0, 1.0])?;
+    let s = read("scale", &[1.0, 1.0, 1.0])?;
+
+    if t.iter().chain(&r).chain(&s).any(|v| !v.is_finite()) {
+        return invalid("node transform has a non-finite value");
+    }
+    let (x, y, z, w) = (r[0], r[1], r[2], r[3]);
+    Ok([
+        (1.0 - 2.0 * (y * y + z * z)) * s[0], (2.0 * (x * y + z * w)) * s[0], (2.0 * (x * z - y * w)) * s[0], 0.0,
+        (2.0 * (x * y - z * w)) * s[1], (1.0 - 2.0 * (x * x + z * z)) * s[1], (2.0 * (y * z + x * w)) * s[1], 0.0,
+        (2.0 * (x * z + y * w)) * s[2], (2.0 * (y * z - x * w)) * s[2], (1.0 - 2.0 * (x * x + y * y)) * s[2], 0.0,
+        t[0], t[1], t[2], 1.0,
+    ])
+}
+
+// ---------------------------------------------------------------- uri helpers
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        if let (b'%', Some(v)) = (bytes[i], escaped) {
+            out.push(v);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cube-gltf-{}-{}", std::process::id(), name))
+    }
+
+    fn quad() -> Objects {
+        let vertices = vec![
+            Point3D::new(0.0, 0.0, 2.0), Point3D::new(1.0, 0.0, 2.0),
+            Point3D::new(1.0, 1.0, 3.0), Point3D::new(0.0, 1.0, 3.0),
+        ];
+        Objects::Mesh(Mesh::new(vertices, vec![[0, 1, 2], [0, 2, 3]], [200, 40, 10]))
+    }
+
+    fn assert_same_triangles(a: &Objects, b: &Objects) {
+        let (a, b) = (a.triangles(), b.triangles());
+        assert_eq!(a.len(), b.len());
+        for (s, t) in a.iter().zip(&b) {
+            for (p, q) in [(s.p1, t.p1), (s.p2, t.p2), (s.p3, t.p3)] {
+                let d = p - q;
+                assert!(d.x.abs() + d.y.abs() + d.z.abs() < 1e-4, "{:?} != {:?}", p, q);
+            }
+            assert_eq!(s.color, t.color);
+        }
+    }
+
+    #[test]
+    fn glb_round_trip_keeps_triangles() {
+        let path = temp_path("round.glb");
+        save(&path, &[quad()]).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(loaded.len(), 1);
+        assert_same_triangles(&quad(), &loaded[0]);
+    }
+
+    #[test]
+    fn gltf_round_trip_uses_side_buffer() {
+        let path = temp_path("round.gltf");
+        save(&path, &[quad(), quad()]).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).ok();
+        fs::remove_file(path.with_extension("bin")).ok();
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_same_triangles(&quad(), &loaded[1]);
+    }
+
+    fn load_text(name: &str, text: &str) -> Result<Vec<Objects>, GltfError> {
+        let path = temp_path(name);
+        fs::write(&path, text).unwrap();
+        let result = load(&path);
+        fs::remove_file(&path).ok();
+        result
+    }
+
+    fn with_accessor(accessor: &str) -> String {
+        format!(r#"{{"asset": {{"version": "2.0"}}, "scene": 0, "scenes": [{{"nodes": [0]}}], "nodes": [{{"mesh": 0}}],
+            "meshes": [{{"primitives": [{{"attributes": {{"POSITION": 0}}}}]}}],
+            "buffers": [{{"byteLength": 12, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAA"}}],
+            "bufferViews": [{{"buffer": 0, "byteLength": 12}}],
+            "accessors": [{}]}}"#, accessor)
+    }
+
+    #[test]
+    fn rejects_accessors_past_their_view() {
+        for (i, accessor) in [
+            r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 2}"#,
+            r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1e19}"#,
+            r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1, "byteOffset": 1e19}"#,
+            r#"{"componentType": 5126, "type": "VEC3", "count": 1e15}"#,
+        ].iter().enumerate() {
+            let result = load_text(&format!("bad{}.gltf", i), &with_accessor(accessor));
+            assert!(matches!(result, Err(GltfError::Invalid(_))), "{}", accessor);
+        }
+    }
+
+    #[test]
+    fn rejects_non_finite_values() {
+        let nan = with_accessor(r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1}"#)
+            .replace("AAAAAAAAAAAAAAAA", "AADAfwAAwH8AAMB/");
+        assert!(matches!(load_text("nan.gltf", &nan), Err(GltfError::Invalid(_))));
+        let far = with_accessor(r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1}"#)
+            .replace(r#"{"mesh": 0}"#, r#"{"mesh": 0, "translation": [1e39, 0, 0]}"#);
+        assert!(matches!(load_text("far.gltf", &far), Err(GltfError::Invalid(_))));
+    }
+
+    fn with_nodes(nodes: &[String]) -> String {
+        with_accessor(r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1}"#)
+            .replace(r#""nodes": [{"mesh": 0}]"#, &format!(r#""nodes": [{}]"#, nodes.join(", ")))
+    }
+
+    #[test]
+    fn shared_children_fail_instead_of_blowing_up() {
+        // Each node lists the next one twice, doubling the paths at every level.
+        let nodes: Vec<String> = (0..40).map(|i| match i {
+            39 => r#"{"mesh": 0}"#.to_string(),
+            i => format!(r#"{{"children": [{}, {}]}}"#, i + 1, i + 1),
+        }).collect();
+        let started = std::time::Instant::now();
+        assert!(matches!(load_text("dag.gltf", &with_nodes(&nodes)), Err(GltfError::Invalid(_))));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn rejects_cyclic_nodes() {
+        let nodes = [r#"{"children": [1]}"#.to_string(), r#"{"children": [0], "mesh": 0}"#.to_string()];
+        assert!(matches!(load_text("cycle.gltf", &with_nodes(&nodes)), Err(GltfError::Invalid(_))));
+
+        let nodes = [r#"{"children": [1]}"#.to_string(), r#"{"mesh": 0}"#.to_string()];
+        assert!(load_text("tree.gltf", &with_nodes(&nodes)).is_ok());
+    }
+
+    #[test]
+    fn rejects_broken_documents() {
+        assert!(matches!(load_text("json.gltf", "{\"asset\": "), Err(GltfError::Json(_))));
+        assert!(load_text("glb.glb", "glTF\x02\0\0\0").is_err());
+        assert!(load(temp_path("missing.gltf")).is_err());
+    }
+}
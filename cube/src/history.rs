@@ -0,0 +1,596 @@
+use std::time::{Duration, Instant};
+
+use crate::animation::{self, Easing, Property};
+use crate::geometry::*;
+use crate::objec::{Objects, Transformable};
+use crate::physics::Body;
+use crate::scene::Scene;
+use crate::transform::{Exact, Snap};
+
+/// Edits of the same kind to the same objects closer together than this share one entry.
+const COALESCE_WINDOW: Duration = Duration::from_millis(1000);
+
+/// Oldest entries are dropped past this many.
+const HISTORY_LIMIT: usize = 256;
+
+/// Scene edit that can be undone. All but `Spawn` and `Insert` act on the selected objects.
+#[derive(Clone)]
+pub enum Edit {
+    /// Adds the object at the end of the scene and selects it alone.
+    Spawn(Box<Objects>),
+    /// Adds the objects at the end of the scene and selects just them, e.g. when pasting.
+    Insert(Vec<Objects>),
+    Delete,
+    /// Shifts each selected object this many places through the scene's object
+    /// list, stopping at either end or next to another selected object.
+    Reorder(isize),
+    /// World offset; with snapping on, to the next grid lines that way instead.
+    Move(Point3D, Snap),
+    /// Turn in radians; with snapping on, to the next angle increments that way instead.
+    Rotate(f32, f32, Snap),
+    Scale(f32),
+    Color([u8; 3]),
+    /// Mass for the physics simulation; zero pins the objects in place.
+    Mass(f32),
+    /// Typed position, angles or scale.
+    Set(Exact),
+    /// Keys every property of the selected objects at this time, as they are now.
+    Key(f32, Easing),
+    /// Removes the selected objects' keys at this time.
+    Unkey(f32),
+}
+
+/// What an entry did, so runs of small steps can be merged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditKind {
+    Spawn,
+    Delete,
+    Reorder,
+    Move,
+    Rotate,
+    Scale,
+    Color,
+    Mass,
+    Key,
+}
+
+impl EditKind {
+    fn coalesces(self) -> bool {
+        !matches!(self, EditKind::Spawn | EditKind::Delete | EditKind::Reorder | EditKind::Key)
+    }
+}
+
+#[derive(Clone)]
+enum Change {
+    Replace { index: usize, before: Box<Objects>, after: Box<Objects> },
+    Insert { index: usize, object: Objects },
+    Remove { index: usize, object: Objects },
+}
+
+#[derive(Clone)]
+struct Entry {
+    kind: EditKind,
+    changes: Vec<Change>,
+    time: Instant,
+    sealed: bool,
+}
+
+impl Entry {
+    /// Whether the entry adds, removes or reorders objects rather than only changing them.
+    fn moves_indices(&self) -> bool {
+        self.changes.iter().any(|c| !matches!(c, Change::Replace { .. }))
+    }
+
+    fn replaced(&self) -> Vec<usize> {
+        self.changes.iter().filter_map(|c| match c {
+            Change::Replace { index, .. } => Some(*index),
+            _ => None,
+        }).collect()
+    }
+}
+
+/// Undo and redo stacks of scene edits.
+///
+/// Every change to `scene.objects` must go through here for the recorded indices
+/// to stay valid; replacing the whole scene calls for `clear`.
+#[derive(Clone, Default)]
+pub struct History {
+    undo: Vec<Entry>,
+    redo: Vec<Entry>,
+    layout: u64,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Applies `edit` to `scene` and records it.
+    pub fn execute(&mut self, scene: &mut Scene, edit: Edit) {
+        let selection = scene.selection();
+        match edit {
+            Edit::Spawn(object) => self.execute(scene, Edit::Insert(vec![*object])),
+            Edit::Insert(objects) => {
+                if objects.is_empty() {
+                    return;
+                }
+                scene.deselect_all();
+                let changes = objects.into_iter().map(|mut object| {
+                    object.select();
+                    scene.objects.push(object.clone());
+                    Change::Insert { index: scene.objects.len() - 1, object }
+                }).collect();
+                self.push(EditKind::Spawn, changes);
+            }
+            Edit::Delete => {
+                if selection.is_empty() {
+                    return;
+                }
+                // Back to front, so undoing in reverse order reinserts front to back.
+                let changes = selection.iter().rev()
+                    .map(|&index| Change::Remove { index, object: scene.objects.remove(index) })
+                    .collect();
+                self.push(EditKind::Delete, changes);
+            }
+            Edit::Reorder(offset) => {
+                // Lead with the object furthest along, so the ones behind it in the
+                // selection stop next to it instead of being blocked by its old place.
+                let mut order = selection;
+                if offset > 0 {
+                    order.reverse();
+                }
+                let last = scene.objects.len() as isize - 1;
+                let mut changes = Vec::new();
+                for index in order {
+                    let mut target = (index as isize + offset).clamp(0, last) as usize;
+                    while target != index && scene.objects[target].is_selected() {
+                        target = if target > index { target - 1 } else { target + 1 };
+                    }
+                    if target == index {
+                        continue;
+                    }
+                    let object = scene.objects.remove(index);
+                    scene.objects.insert(target, object.clone());
+                    changes.push(Change::Remove { index, object: object.clone() });
+                    changes.push(Change::Insert { index: target, object });
+                }
+                if !changes.is_empty() {
+                    self.push(EditKind::Reorder, changes);
+                }
+            }
+            Edit::Move(delta, snap) => self.modify(scene, EditKind::Move, &selection, |scene| {
+                for &i in &selection {
+                    let object = &mut scene.objects[i];
+                    object.set_position(snap.step_position(object.position(), delta));
+                }
+            }),
+            Edit::Rotate(ax, ay, snap) => self.modify(scene, EditKind::Rotate, &selection, |scene| {
+                for &i in &selection {
+                    let object = &mut scene.objects[i];
+                    let (x, y) = object.angles();
+                    object.set_angles(snap.step_angle(x, ax), snap.step_angle(y, ay));
+                }
+            }),
+            Edit::Scale(delta) => self.modify(scene, EditKind::Scale, &selection, |scene| {
+                for &i in &selection {
+                    scene.objects[i].scale(delta);
+                }
+            }),
+            Edit::Color(color) => self.modify(scene, EditKind::Color, &selection, |scene| {
+                for &i in &selection {
+                    scene.objects[i].set_color(color);
+                }
+            }),
+            Edit::Mass(mass) => self.modify(scene, EditKind::Mass, &selection, |scene| {
+                for &i in &selection {
+                    let body = &mut scene.objects[i].state_mut().body;
+                    body.mass = mass.max(0.0);
+                    if body.is_fixed() {
+                        *body = Body::fixed();
+                    }
+                }
+            }),
+            Edit::Set(exact) => {
+                let kind = match exact {
+                    Exact::Position(_) => EditKind::Move,
+                    Exact::Angles(..) => EditKind::Rotate,
+                    Exact::Scale(_) => EditKind::Scale,
+                };
+                self.modify(scene, kind, &selection, |scene| {
+                    for &i in &selection {
+                        let object = &mut scene.objects[i];
+                        match exact {
+                            Exact::Position(p) => object.set_position(p),
+                            Exact::Angles(x, y) => object.set_angles(x, y),
+                            Exact::Scale(s) => object.set_scale_factor(s),
+                        }
+                    }
+                });
+            }
+            Edit::Key(time, easing) => self.modify(scene, EditKind::Key, &selection, |scene| {
+                for &i in &selection {
+                    animation::key_object(&mut scene.objects[i], time, easing);
+                }
+            }),
+            Edit::Unkey(time) => {
+                let keyed: Vec<usize> = selection.into_iter().filter(|&i| scene.objects[i].state().animation.has_key(time)).collect();
+                self.modify(scene, EditKind::Key, &keyed, |scene| {
+                    for &i in &keyed {
+                        for property in Property::ALL {
+                            scene.objects[i].state_mut().animation.track_mut(property).remove(time);
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Runs `edit`, which may only change the objects at `indices`, and records
+    /// their state before and after, e.g. for each step of a gizmo drag.
+    pub fn modify(&mut self, scene: &mut Scene, kind: EditKind, indices: &[usize], edit: impl FnOnce(&mut Scene)) {
+        let indices: Vec<usize> = indices.iter().copied().filter(|&i| i < scene.objects.len()).collect();
+        if indices.is_empty() {
+            return;
+        }
+        let before: Vec<Objects> = indices.iter().map(|&i| scene.objects[i].clone()).collect();
+        edit(scene);
+
+        let now = Instant::now();
+        if let Some(last) = self.undo.last_mut() {
+            let mergeable = !last.sealed && last.kind == kind && kind.coalesces()
+                && now.duration_since(last.time) < COALESCE_WINDOW && last.replaced() == indices;
+            if mergeable {
+                for change in &mut last.changes {
+                    if let Change::Replace { index, after, .. } = change {
+                        **after = scene.objects[*index].clone();
+                    }
+                }
+                last.time = now;
+                self.redo.clear();
+                return;
+            }
+        }
+
+        let changes = indices.iter().zip(before)
+            .map(|(&index, before)| Change::Replace { index, before: Box::new(before), after: Box::new(scene.objects[index].clone()) })
+            .collect();
+        self.push(kind, changes);
+    }
+
+    /// Ends the current run of edits, so the next one starts a new entry.
+    pub fn seal(&mut self) {
+        if let Some(last) = self.undo.last_mut() {
+            last.sealed = true;
+        }
+    }
+
+    /// Reverts the latest entry; false when there is nothing to undo.
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        let mut entry = match self.undo.pop() {
+            Some(entry) => entry,
+            None => return false,
+        };
+        for change in entry.changes.iter().rev() {
+            match change {
+                Change::Replace { index, before, .. } => restore(scene, *index, before),
+                Change::Insert { index, .. } => remove(scene, *index),
+                Change::Remove { index, object } => insert(scene, *index, object),
+            }
+        }
+        entry.sealed = true;
+        self.layout += entry.moves_indices() as u64;
+        self.redo.push(entry);
+        true
+    }
+
+    /// Reapplies the latest undone entry; false when there is nothing to redo.
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        let entry = match self.redo.pop() {
+            Some(entry) => entry,
+            None => return false,
+        };
+        for change in &entry.changes {
+            match change {
+                Change::Replace { index, after, .. } => restore(scene, *index, after),
+                Change::Insert { index, object } => insert(scene, *index, object),
+                Change::Remove { index, .. } => remove(scene, *index),
+            }
+        }
+        self.layout += entry.moves_indices() as u64;
+        self.undo.push(entry);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Goes up with every applied edit, undo or redo that added, removed or reordered
+    /// objects, so indices or copies kept from before can be told apart as stale.
+    pub fn layout(&self) -> u64 {
+        self.layout
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn push(&mut self, kind: EditKind, changes: Vec<Change>) {
+        let entry = Entry { kind, changes, time: Instant::now(), sealed: false };
+        self.layout += entry.moves_indices() as u64;
+        self.undo.push(entry);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+}
+
+/// Puts `state` back at `index`, keeping whether the object is selected now.
+fn restore(scene: &mut Scene, index: usize, state: &Objects) {
+    if let Some(object) = scene.objects.get_mut(index) {
+        let selected = object.is_selected();
+        *object = state.clone();
+        if selected {
+            object.select();
+        } else {
+            object.deselect();
+        }
+    }
+}
+
+// Indices only go stale if the scene was edited behind the history's back; stay
+// clear of panicking then.
+
+fn insert(scene: &mut Scene, index: usize, object: &Objects) {
+    let index = index.min(scene.objects.len());
+    scene.objects.insert(index, object.clone());
+}
+
+fn remove(scene: &mut Scene, index: usize) {
+    if index < scene.objects.len() {
+        scene.objects.remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objec::Cube;
+
+    fn cube(x: f32) -> Box<Objects> {
+        Box::new(Objects::Cube(Cube::new(Point3D::new(x, 0.0, 2.0), Point3D::new(x + 1.0, 1.0, 3.0), [255, 0, 0])))
+    }
+
+    #[test]
+    fn layout_counts_only_edits_that_move_indices() {
+        let mut scene = Scene::default();
+        let mut history = History::new();
+        history.execute(&mut scene, Edit::Spawn(cube(0.0)));
+        history.execute(&mut scene, Edit::Spawn(cube(2.0)));
+        let layout = history.layout();
+
+        history.execute(&mut scene, Edit::Move(Point3D::new(1.0, 0.0, 0.0), Snap::default()));
+        history.undo(&mut scene);
+        assert_eq!(history.layout(), layout);
+
+        history.execute(&mut scene, Edit::Reorder(-1));
+        assert_eq!(history.layout(), layout + 1);
+        history.undo(&mut scene);
+        history.redo(&mut scene);
+        history.execute(&mut scene, Edit::Delete);
+        assert_eq!(history.layout(), layout + 4);
+        assert_eq!(scene.objects.len(), 1);
+    }
+
+    fn near(a: Point3D, b: Point3D) -> bool {
+        (a.x - b.x).abs() + (a.y - b.y).abs() + (a.z - b.z).abs() < 1e-5
+    }
+
+    fn one_cube() -> (Scene, History) {
+        let mut scene = Scene::default();
+        let mut history = History::new();
+        history.execute(&mut scene, Edit::Spawn(cube(0.0)));
+        (scene, history)
+    }
+
+    #[test]
+    fn move_scale_and_color_undo_and_redo() {
+        let (mut scene, mut history) = one_cube();
+        let (position, scale, color) = (scene.objects[0].position(), scene.objects[0].scale_factor(), scene.objects[0].color());
+        let offset = Point3D::new(1.0, -2.0, 0.5);
+
+        history.execute(&mut scene, Edit::Move(offset, Snap::default()));
+        history.seal();
+        history.execute(&mut scene, Edit::Scale(0.5));
+        history.seal();
+        history.execute(&mut scene, Edit::Color([0, 0, 255]));
+        let moved = position + offset;
+        assert_eq!((scene.objects[0].scale_factor(), scene.objects[0].color()), (scale + 0.5, [0, 0, 255]));
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene.objects[0].color(), color);
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene.objects[0].scale_factor(), scale);
+        assert!(history.undo(&mut scene));
+        assert!(near(scene.objects[0].position(), position));
+
+        assert!(history.redo(&mut scene));
+        assert!(near(scene.objects[0].position(), moved));
+        assert!(history.redo(&mut scene));
+        assert!(history.redo(&mut scene));
+        assert_eq!((scene.objects[0].scale_factor(), scene.objects[0].color()), (scale + 0.5, [0, 0, 255]));
+        assert!(!history.redo(&mut scene));
+    }
+
+    #[test]
+    fn steps_within_the_window_undo_together_until_sealed() {
+        let (mut scene, mut history) = one_cube();
+        let start = scene.objects[0].position();
+        let step = Point3D::new(0.25, 0.0, 0.0);
+        for _ in 0..4 {
+            history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        }
+        history.undo(&mut scene);
+        assert!(near(scene.objects[0].position(), start), "one undo reverts the whole run");
+        assert!(history.undo(&mut scene), "the spawn is still there to undo");
+        assert!(scene.objects.is_empty());
+
+        let (mut scene, mut history) = one_cube();
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.seal();
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.undo(&mut scene);
+        assert!(near(scene.objects[0].position(), start + step), "sealing starts a new entry");
+
+        // A different kind of edit breaks the run as well.
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.execute(&mut scene, Edit::Scale(0.5));
+        let scaled = scene.objects[0].position();
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.undo(&mut scene);
+        assert!(near(scene.objects[0].position(), scaled));
+        history.undo(&mut scene);
+        assert_eq!(scene.objects[0].scale_factor(), 1.0);
+    }
+
+    #[test]
+    fn steps_past_the_window_start_a_new_entry() {
+        let (mut scene, mut history) = one_cube();
+        let start = scene.objects[0].position();
+        let step = Point3D::new(0.0, 0.5, 0.0);
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.undo.last_mut().unwrap().time -= COALESCE_WINDOW;
+        history.execute(&mut scene, Edit::Move(step, Snap::default()));
+        history.undo(&mut scene);
+        assert!(near(scene.objects[0].position(), start + step));
+        history.undo(&mut scene);
+        assert!(near(scene.objects[0].position(), start));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let (mut scene, mut history) = one_cube();
+        history.execute(&mut scene, Edit::Color([0, 255, 0]));
+        history.undo(&mut scene);
+        assert!(history.can_redo());
+        history.execute(&mut scene, Edit::Scale(1.0));
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut scene));
+        assert_eq!(scene.objects[0].color(), [255, 0, 0]);
+    }
+
+    #[test]
+    fn oldest_entries_drop_past_the_limit() {
+        let (mut scene, mut history) = one_cube();
+        let extra = 10;
+        for i in 0..HISTORY_LIMIT + extra {
+            history.execute(&mut scene, Edit::Color([i as u8, 0, 0]));
+            history.seal();
+        }
+        let mut undone = 0;
+        while history.undo(&mut scene) {
+            undone += 1;
+        }
+        assert_eq!(undone, HISTORY_LIMIT);
+        // The spawn and the first colors are gone, so the oldest kept color is what is left.
+        assert_eq!(scene.objects.len(), 1);
+        assert_eq!(scene.objects[0].color(), [extra as u8 - 1, 0, 0]);
+    }
+
+    /// Scene of cubes numbered by their green channel, with the ones at `selected` indices selected.
+    fn row(count: usize, selected: &[usize]) -> (Scene, History) {
+        let mut scene = Scene::default();
+        let mut history = History::new();
+        let cubes = (0..count).map(|i| {
+            let mut object = *cube(2.0 * i as f32);
+            object.set_color([255, i as u8, 0]);
+            object
+        });
+        history.execute(&mut scene, Edit::Insert(cubes.collect()));
+        scene.deselect_all();
+        for &i in selected {
+            scene.select(i, true);
+        }
+        (scene, history)
+    }
+
+    /// Which cube of `row` is at each place.
+    fn order(scene: &Scene) -> Vec<usize> {
+        scene.objects.iter().map(|o| o.color()[1] as usize).collect()
+    }
+
+    #[test]
+    fn deleting_a_selection_undoes_in_the_original_order() {
+        let (mut scene, mut history) = row(5, &[0, 2, 4]);
+        history.execute(&mut scene, Edit::Delete);
+        assert_eq!(order(&scene), [1, 3]);
+        history.undo(&mut scene);
+        assert_eq!(order(&scene), [0, 1, 2, 3, 4]);
+        history.redo(&mut scene);
+        assert_eq!(order(&scene), [1, 3]);
+    }
+
+    #[test]
+    fn reorder_stops_at_either_end() {
+        let (mut scene, mut history) = row(4, &[0, 1]);
+        let undos = history.undo.len();
+        history.execute(&mut scene, Edit::Reorder(-1));
+        assert_eq!(order(&scene), [0, 1, 2, 3]);
+        assert_eq!(history.undo.len(), undos, "nothing moved, so nothing to undo");
+
+        history.execute(&mut scene, Edit::Reorder(10));
+        assert_eq!(order(&scene), [2, 3, 0, 1]);
+        assert_eq!(scene.selection(), [2, 3]);
+        history.execute(&mut scene, Edit::Reorder(1));
+        assert_eq!(order(&scene), [2, 3, 0, 1]);
+
+        history.execute(&mut scene, Edit::Reorder(-10));
+        assert_eq!(order(&scene), [0, 1, 2, 3]);
+        history.undo(&mut scene);
+        assert_eq!(order(&scene), [2, 3, 0, 1]);
+        history.undo(&mut scene);
+        assert_eq!(order(&scene), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn edits_on_an_empty_scene_do_nothing() {
+        let (mut scene, mut history) = row(0, &[]);
+        for edit in [Edit::Delete, Edit::Reorder(1), Edit::Reorder(-1), Edit::Insert(scene.copy_selection(Point3D::new(1.0, 0.0, 0.0)))] {
+            history.execute(&mut scene, edit);
+        }
+        assert!(scene.objects.is_empty());
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut scene));
+
+        // Nothing selected in a full scene is the same.
+        let (mut scene, mut history) = row(3, &[]);
+        let undos = history.undo.len();
+        history.execute(&mut scene, Edit::Delete);
+        history.execute(&mut scene, Edit::Reorder(1));
+        let copies = scene.copy_selection(Point3D::new(1.0, 0.0, 0.0));
+        assert!(copies.is_empty());
+        history.execute(&mut scene, Edit::Insert(copies));
+        assert_eq!(order(&scene), [0, 1, 2]);
+        assert_eq!(history.undo.len(), undos);
+    }
+
+    #[test]
+    fn duplicates_land_at_the_end_selected() {
+        let (mut scene, mut history) = row(3, &[0, 2]);
+        let copies = scene.copy_selection(Point3D::new(1.0, 0.0, 0.0));
+        history.execute(&mut scene, Edit::Insert(copies));
+        assert_eq!(scene.objects.len(), 5);
+        assert_eq!(scene.selection(), [3, 4]);
+        assert_eq!(order(&scene), [0, 1, 2, 0, 2]);
+        assert!(near(scene.objects[3].position(), scene.objects[0].position() + Point3D::new(1.0, 0.0, 0.0)));
+        assert!(near(scene.objects[4].position(), scene.objects[2].position() + Point3D::new(1.0, 0.0, 0.0)));
+        history.undo(&mut scene);
+        assert_eq!(order(&scene), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::animation::{Property, Timeline};
+use crate::drawing::Frame;
+use crate::objec::Transformable;
+use crate::scene::Scene;
+use crate::text::{draw_text, fill_rect, text_width, LINE_HEIGHT};
+
+/// Frames are counted over this long a window.
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Space around the text of a panel, in pixels.
+const PADDING: i32 = 4;
+
+const BACKGROUND: [u8; 4] = [0, 0, 0, 160];
+const TEXT: [u8; 4] = [220, 220, 220, 255];
+const HIGHLIGHT: [u8; 4] = [255, 220, 0, 255];
+const PLAYHEAD: [u8; 4] = [255, 255, 255, 255];
+
+/// Shortest length of the timeline bar, in pixels.
+const TIMELINE_WIDTH: i32 = 200;
+
+/// Frames drawn over the last second.
+#[derive(Clone, Debug, Default)]
+pub struct FpsCounter {
+    frames: VecDeque<Instant>,
+}
+
+impl FpsCounter {
+    pub fn new() -> Self {
+        FpsCounter::default()
+    }
+
+    /// Counts a frame drawn now and returns the frames per second.
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        self.frames.push_back(now);
+        while self.frames.front().is_some_and(|&t| now.duration_since(t) > FPS_WINDOW) {
+            self.frames.pop_front();
+        }
+        self.frames.len() as f32 / FPS_WINDOW.as_secs_f32()
+    }
+}
+
+/// Draws the statistics and object list in the top-left corner, the first selected
+/// object's transform in the top-right and, once anything is keyed or the playhead
+/// has moved, the timeline at the bottom, over a frame filled by `render_scene`.
+pub fn draw_hud(scene: &Scene, fps: f32, timeline: &Timeline, simulating: bool, frame: &mut Frame) {
+    let mut lines = vec![
+        (format!("FPS {:.0}  TRIS {}", fps, frame.triangles), TEXT),
+        (format!("OBJECTS {}", scene.objects.len()), TEXT),
+    ];
+    if simulating {
+        lines[1].0.push_str("  PHYSICS");
+    }
+    // Leave the bottom of the frame free for the orientation gizmo.
+    let room = ((frame.height as i32 - 120) / LINE_HEIGHT).max(3) as usize - 2;
+    let shown = if scene.objects.len() > room { room - 1 } else { scene.objects.len() };
+    for (i, object) in scene.objects.iter().enumerate().take(shown) {
+        let (marker, color) = if object.is_selected() { ('>', HIGHLIGHT) } else { (' ', TEXT) };
+        lines.push((format!("{}{:>3} {}", marker, i, object.kind()), color));
+    }
+    if shown < scene.objects.len() {
+        lines.push((format!("  +{} more", scene.objects.len() - shown), TEXT));
+    }
+    draw_panel(frame, PADDING, PADDING, &lines);
+
+    let selection = scene.selection();
+    if let Some(&first) = selection.first() {
+        let object = &scene.objects[first];
+        let p = object.position();
+        let (ax, ay) = object.angles();
+        let [r, g, b] = object.color();
+        let title = match selection.len() {
+            1 => format!("{}.{}", object.kind(), first),
+            n => format!("{}.{} (+{} more)", object.kind(), first, n - 1),
+        };
+        let lines = [
+            (title, HIGHLIGHT),
+            (format!("pos   {:.2} {:.2} {:.2}", p.x, p.y, p.z), TEXT),
+            (format!("rot   {:.1} {:.1}", ax.to_degrees(), ay.to_degrees()), TEXT),
+            (format!("scale {:.2}", object.scale_factor()), TEXT),
+            (format!("color {} {} {}", r, g, b), TEXT),
+            (match &object.state().body {
+                body if body.is_fixed() => String::from("mass  pinned"),
+                body => format!("mass  {:.2}", body.mass),
+            }, TEXT),
+        ];
+        let x = frame.width as i32 - panel_width(&lines) - PADDING;
+        draw_panel(frame, x, PADDING, &lines);
+        // Swatch of the color after its numbers, in the room `panel_width` leaves.
+        let swatch_y = PADDING + PADDING + 4 * LINE_HEIGHT;
+        let swatch_x = x + PADDING + text_width(&lines[4].0) + PADDING;
+        fill_rect(frame, swatch_x, swatch_y, 7, 7, [r, g, b, 255]);
+    }
+
+    if scene.animation_end() > 0.0 || timeline.time > 0.0 {
+        draw_timeline(scene, timeline, frame);
+    }
+}
+
+/// The playhead, playback state and easing of new keys, centered at the bottom,
+/// with the selected objects' keys marked along the bar.
+fn draw_timeline(scene: &Scene, timeline: &Timeline, frame: &mut Frame) {
+    let end = scene.animation_end().max(timeline.time);
+    let text = format!(
+        "{} {:.2}/{:.2}s{}  EASE {}",
+        if timeline.playing { "PLAY" } else { "PAUSE" },
+        timeline.time,
+        end,
+        if timeline.looping { " LOOP" } else { "" },
+        timeline.easing.name().to_uppercase(),
+    );
+    let width = text_width(&text).max(TIMELINE_WIDTH) + 2 * PADDING;
+    let height = LINE_HEIGHT + 7 + 2 * PADDING;
+    let x = (frame.width as i32 - width) / 2;
+    let y = frame.height as i32 - height - PADDING;
+    fill_rect(frame, x, y, width, height, BACKGROUND);
+    draw_text(frame, x + PADDING, y + PADDING, &text, TEXT);
+
+    let bar_x = x + PADDING;
+    let bar_y = y + PADDING + LINE_HEIGHT + 1;
+    let bar_width = width - 2 * PADDING;
+    let at = |time: f32| if end > 0.0 { bar_x + (time / end * (bar_width - 1) as f32) as i32 } else { bar_x };
+    fill_rect(frame, bar_x, bar_y + 2, bar_width, 1, TEXT);
+    for object in scene.objects.iter().filter(|o| o.is_selected()) {
+        for property in Property::ALL {
+            for key in object.state().animation.track(property).keys() {
+                fill_rect(frame, at(key.time), bar_y, 1, 5, HIGHLIGHT);
+            }
+        }
+    }
+    fill_rect(frame, at(timeline.time) - 1, bar_y - 1, 3, 7, PLAYHEAD);
+}
+
+/// Text lines on a translucent background, with its top-left corner at (`x`, `y`).
+fn draw_panel(frame: &mut Frame, x: i32, y: i32, lines: &[(String, [u8; 4])]) {
+    let height = lines.len() as i32 * LINE_HEIGHT + 2 * PADDING;
+    fill_rect(frame, x, y, panel_width(lines), height, BACKGROUND);
+    for (i, (text, color)) in lines.iter().enumerate() {
+        draw_text(frame, x + PADDING, y + PADDING + i as i32 * LINE_HEIGHT, text, *color);
+    }
+}
+
+/// Width of the panel for `lines`, with room for a swatch after the longest one.
+fn panel_width(lines: &[(String, [u8; 4])]) -> i32 {
+    lines.iter().map(|(text, _)| text_width(text)).max().unwrap_or(0) + 3 * PADDING + 7
+}
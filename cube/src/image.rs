@@ -0,0 +1,97 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::drawing::Frame;
+
+/// Writes the frame's colors as PNG or binary PPM, chosen by the file extension (PNG otherwise).
+pub fn save(path: impl AsRef<Path>, frame: &Frame) -> io::Result<()> {
+    let path = path.as_ref();
+    let is_ppm = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("ppm"));
+    let data = if is_ppm { encode_ppm(frame) } else { encode_png(frame) };
+    fs::write(path, data)
+}
+
+fn rgb_rows(frame: &Frame) -> impl Iterator<Item = Vec<u8>> + '_ {
+    frame.color.chunks_exact(frame.width as usize * 4)
+        .map(|row| row.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect())
+}
+
+pub fn encode_ppm(frame: &Frame) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", frame.width, frame.height).into_bytes();
+    for row in rgb_rows(frame) {
+        out.extend(row);
+    }
+    out
+}
+
+/// Encodes an 8-bit RGB PNG. The pixel data is stored in uncompressed deflate blocks,
+/// which keeps the encoder tiny at the cost of file size.
+pub fn encode_png(frame: &Frame) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((frame.width as usize * 3 + 1) * frame.height as usize);
+    for row in rgb_rows(frame) {
+        raw.push(0);
+        raw.extend(row);
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend(frame.width.to_be_bytes());
+    ihdr.extend(frame.height.to_be_bytes());
+    ihdr.extend([8, 2, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    png_chunk(&mut out, b"IHDR", &ihdr);
+    png_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let len = block.len() as u16;
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
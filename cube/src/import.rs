@@ -0,0 +1,592 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::geometry::*;
+use crate::mesh::Mesh;
+
+/// Color given to imported meshes that carry no colors of their own.
+pub const DEFAULT_COLOR: [u8; 3] = [180, 180, 180];
+
+#[derive(Debug)]
+pub enum ImportError{
+    Io(std::io::Error),
+    UnknownFormat(String),
+    Parse(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(err) => write!(f, "i/o error: {}", err),
+            ImportError::UnknownFormat(ext) => write!(f, "unknown mesh format '{}'", ext),
+            ImportError::Parse(msg) => write!(f, "parse error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<std::io::Error> for ImportError {
+    fn from(err: std::io::Error) -> Self {
+        ImportError::Io(err)
+    }
+}
+
+fn parse_err<T>(msg: impl Into<String>) -> Result<T, ImportError> {
+    Err(ImportError::Parse(msg.into()))
+}
+
+/// Loads an STL, PLY or OFF file, picking the parser from the file extension.
+pub fn load(path: impl AsRef<Path>) -> Result<Mesh, ImportError> {
+    let path = path.as_ref();
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    let data = fs::read(path)?;
+
+    match ext.as_str() {
+        "stl" => parse_stl(&data),
+        "ply" => parse_ply(&data),
+        "off" => parse_off(&data),
+        _ => Err(ImportError::UnknownFormat(ext)),
+    }
+}
+
+// ---------------------------------------------------------------- STL
+
+/// Parses binary or ASCII STL. Binary files using the VisCAM/SolidView
+/// convention of 15-bit facet colors get per-vertex colors.
+pub fn parse_stl(data: &[u8]) -> Result<Mesh, ImportError> {
+    if data.len() >= 84 {
+        let count = u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize;
+        if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(data.len()) {
+            return parse_stl_binary(data, count);
+        }
+    }
+    if data.trim_ascii_start().starts_with(b"solid") {
+        return parse_stl_ascii(data);
+    }
+    parse_err("STL file is neither valid binary nor ASCII")
+}
+
+fn parse_stl_binary(data: &[u8], count: usize) -> Result<Mesh, ImportError> {
+    let mut vertices = Vec::with_capacity(count * 3);
+    let mut colors = Vec::with_capacity(count * 3);
+    let mut has_colors = false;
+
+    for facet in data[84..].chunks_exact(50) {
+        let f = |i: usize| f32::from_le_bytes([facet[i], facet[i + 1], facet[i + 2], facet[i + 3]]);
+        for v in 0..3 {
+            let base = 12 + v * 12;
+            vertices.push(Point3D::new(f(base), f(base + 4), f(base + 8)));
+        }
+
+        let attr = u16::from_le_bytes([facet[48], facet[49]]);
+        let color = if attr & 0x8000 != 0 {
+            has_colors = true;
+            let channel = |shift: u16| (((attr >> shift) & 0x1f) as u32 * 255 / 31) as u8;
+            [channel(10), channel(5), channel(0)]
+        } else {
+            DEFAULT_COLOR
+        };
+        colors.extend([color; 3]);
+    }
+
+    check_vertices(&vertices)?;
+    let faces = (0..count).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect();
+    let mesh = Mesh::new(vertices, faces, DEFAULT_COLOR);
+    Ok(if has_colors { mesh.with_colors(colors) } else { mesh })
+}
+
+fn parse_stl_ascii(data: &[u8]) -> Result<Mesh, ImportError> {
+    let text = std::str::from_utf8(data).map_err(|_| ImportError::Parse("ASCII STL is not valid UTF-8".into()))?;
+    let mut vertices = Vec::new();
+    let mut in_loop = 0;
+
+    for (n, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("outer") => in_loop = 0,
+            Some("vertex") => {
+                let coords: Vec<f32> = tokens.map(|t| t.parse::<f32>()).collect::<Result<_, _>>()
+                    .map_err(|_| ImportError::Parse(format!("line {}: bad vertex coordinate", n + 1)))?;
+                if coords.len() != 3 {
+                    return parse_err(format!("line {}: vertex needs 3 coordinates", n + 1));
+                }
+                vertices.push(Point3D::new(coords[0], coords[1], coords[2]));
+                in_loop += 1;
+            }
+            Some("endloop") if in_loop != 3 => {
+                return parse_err(format!("line {}: facet with {} vertices, expected 3", n + 1, in_loop));
+            }
+            _ => {}
+        }
+    }
+
+    if vertices.is_empty() {
+        return parse_err("STL contains no facets");
+    }
+    check_vertices(&vertices)?;
+    let faces = (0..vertices.len() / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect();
+    Ok(Mesh::new(vertices, faces, DEFAULT_COLOR))
+}
+
+// ---------------------------------------------------------------- PLY
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Scalar {
+    I8, U8, I16, U16, I32, U32, F32, F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Scalar::F32 | Scalar::F64)
+    }
+}
+
+#[derive(Debug)]
+struct PlyProperty {
+    name: String,
+    kind: Scalar,
+    /// Type of the length prefix for list properties.
+    list: Option<Scalar>,
+}
+
+#[derive(Debug)]
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Reads scalar values out of either the ASCII token stream or the binary body.
+struct PlyReader<'a> {
+    format: PlyFormat,
+    data: &'a [u8],
+    pos: usize,
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> PlyReader<'a> {
+    fn read(&mut self, kind: Scalar) -> Result<f64, ImportError> {
+        if self.format == PlyFormat::Ascii {
+            let token = match self.tokens.next() {
+                Some(t) => t,
+                None => return parse_err("PLY body ended early"),
+            };
+            return token.parse::<f64>().map_err(|_| ImportError::Parse(format!("bad PLY value '{}'", token)));
+        }
+
+        let size = kind.size();
+        let bytes = match self.data.get(self.pos..self.pos + size) {
+            Some(b) => b,
+            None => return parse_err("PLY body ended early"),
+        };
+        self.pos += size;
+
+        let mut buf = [0u8; 8];
+        buf[..size].copy_from_slice(bytes);
+        if self.format == PlyFormat::BinaryBigEndian {
+            buf[..size].reverse();
+        }
+        Ok(match kind {
+            Scalar::I8 => buf[0] as i8 as f64,
+            Scalar::U8 => buf[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(buf),
+        })
+    }
+}
+
+/// Parses ASCII and binary PLY. Vertex colors are read from `red`/`green`/`blue`
+/// (integer 0-255 or float 0-1); polygons are fan-triangulated.
+pub fn parse_ply(data: &[u8]) -> Result<Mesh, ImportError> {
+    let header_end = match find(data, b"end_header") {
+        Some(i) => i,
+        None => return parse_err("PLY header has no end_header"),
+    };
+    let header = std::str::from_utf8(&data[..header_end]).map_err(|_| ImportError::Parse("PLY header is not valid text".into()))?;
+    let mut body_start = header_end + b"end_header".len();
+    if data.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    if data.get(body_start) == Some(&b'\n') {
+        body_start += 1;
+    }
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return parse_err("missing 'ply' magic");
+    }
+
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", f, _] => {
+                format = Some(match *f {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    other => return parse_err(format!("unknown PLY format '{}'", other)),
+                })
+            }
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| ImportError::Parse(format!("bad element count '{}'", count)))?;
+                elements.push(PlyElement { name: name.to_string(), count, properties: Vec::new() });
+            }
+            ["property", "list", len, kind, name] => {
+                let (len, kind) = match (Scalar::parse(len), Scalar::parse(kind)) {
+                    (Some(l), Some(k)) => (l, k),
+                    _ => return parse_err(format!("bad list property '{}'", line)),
+                };
+                match elements.last_mut() {
+                    Some(e) => e.properties.push(PlyProperty { name: name.to_string(), kind, list: Some(len) }),
+                    None => return parse_err("property before any element"),
+                }
+            }
+            ["property", kind, name] => {
+                let kind = match Scalar::parse(kind) {
+                    Some(k) => k,
+                    None => return parse_err(format!("unknown property type '{}'", kind)),
+                };
+                match elements.last_mut() {
+                    Some(e) => e.properties.push(PlyProperty { name: name.to_string(), kind, list: None }),
+                    None => return parse_err("property before any element"),
+                }
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return parse_err(format!("unexpected header line '{}'", line)),
+        }
+    }
+    let format = match format {
+        Some(f) => f,
+        None => return parse_err("PLY header has no format line"),
+    };
+
+    let body = &data[body_start..];
+    let text = if format == PlyFormat::Ascii {
+        std::str::from_utf8(body).map_err(|_| ImportError::Parse("ASCII PLY body is not valid text".into()))?
+    } else {
+        ""
+    };
+    let mut reader = PlyReader { format, data: body, pos: 0, tokens: text.split_ascii_whitespace() };
+
+    let mut vertices = Vec::new();
+    let mut colors = Vec::new();
+    let mut faces = Vec::new();
+
+    for element in &elements {
+        let prop_index = |name: &str| element.properties.iter().position(|p| p.name == name);
+        let xyz = [prop_index("x"), prop_index("y"), prop_index("z")];
+        let rgb = [prop_index("red"), prop_index("green"), prop_index("blue")];
+        let face_list = prop_index("vertex_indices").or_else(|| prop_index("vertex_index"));
+
+        for _ in 0..element.count {
+            let mut scalars = vec![0.0; element.properties.len()];
+            let mut list = Vec::new();
+            for (i, prop) in element.properties.iter().enumerate() {
+                match prop.list {
+                    Some(len_kind) => {
+                        let len = reader.read(len_kind)?;
+                        if !(0.0..=65536.0).contains(&len) {
+                            return parse_err(format!("bad list length {}", len));
+                        }
+                        let values = (0..len as usize).map(|_| reader.read(prop.kind)).collect::<Result<Vec<_>, _>>()?;
+                        if Some(i) == face_list {
+                            list = values;
+                        }
+                    }
+                    None => scalars[i] = reader.read(prop.kind)?,
+                }
+            }
+
+            if element.name == "vertex" {
+                let [Some(x), Some(y), Some(z)] = xyz else {
+                    return parse_err("vertex element lacks x/y/z");
+                };
+                vertices.push(Point3D::new(scalars[x] as f32, scalars[y] as f32, scalars[z] as f32));
+                if let [Some(r), Some(g), Some(b)] = rgb {
+                    let channel = |i: usize| {
+                        let v = scalars[i];
+                        if element.properties[i].kind.is_float() { (v * 255.0).clamp(0.0, 255.0) as u8 } else { v.clamp(0.0, 255.0) as u8 }
+                    };
+                    colors.push([channel(r), channel(g), channel(b)]);
+                }
+            } else if element.name == "face" {
+                let indices = list.iter().map(|&i| to_index(i)).collect::<Result<Vec<_>, _>>()?;
+                triangulate(&indices, &mut faces);
+            }
+        }
+    }
+
+    check_vertices(&vertices)?;
+    validate_faces(&faces, vertices.len())?;
+    let mesh = Mesh::new(vertices, faces, DEFAULT_COLOR);
+    Ok(if colors.is_empty() { mesh } else { mesh.with_colors(colors) })
+}
+
+// ---------------------------------------------------------------- OFF
+
+/// Parses OFF and COFF files. Colors may be given per vertex (COFF) or per face;
+/// face colors are turned into per-vertex colors by unsharing the face's vertices.
+pub fn parse_off(data: &[u8]) -> Result<Mesh, ImportError> {
+    let text = std::str::from_utf8(data).map_err(|_| ImportError::Parse("OFF file is not valid text".into()))?;
+    let mut lines = text.lines()
+        .map(|l| l.split('#').next().unwrap_or("").trim())
+        .filter(|l| !l.is_empty());
+
+    let header = lines.next().unwrap_or("");
+    let keyword = header.split_whitespace().next().unwrap_or("");
+    if !keyword.ends_with("OFF") {
+        return parse_err("missing OFF header");
+    }
+    let vertex_colors = keyword.contains('C');
+    let has_normals = keyword.contains('N');
+
+    // Counts may share the header line ("OFF 8 6 12").
+    let mut counts_line: Vec<&str> = header.split_whitespace().skip(1).collect();
+    if counts_line.is_empty() {
+        counts_line = lines.next().unwrap_or("").split_whitespace().collect();
+    }
+    let counts: Vec<usize> = counts_line.iter().take(2)
+        .map(|t| t.parse()).collect::<Result<_, _>>()
+        .map_err(|_| ImportError::Parse("bad OFF counts".into()))?;
+    let [vertex_count, face_count] = counts[..] else {
+        return parse_err("OFF counts line needs vertex and face counts");
+    };
+
+    let mut vertices = Vec::new();
+    let mut colors = Vec::new();
+    for i in 0..vertex_count {
+        let values = parse_floats(lines.next(), i)?;
+        if values.len() < 3 {
+            return parse_err(format!("vertex {} has fewer than 3 coordinates", i));
+        }
+        vertices.push(Point3D::new(values[0], values[1], values[2]));
+        let color_at = if has_normals { 6 } else { 3 };
+        if vertex_colors {
+            colors.push(off_color(&values[color_at.min(values.len())..]).unwrap_or(DEFAULT_COLOR));
+        }
+    }
+
+    let mut faces = Vec::new();
+    let mut face_colors = Vec::new();
+    for i in 0..face_count {
+        let values = parse_floats(lines.next(), i)?;
+        let n = to_index(values.first().copied().unwrap_or(0.0) as f64)?;
+        if n >= values.len() {
+            return parse_err(format!("face {} lists fewer than {} indices", i, n));
+        }
+        let indices = values[1..=n].iter().map(|&v| to_index(v as f64)).collect::<Result<Vec<_>, _>>()?;
+        let before = faces.len();
+        triangulate(&indices, &mut faces);
+        let color = off_color(&values[n + 1..]);
+        face_colors.extend(std::iter::repeat_n(color, faces.len() - before));
+    }
+    check_vertices(&vertices)?;
+    validate_faces(&faces, vertices.len())?;
+
+    if face_colors.iter().any(|c| c.is_some()) {
+        let mut flat_vertices = Vec::with_capacity(faces.len() * 3);
+        let mut flat_colors = Vec::with_capacity(faces.len() * 3);
+        for (face, color) in faces.iter().zip(&face_colors) {
+            for &i in face {
+                flat_vertices.push(vertices[i]);
+                flat_colors.push(color.unwrap_or(if vertex_colors { colors[i] } else { DEFAULT_COLOR }));
+            }
+        }
+        let flat_faces = (0..faces.len()).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect();
+        return Ok(Mesh::new(flat_vertices, flat_faces, DEFAULT_COLOR).with_colors(flat_colors));
+    }
+
+    let mesh = Mesh::new(vertices, faces, DEFAULT_COLOR);
+    Ok(if vertex_colors { mesh.with_colors(colors) } else { mesh })
+}
+
+fn parse_floats(line: Option<&str>, index: usize) -> Result<Vec<f32>, ImportError> {
+    let line = match line {
+        Some(l) => l,
+        None => return parse_err(format!("file ended before element {}", index)),
+    };
+    line.split_whitespace().map(|t| t.parse::<f32>()).collect::<Result<_, _>>()
+        .map_err(|_| ImportError::Parse(format!("bad number in '{}'", line)))
+}
+
+/// OFF colors are either integers 0-255 or floats 0-1; alpha is ignored.
+fn off_color(values: &[f32]) -> Option<[u8; 3]> {
+    if values.len() < 3 {
+        return None;
+    }
+    let is_float = values[..3].iter().all(|&v| v <= 1.0) && values[..3].iter().any(|&v| v.fract() != 0.0 || v == 1.0);
+    let channel = |v: f32| if is_float { (v * 255.0).clamp(0.0, 255.0) as u8 } else { v.clamp(0.0, 255.0) as u8 };
+    Some([channel(values[0]), channel(values[1]), channel(values[2])])
+}
+
+// ---------------------------------------------------------------- shared
+
+fn triangulate(polygon: &[usize], faces: &mut Vec<[usize; 3]>) {
+    for i in 1..polygon.len().saturating_sub(1) {
+        faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+    }
+}
+
+/// Converts a count or vertex index read as a number, rejecting negative and fractional values.
+fn to_index(value: f64) -> Result<usize, ImportError> {
+    if value < 0.0 || value.fract() != 0.0 || value > u32::MAX as f64 {
+        return parse_err(format!("bad index {}", value));
+    }
+    Ok(value as usize)
+}
+
+/// NaN or infinite coordinates would be saved into scenes that then fail to load.
+fn check_vertices(vertices: &[Point3D]) -> Result<(), ImportError> {
+    match vertices.iter().position(|v| !(v.x.is_finite() && v.y.is_finite() && v.z.is_finite())) {
+        Some(i) => parse_err(format!("vertex {} has a non-finite coordinate", i)),
+        None => Ok(()),
+    }
+}
+
+fn validate_faces(faces: &[[usize; 3]], vertex_count: usize) -> Result<(), ImportError> {
+    for face in faces {
+        if let Some(&i) = face.iter().find(|&&i| i >= vertex_count) {
+            return parse_err(format!("face index {} out of range ({} vertices)", i, vertex_count));
+        }
+    }
+    Ok(())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_stl_reads_facets() {
+        let stl = b"solid t\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid t\n";
+        let mesh = parse_stl(stl).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+    }
+
+    #[test]
+    fn binary_stl_reads_facet_colors() {
+        let mut stl = vec![0u8; 80];
+        stl.extend(1u32.to_le_bytes());
+        stl.extend([0u8; 12]);
+        for v in [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] {
+            for c in v {
+                stl.extend(c.to_le_bytes());
+            }
+        }
+        stl.extend((0x8000u16 | 0x1f << 10).to_le_bytes());
+        let mesh = parse_stl(&stl).unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(mesh.colors.unwrap()[0], [255, 0, 0]);
+    }
+
+    #[test]
+    fn ascii_ply_triangulates_quads() {
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n";
+        let mesh = parse_ply(ply).unwrap();
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.colors.unwrap()[1], [0, 255, 0]);
+    }
+
+    #[test]
+    fn ply_rejects_negative_index() {
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 -1 1 2\n";
+        assert!(matches!(parse_ply(ply), Err(ImportError::Parse(_))));
+    }
+
+    #[test]
+    fn ply_rejects_truncated_binary_body() {
+        let ply = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n\0\0\0\0";
+        assert!(matches!(parse_ply(ply), Err(ImportError::Parse(_))));
+    }
+
+    #[test]
+    fn off_face_colors_unshare_vertices() {
+        let off = b"OFF\n# square\n4 2 0\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n3 0 1 2 255 0 0\n3 0 2 3\n";
+        let mesh = parse_off(off).unwrap();
+        assert_eq!(mesh.vertices.len(), 6);
+        let colors = mesh.colors.unwrap();
+        assert_eq!(colors[0], [255, 0, 0]);
+        assert_eq!(colors[3], DEFAULT_COLOR);
+    }
+
+    #[test]
+    fn off_rejects_bad_face_counts() {
+        for face in ["1e30 0 1 2", "-1 0 1 2", "2.5 0 1 2", "4 0 1 2", "3 0 -1 2"] {
+            let off = format!("OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n{}\n", face);
+            assert!(matches!(parse_off(off.as_bytes()), Err(ImportError::Parse(_))), "{}", face);
+        }
+    }
+
+    #[test]
+    fn rejects_non_finite_coordinates() {
+        let stl = b"solid t\nouter loop\nvertex nan 1 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendsolid t\n";
+        assert!(matches!(parse_stl(stl), Err(ImportError::Parse(_))));
+
+        let mut binary = vec![0u8; 80];
+        binary.extend(1u32.to_le_bytes());
+        binary.extend([0u8; 12]);
+        for c in [f32::INFINITY, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend(c.to_le_bytes());
+        }
+        binary.extend([0u8; 2]);
+        assert!(matches!(parse_stl(&binary), Err(ImportError::Parse(_))));
+
+        let ply = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty double x\nproperty double y\nproperty double z\nend_header\n1e300 0 0\n";
+        assert!(matches!(parse_ply(ply), Err(ImportError::Parse(_))));
+
+        let off = b"OFF\n3 1 0\n0 0 0\n1 inf 0\n0 1 0\n3 0 1 2\n";
+        assert!(matches!(parse_off(off), Err(ImportError::Parse(_))));
+    }
+
+    #[test]
+    fn off_rejects_out_of_range_index() {
+        let off = b"OFF 3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n";
+        assert!(matches!(parse_off(off), Err(ImportError::Parse(_))));
+    }
+}
@@ -0,0 +1,37 @@
+pub mod operations;
+pub mod drawing;
+pub mod lighting;
+pub mod objec;
+pub mod geometry;
+pub mod bounds;
+pub mod picking;
+pub mod gizmo;
+pub mod history;
+pub mod transform;
+pub mod mesh;
+pub mod primitives;
+pub mod polyhedra;
+pub mod import;
+pub mod json;
+pub mod gltf;
+pub mod camera;
+pub mod scene;
+pub mod render;
+pub mod text;
+pub mod hud;
+pub mod config;
+pub mod motion;
+pub mod animation;
+pub mod image;
+pub mod gif;
+pub mod y4m;
+pub mod recording;
+pub mod physics;
+pub mod collision;
+pub mod particles;
+pub mod raytrace;
+pub mod terminal;
+
+/// Default window and image size.
+pub const WIDTH: u32 = 640;
+pub const HEIGHT: u32 = 480;
@@ -2,14 +2,6 @@
 use crate::geometry::*;
 
 impl Point3D {
-    pub fn sub(self, other: Point3D) -> Point3D {
-        Point3D {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-        }
-    }
-
     pub fn dot(self, other: Point3D) -> f32 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
@@ -33,8 +25,8 @@ impl Point3D {
 }
 
 pub fn compute_light(tri: &Triangle3D, light_pos: Point3D) -> f32 {
-    let u = tri.p2.sub(tri.p1);
-    let v = tri.p3.sub(tri.p1);
+    let u = tri.p2 - tri.p1;
+    let v = tri.p3 - tri.p1;
 
     let normal = u.cross(v).normalize();
 
@@ -44,11 +36,18 @@ pub fn compute_light(tri: &Triangle3D, light_pos: Point3D) -> f32 {
         z: (tri.p1.z + tri.p2.z + tri.p3.z) / 3.0,
     };
 
-    let light_dir = (light_pos.sub(center)).normalize();
+    let light_dir = (light_pos - center).normalize();
 
     normal.dot(light_dir).clamp(0.1, 1.0)
 }
 
+/// Light reaching a single vertex with the given normal, for smooth shading.
+pub fn vertex_light(point: Point3D, normal: Point3D, light_pos: Point3D) -> f32 {
+    let light_dir = (light_pos - point).normalize();
+
+    normal.normalize().dot(light_dir).clamp(0.1, 1.0)
+}
+
 /* 
 pub fn shaded_gray(intensity: f32) -> [u8; 4] {
     let value = (255.0 * intensity.clamp(0.1, 1.0)) as u8;
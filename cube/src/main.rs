@@ -1,54 +1,187 @@
-use std::vec;
-
 use pixels::{Pixels, SurfaceTexture};
 use winit::{
     dpi::LogicalSize,
-    event::{ElementState, Event, WindowEvent},
+    event::{ElementState, Event, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 
-mod operations;
-mod drawing;
-mod lighting;
-mod objec;
-mod geometry;
-use geometry::*;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use cube::config::Input;
+use cube::drawing::Frame;
+use cube::geometry::*;
+use cube::objec::Objects;
+use cube::raytrace::trace_scene;
+use cube::recording::{record, turntable_center, CameraPath, Recording, Shot};
+use cube::render::render_scene;
+use cube::scene::Scene;
+use cube::terminal::{Decoder, Palette, Screen, TermEvent, Terminal, ESCAPE_WAIT};
+use cube::{gltf, image, import};
+
+mod cli;
+mod viewer;
 
-use crate::objec::{Cube, Objects, Plane, Pyramid, Sphere, Transformable, Triangle};
+use cli::{Command, Options, ShotOption};
+use viewer::{Viewer, Wake};
 
-const WIDTH: u32 = 640;
-const HEIGHT: u32 = 480;
+/// How long a terminal key counts as held after it is pressed, waiting for its
+/// first repeat; terminals report presses but not releases.
+const REPEAT_DELAY: Duration = Duration::from_millis(550);
+
+/// How long a repeating terminal key counts as held after its last repeat.
+const REPEAT_GAP: Duration = Duration::from_millis(120);
+
+/// Longest the terminal goes without looking at its size, which can change while idle.
+const RESIZE_POLL: Duration = Duration::from_millis(250);
 
 fn main() {
     env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title("3D Rendering")
-        .with_inner_size(LogicalSize::new(WIDTH, HEIGHT))
-        .build(&event_loop)
-        .unwrap();
 
-    let surface_texture = SurfaceTexture::new(WIDTH, HEIGHT, &window);
-    let mut pixels = Pixels::new(WIDTH, HEIGHT, surface_texture).unwrap();
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let mut framebuffer = vec![0u8; (WIDTH * HEIGHT * 4) as usize];
-    let mut zbuffer = vec![f32::INFINITY; (WIDTH * HEIGHT) as usize];
-    let mut z_move = 0.6;
-    let mut light_dir = Point3D::new((0 as f64) as f32, (0 as f64) as f32, z_move);
-    let mut obj_num: usize = 0;
+    match command {
+        Command::Help => println!("{}", cli::USAGE),
+        Command::Render(options) => {
+            let scene = open_scene_or_exit(&options);
+            let mut frame = Frame::new(options.width, options.height);
+            match options.tracer {
+                Some(tracer) => trace_scene(&scene, tracer, &mut frame),
+                None => render_scene(&scene, options.mode, &mut frame),
+            }
+            let output = options.output.unwrap_or_default();
+            if let Err(err) = image::save(&output, &frame) {
+                eprintln!("{}: {}", output, err);
+                std::process::exit(1);
+            }
+        }
+        Command::Record(options) => {
+            let scene = open_scene_or_exit(&options);
+            let shot = match &options.shot {
+                ShotOption::Still => Shot::Still,
+                ShotOption::Turntable(None) => Shot::Turntable(turntable_center(&scene, &[])),
+                ShotOption::Turntable(Some(index)) if *index < scene.objects.len() => Shot::Turntable(turntable_center(&scene, &[*index])),
+                ShotOption::Turntable(Some(index)) => {
+                    eprintln!("no object {} to orbit, the scene has {}", index, scene.objects.len());
+                    std::process::exit(1);
+                }
+                ShotOption::Path(path) => match CameraPath::load(path) {
+                    Ok(camera_path) => Shot::Path(camera_path),
+                    Err(err) => {
+                        eprintln!("{}: {}", path, err);
+                        std::process::exit(1);
+                    }
+                },
+            };
+            let frames = options.frames.unwrap_or_else(|| (shot.duration(&scene) * options.fps as f32).round().max(1.0) as u32);
+            let recording = Recording{
+                width: options.width,
+                height: options.height,
+                fps: options.fps,
+                frames,
+                start: options.time.unwrap_or(0.0),
+                mode: options.mode,
+                tracer: options.tracer,
+            };
+            let output = options.output.unwrap_or_default();
+            if let Err(err) = record(&scene, &shot, &recording, &output) {
+                eprintln!("{}: {}", output, err);
+                std::process::exit(1);
+            }
+        }
+        Command::View(options) => match options.terminal {
+            Some(palette) => run_terminal(options, palette),
+            None => run_viewer(options),
+        },
+    }
+}
 
+/// `open_scene` for the commands without a window, which stop at the first problem.
+/// They have nothing to save, so a scene file that does not exist is a mistake.
+fn open_scene_or_exit(options: &Options) -> Scene {
+    let (scene, mut errors) = open_scene(options);
+    if let Some(path) = options.scene.as_ref().filter(|p| !std::path::Path::new(p).exists()) {
+        errors.insert(0, format!("{}: no such scene file", path));
+    }
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("{}", err);
+        }
+        std::process::exit(1);
+    }
+    scene
+}
 
-    let p1 = Point3D::new(-0.7, -0.7, 2.0);
-    let p2 = Point3D::new(0.7, 0.7, 4.0);
-    let p3 = Point3D::new(0.0, 0.0, 4.0);
+/// Builds the starting scene from the scene file and imported files on the command line.
+fn open_scene(options: &Options) -> (Scene, Vec<String>) {
+    let mut scene = Scene::default();
+    scene.light.z = options.config.light_depth;
+    let mut errors = Vec::new();
 
-    let mut scene: Vec<Objects> = vec![];
+    // A scene that does not exist yet is created by the first save.
+    if let Some(path) = options.scene.as_ref().filter(|p| std::path::Path::new(p).exists()) {
+        match Scene::load(path) {
+            Ok(loaded) => scene = loaded,
+            Err(err) => errors.push(format!("{}: {}", path, err)),
+        }
+    }
 
+    for path in &options.files {
+        if path.ends_with(".gltf") || path.ends_with(".glb") {
+            match gltf::load(path) {
+                Ok(objects) => scene.objects.extend(objects),
+                Err(err) => errors.push(format!("{}: {}", path, err)),
+            }
+            continue;
+        }
+        match import::load(path) {
+            Ok(mut mesh) => {
+                mesh.place(Point3D::new(0.0, 0.0, 2.0), 1.0);
+                scene.objects.push(Objects::Mesh(mesh));
+            }
+            Err(err) => errors.push(format!("{}: {}", path, err)),
+        }
+    }
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+    if let Some(time) = options.time {
+        scene.pose(time);
+    }
+    if let Some(fov) = options.fov {
+        scene.camera.fov = fov;
+    }
+    if let Some(light) = options.light {
+        scene.light = light;
+    }
+    (scene, errors)
+}
 
+fn run_viewer(options: Options) {
+    let (width, height) = (options.width, options.height);
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(options.title.as_str())
+        .with_inner_size(LogicalSize::new(width, height))
+        .build(&event_loop)
+        .unwrap();
+
+    let surface_texture = SurfaceTexture::new(width, height, &window);
+    let mut pixels = Pixels::new(width, height, surface_texture).unwrap();
+
+    let (scene, errors) = open_scene(&options);
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    let mut viewer = Viewer::new(&options, scene);
+    let mut title = String::new();
+
+    event_loop.run(move |event, _, control_flow| {
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
@@ -56,122 +189,214 @@ fn main() {
             } => *control_flow = ControlFlow::Exit,
 
             Event::WindowEvent {
-                event: WindowEvent::MouseWheel { delta, .. },
+                event: WindowEvent::MouseWheel { delta: winit::event::MouseScrollDelta::LineDelta(_, y), .. },
                 ..
-            } => {
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(_, y) => {
-                        z_move += y * 0.1;
-                        z_move = z_move.clamp(-10.0, 10.0);
-                    }
-                    _ => {}
-                }
-            }
+            } => viewer.wheel(y),
 
             Event::WindowEvent {
                 event: WindowEvent::KeyboardInput { input, .. },
                 ..
-                } => {
+            } => {
                 if let Some(keycode) = input.virtual_keycode {
-                    if input.state == ElementState::Pressed {
-                        match keycode {
-                            winit::event::VirtualKeyCode::Up => if obj_num < scene.len() {scene[obj_num].rotate(-0.04, 0.0);},
-                            winit::event::VirtualKeyCode::Down => if obj_num < scene.len() {scene[obj_num].rotate(0.04, 0.0)},
-                            winit::event::VirtualKeyCode::Left => if obj_num < scene.len() {scene[obj_num].rotate(0.0, -0.04)},
-                            winit::event::VirtualKeyCode::Right => if obj_num < scene.len() {scene[obj_num].rotate(0.0, 0.04)},
-                            winit::event::VirtualKeyCode::Numpad0 => {obj_num = 0; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad1 => {obj_num = 1; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad2 => {obj_num = 2; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad3 => {obj_num = 3; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad4 => {obj_num = 4; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad5 => {obj_num = 5; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad6 => {obj_num = 6; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad7 => {obj_num = 7; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad8 => {obj_num = 8; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Numpad9 => {obj_num = 9; select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::W => { if obj_num < scene.len() {scene[obj_num].move_trans(0.0, -0.01, 0.0)}},
-                            winit::event::VirtualKeyCode::S => { if obj_num < scene.len() {scene[obj_num].move_trans(0.0, 0.01, 0.0)}},
-                            winit::event::VirtualKeyCode::A => { if obj_num < scene.len() {scene[obj_num].move_trans(-0.01, 0.0, 0.0)}},
-                            winit::event::VirtualKeyCode::D => { if obj_num < scene.len() {scene[obj_num].move_trans(0.01, 0.0, 0.0)}},
-                            winit::event::VirtualKeyCode::Q => { if obj_num < scene.len() {scene[obj_num].move_trans(0.0, 0.0, -0.01)}},
-                            winit::event::VirtualKeyCode::E => { if obj_num < scene.len() {scene[obj_num].move_trans(0.0, 0.0, 0.01)}},
-                            winit::event::VirtualKeyCode::C => { scene.push(Objects::Cube(Cube::new(p1, p2, [100,200,50])));   
-                                                                    obj_num = scene.len()-1;
-                                                                    select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::P => { scene.push(Objects::Plane(Plane::new(p1, p2,[100,0,50])));
-                                                                    obj_num = scene.len()-1;
-                                                                    select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::T => { scene.push(Objects::Triangle(Triangle::new(p1, p2, p3,[200,200,50])));
-                                                                    obj_num = scene.len()-1;
-                                                                    select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::Y => { scene.push(Objects::Pyramid(Pyramid::new(p1, p2, p3,[100,200,200])));
-                                                                    obj_num = scene.len()-1;
-                                                                    select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::O => { scene.push(Objects::Sphere(Sphere::new(p1, 1.0, 8, 16,[100,100,0])));
-                                                                    obj_num = scene.len()-1;
-                                                                    select_object(obj_num, &mut scene);},
-                            winit::event::VirtualKeyCode::R => { if obj_num < scene.len() {scene[obj_num].scale(0.01); }},
-                            winit::event::VirtualKeyCode::F => { if obj_num < scene.len() {scene[obj_num].scale(-0.01); }},
-                            winit::event::VirtualKeyCode::Escape => { scene[obj_num].deselect();},
-                            _ => {}
-                        }
+                    match input.state {
+                        ElementState::Pressed => viewer.key(keycode),
+                        ElementState::Released => viewer.release(Input::Key(keycode)),
                     }
-                    
-                    render_scene(&scene, light_dir, &mut framebuffer, &mut zbuffer);
-                    window.request_redraw();
                 }
             }
 
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => {
+                // Keys let go of in another window never report their release here.
+                viewer.release_all();
+            }
+
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(c),
+                ..
+            } => viewer.type_char(c),
+
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(state),
+                ..
+            } => {
+                viewer.ctrl = state.ctrl();
+                viewer.shift = state.shift();
+            }
+
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { state, button, .. },
+                ..
+            } => match state {
+                ElementState::Pressed => viewer.press(Input::Mouse(button)),
+                ElementState::Released => viewer.release(Input::Mouse(button)),
+            },
+
             Event::WindowEvent {
                 event: WindowEvent::CursorMoved { position ,.. },
                 ..
             } => {
-                light_dir = Point3D::new(-(position.x-(WIDTH/2) as f64) as f32, -(position.y-(HEIGHT/2) as f64) as f32, z_move);
-            
-                render_scene(&scene, light_dir, &mut framebuffer, &mut zbuffer);
-                window.request_redraw();
+                // Positions off the pixel area are clamped so drags keep following the cursor.
+                let (pixel, inside) = match pixels.window_pos_to_pixel((position.x as f32, position.y as f32)) {
+                    Ok(pixel) => (pixel, true),
+                    Err(pos) => (pixels.clamp_pixel_pos(pos), false),
+                };
+                viewer.point(pixel.0 as u32, pixel.1 as u32, inside);
+                viewer.aim_light(position.x as f32 / width as f32, position.y as f32 / height as f32);
+            }
+
+            Event::MainEventsCleared => {
+                if viewer.update() {
+                    window.request_redraw();
+                }
+                for err in viewer.take_errors() {
+                    eprintln!("{}", err);
+                }
+                if viewer.title() != title {
+                    title = viewer.title();
+                    window.set_title(&title);
+                }
+                if viewer.quit {
+                    *control_flow = ControlFlow::Exit;
+                } else if !matches!(*control_flow, ControlFlow::ExitWithCode(_)) {
+                    *control_flow = match viewer.wake() {
+                        Wake::At(deadline) => ControlFlow::WaitUntil(deadline),
+                        // More passes to trace; go on as soon as this one is shown.
+                        Wake::Now => ControlFlow::Poll,
+                        Wake::Idle => ControlFlow::Wait,
+                    };
+                }
             }
 
             Event::RedrawRequested(_) => {
-                pixels.frame_mut().copy_from_slice(&framebuffer);
+                pixels.frame_mut().copy_from_slice(&viewer.frame.color);
                 pixels.render().unwrap();
             }
- 
+
             _ => {}
         }
     });
 }
 
-fn render_scene(scene: &Vec<Objects>, light_dir: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]){
-    framebuffer.fill(0);
-    zbuffer.fill(f32::INFINITY);
+/// The viewer drawn with characters in the terminal it was started from, with
+/// the same controls; the bottom row shows the title and the HUD's figures.
+fn run_terminal(options: Options, palette: Palette) {
+    let (scene, errors) = open_scene(&options);
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    let terminal = match Terminal::open() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            eprintln!("terminal: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut viewer = Viewer::new(&options, scene);
+    viewer.hud_in_frame = false;
+    let mut screen = Screen::new(palette);
+    let mut decoder = Decoder::new();
+    // Keys by when they were first and last pressed, until they count as let go.
+    let mut down: HashMap<VirtualKeyCode, (Instant, Instant)> = HashMap::new();
+    let hold = |(first, last): (Instant, Instant)| last + if first == last { REPEAT_DELAY } else { REPEAT_GAP };
+    let mut size = (0, 0);
+    // Shown in place of the status line until the next key or click.
+    let mut error: Option<String> = None;
+    let mut out = String::new();
 
-    for object in scene{
-        match object{
-            Objects::Cube(obj) => {
-                objec::draw_cube(obj, light_dir, framebuffer, zbuffer);
+    while !viewer.quit {
+        let now = Instant::now();
+        down.retain(|&key, times| {
+            let held = hold(*times) > now;
+            if !held {
+                viewer.release(Input::Key(key));
             }
-            Objects::Plane(obj) => {
-                objec::draw_plane(obj, light_dir, framebuffer, zbuffer);
+            held
+        });
+        match terminal.size() {
+            Ok(current) if current != size => {
+                size = current;
+                let (width, height) = Screen::frame_size(size.0, size.1);
+                viewer.resize(width, height);
             }
-            Objects::Triangle(obj) => {
-                objec::draw_triangle(obj, light_dir, framebuffer, zbuffer);
+            Ok(_) => {}
+            Err(err) => {
+                error = Some(format!("terminal: {}", err));
+                break;
             }
-            Objects::Pyramid(obj) => {
-                objec::draw_pyramid(obj, light_dir, framebuffer, zbuffer);
+        }
+
+        viewer.update();
+        if let Some(last) = viewer.take_errors().pop() {
+            error = Some(last);
+        }
+        out.clear();
+        screen.draw(&viewer.frame, error.as_deref().unwrap_or(&viewer.status()), &mut out);
+        if !out.is_empty() && terminal.write(&out).is_err() {
+            break;
+        }
+
+        let wake = match viewer.wake() {
+            Wake::At(deadline) => deadline.saturating_duration_since(Instant::now()),
+            Wake::Now => Duration::ZERO,
+            Wake::Idle => RESIZE_POLL,
+        };
+        let release = down.values().map(|&times| hold(times).saturating_duration_since(Instant::now())).min();
+        let mut timeout = wake.min(release.unwrap_or(RESIZE_POLL)).min(RESIZE_POLL);
+        if decoder.waiting() {
+            // Just long enough for the rest of a sequence; if none comes, ESC was the Escape key.
+            timeout = ESCAPE_WAIT;
+        }
+        let bytes = match terminal.read(Some(timeout)) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error = Some(format!("terminal: {}", err));
+                break;
             }
-            Objects::Sphere(obj) => {
-                objec::draw_sphere(obj, light_dir, framebuffer, zbuffer);
+        };
+
+        let (width, height) = (viewer.frame.width, viewer.frame.height);
+        let events = if bytes.is_empty() { decoder.flush() } else { decoder.feed(&bytes) };
+        for event in events {
+            match event {
+                TermEvent::Key{ key, ctrl, shift, text } => {
+                    error = None;
+                    viewer.ctrl = ctrl;
+                    viewer.shift = shift;
+                    match text.filter(|_| viewer.prompting()) {
+                        Some(c) => viewer.type_char(c),
+                        None => {
+                            viewer.key(key);
+                            let now = Instant::now();
+                            down.entry(key).and_modify(|(_, last)| *last = now).or_insert((now, now));
+                        }
+                    }
+                }
+                TermEvent::Text(c) => viewer.type_char(c),
+                TermEvent::Pointer{ column, row } | TermEvent::Button{ column, row, .. } => {
+                    // The upper of the cell's two pixels; the status row is off the frame.
+                    let (x, y) = (column as u32, row as u32 * 2);
+                    viewer.point(x.min(width - 1), y.min(height - 1), x < width && y < height);
+                    viewer.aim_light((x as f32 + 0.5) / width as f32, (y as f32 + 1.0) / height as f32);
+                    if let TermEvent::Button{ button, pressed, ctrl, shift, .. } = event {
+                        error = None;
+                        viewer.ctrl = ctrl;
+                        viewer.shift = shift;
+                        match pressed {
+                            true => viewer.press(Input::Mouse(button)),
+                            false => viewer.release(Input::Mouse(button)),
+                        }
+                    }
+                }
+                TermEvent::Wheel(lines) => viewer.wheel(lines),
             }
         }
     }
-}
 
-fn select_object(obj_num: usize, scene: &mut Vec<Objects>){
-    for i in 0..scene.len(){
-        scene[i].deselect();
+    drop(terminal);
+    if let Some(err) = error.filter(|_| !viewer.quit) {
+        eprintln!("{}", err);
     }
-    scene[obj_num].select();
 }
-
-
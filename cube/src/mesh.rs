@@ -0,0 +1,120 @@
+use crate::geometry::*;
+use crate::operations::*;
+use crate::drawing::*;
+use crate::objec::{ObjectState, Transformable};
+
+/// Arbitrary indexed triangle mesh, e.g. loaded from a file.
+///
+/// Vertices are stored relative to `center`, so moving the mesh only touches the center,
+/// and land exactly at `center + vertex` when unrotated so imported scenes keep their layout.
+#[derive(Clone)]
+pub struct Mesh{
+    pub vertices: Vec<Point3D>,
+    pub colors: Option<Vec<[u8; 3]>>,
+    pub faces: Vec<[usize; 3]>,
+    pub center: Point3D,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Mesh{
+    /// Builds a mesh from world-space vertices, centering it on the middle of its bounding box.
+    pub fn new(vertices: Vec<Point3D>, faces: Vec<[usize; 3]>, color: [u8; 3]) -> Self{
+        let center = bounds_center(&vertices);
+        let vertices = vertices.into_iter().map(|v| v - center).collect();
+
+        Mesh{
+            vertices,
+            colors: None,
+            faces,
+            center,
+            angle_x: 0.0,
+            angle_y: 0.0,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::default(),
+        }
+    }
+
+    pub fn with_colors(mut self, colors: Vec<[u8; 3]>) -> Self{
+        if colors.len() == self.vertices.len() {
+            self.colors = Some(colors);
+        }
+        self
+    }
+
+    /// Moves the mesh to `center` and resizes its geometry so the farthest vertex lies at `radius`.
+    pub fn place(&mut self, center: Point3D, radius: f32){
+        let extent = self.vertices.iter().map(|v| v.dot(*v).sqrt()).fold(0.0, f32::max);
+        if extent > 0.0 {
+            let factor = radius / extent;
+            for v in &mut self.vertices {
+                *v = *v * factor;
+            }
+        }
+        self.center = center;
+    }
+}
+
+fn bounds_center(vertices: &[Point3D]) -> Point3D{
+    if vertices.is_empty() {
+        return Point3D::new(0.0, 0.0, 0.0);
+    }
+    let mut min = vertices[0];
+    let mut max = vertices[0];
+    for v in vertices {
+        min = Point3D::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z));
+        max = Point3D::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z));
+    }
+    Point3D::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0, (min.z + max.z) / 2.0)
+}
+
+impl Transformable for Mesh {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+pub fn mesh_triangles(object: &Mesh) -> Vec<Triangle3D>{
+    let center = object.center;
+    let points: Vec<Point3D> = object.vertices.iter()
+        .map(|v| transform_point(*v, object.angle_x, object.angle_y, object.scale, center))
+        .collect();
+
+    object.faces.iter().map(|&[a, b, c]| {
+        let triangle = Triangle3D::new(points[a], points[b], points[c], object.color);
+        match &object.colors {
+            Some(colors) => triangle.with_colors([colors[a], colors[b], colors[c]]),
+            None => triangle,
+        }
+    }).collect()
+}
+
+pub fn draw_mesh(object: &Mesh, view: &View, frame: &mut Frame){
+    for triangle in mesh_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
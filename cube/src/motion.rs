@@ -0,0 +1,226 @@
+use std::time::{Duration, Instant};
+
+use crate::config::Action;
+use crate::geometry::*;
+use crate::history::Edit;
+use crate::transform::{Snap, Speeds};
+
+/// Updates per second of the fixed-timestep loop.
+pub const TICK_RATE: u32 = 60;
+
+/// Time further behind than this is dropped instead of caught up on, e.g. after
+/// the window was blocked while being dragged.
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+/// Splits elapsed time into ticks of the same length, so updates do not depend on
+/// how often events arrive or frames are drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    tick: Duration,
+    next: Instant,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        let tick = Duration::from_secs(1) / TICK_RATE;
+        Clock { tick, next: Instant::now() + tick }
+    }
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Clock::default()
+    }
+
+    /// Length of one tick in seconds.
+    pub fn dt(&self) -> f32 {
+        self.tick.as_secs_f32()
+    }
+
+    /// When the next tick is due.
+    pub fn deadline(&self) -> Instant {
+        self.next
+    }
+
+    /// Starts counting ticks from `now`, e.g. after idling with nothing to update.
+    pub fn reset(&mut self, now: Instant) {
+        self.next = now + self.tick;
+    }
+
+    /// The number of ticks that came due by `now`.
+    pub fn advance(&mut self, now: Instant) -> u32 {
+        if now.saturating_duration_since(self.next) > MAX_LAG {
+            self.next = now - MAX_LAG;
+        }
+        let mut ticks = 0;
+        while self.next <= now {
+            self.next += self.tick;
+            ticks += 1;
+        }
+        ticks
+    }
+}
+
+/// Turns the held move, turn and scale actions into edits of the selection.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Motion {
+    /// Movement and turning not applied yet, while snapping waits for a whole increment.
+    movement: [f32; 3],
+    turn: [f32; 2],
+}
+
+impl Motion {
+    pub fn new() -> Self {
+        Motion::default()
+    }
+
+    /// Called when `action` starts being held. With snapping on, the next update goes
+    /// a whole increment that way at once, so a tap still reaches the next line.
+    pub fn start(&mut self, action: Action, snap: Snap) {
+        if !snap.enabled {
+            return;
+        }
+        match action {
+            Action::MoveUp => self.movement[1] = -snap.grid,
+            Action::MoveDown => self.movement[1] = snap.grid,
+            Action::MoveLeft => self.movement[0] = -snap.grid,
+            Action::MoveRight => self.movement[0] = snap.grid,
+            Action::MoveNear => self.movement[2] = -snap.grid,
+            Action::MoveFar => self.movement[2] = snap.grid,
+            Action::TurnUp => self.turn[0] = -snap.angle,
+            Action::TurnDown => self.turn[0] = snap.angle,
+            Action::TurnLeft => self.turn[1] = -snap.angle,
+            Action::TurnRight => self.turn[1] = snap.angle,
+            _ => {}
+        }
+    }
+
+    /// Drops what is left over, once nothing is held.
+    pub fn stop(&mut self) {
+        *self = Motion::default();
+    }
+
+    /// The edits that the `held` actions make over `dt` seconds at `speeds`.
+    pub fn update(&mut self, held: impl IntoIterator<Item = Action>, speeds: Speeds, snap: Snap, dt: f32) -> Vec<Edit> {
+        let mut scale = 0.0;
+        for action in held {
+            let (move_by, turn_by) = (speeds.translate * dt, speeds.rotate * dt);
+            match action {
+                Action::MoveUp => self.movement[1] -= move_by,
+                Action::MoveDown => self.movement[1] += move_by,
+                Action::MoveLeft => self.movement[0] -= move_by,
+                Action::MoveRight => self.movement[0] += move_by,
+                Action::MoveNear => self.movement[2] -= move_by,
+                Action::MoveFar => self.movement[2] += move_by,
+                Action::TurnUp => self.turn[0] -= turn_by,
+                Action::TurnDown => self.turn[0] += turn_by,
+                Action::TurnLeft => self.turn[1] -= turn_by,
+                Action::TurnRight => self.turn[1] += turn_by,
+                Action::Grow => scale += speeds.scale * dt,
+                Action::Shrink => scale -= speeds.scale * dt,
+                _ => {}
+            }
+        }
+
+        let mut edits = Vec::new();
+        let mut moved = [0.0; 3];
+        for (applied, pending) in moved.iter_mut().zip(&mut self.movement) {
+            (*applied, *pending) = snap.quantize(*pending, snap.grid);
+        }
+        if moved.iter().any(|&d| d != 0.0) {
+            edits.push(Edit::Move(Point3D::new(moved[0], moved[1], moved[2]), snap));
+        }
+        let mut turned = [0.0; 2];
+        for (applied, pending) in turned.iter_mut().zip(&mut self.turn) {
+            (*applied, *pending) = snap.quantize(*pending, snap.angle);
+        }
+        if turned.iter().any(|&a| a != 0.0) {
+            edits.push(Edit::Rotate(turned[0], turned[1], snap));
+        }
+        if scale != 0.0 {
+            edits.push(Edit::Scale(scale));
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(edits: &[Edit]) -> Vec<Point3D> {
+        edits.iter().filter_map(|e| match e {
+            Edit::Move(d, _) => Some(*d),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn counts_the_ticks_that_came_due() {
+        let mut clock = Clock::new();
+        let start = Instant::now();
+        clock.reset(start);
+        assert_eq!(clock.advance(start), 0);
+        assert_eq!(clock.advance(start + Duration::from_millis(100)), TICK_RATE / 10);
+        assert_eq!(clock.advance(start + Duration::from_millis(100)), 0, "ticks are only counted once");
+        // Any gap within `MAX_LAG` is caught up on in full.
+        assert_eq!(clock.advance(start + Duration::from_millis(300)), TICK_RATE / 5);
+        assert!(clock.deadline() > start + Duration::from_millis(300));
+        assert!((clock.dt() - 1.0 / TICK_RATE as f32).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lag_past_the_cap_is_dropped() {
+        let mut clock = Clock::new();
+        let start = Instant::now();
+        clock.reset(start);
+        let ticks = clock.advance(start + Duration::from_secs(10));
+        let most = (MAX_LAG.as_secs_f32() * TICK_RATE as f32).round() as u32 + 1;
+        assert!((most - 1..=most).contains(&ticks), "{} ticks caught up on", ticks);
+        // Back on time after that.
+        assert_eq!(clock.advance(start + Duration::from_secs(10)), 0);
+    }
+
+    #[test]
+    fn snapped_movement_carries_across_ticks() {
+        let snap = Snap { enabled: true, grid: 0.25, angle: 0.5 };
+        let speeds = Speeds { translate: 0.6, rotate: 1.2, scale: 0.3 };
+        let dt = 1.0 / TICK_RATE as f32;
+        let mut motion = Motion::new();
+
+        // A tap goes a whole grid step at once.
+        motion.start(Action::MoveRight, snap);
+        let first = moves(&motion.update([Action::MoveRight], speeds, snap, dt));
+        assert_eq!(first.len(), 1);
+        assert!((first[0].x - 0.25).abs() < 1e-6);
+
+        // Held on, it waits for a whole step's worth of speed: 0.25 / 0.01 = 25 ticks.
+        let mut ticks = 0;
+        let step = loop {
+            ticks += 1;
+            let edits = motion.update([Action::MoveRight], speeds, snap, dt);
+            if let Some(&d) = moves(&edits).first() {
+                break d;
+            }
+            assert!(ticks < 100, "never moved");
+        };
+        assert!((24..=26).contains(&ticks), "moved after {} ticks", ticks);
+        assert!((step.x - 0.25).abs() < 1e-6 && step.y == 0.0 && step.z == 0.0);
+
+        // Turning carries the same way, and stopping drops what is left.
+        let turns = (0..TICK_RATE).filter(|_| {
+            motion.update([Action::TurnLeft], speeds, snap, dt).iter().any(|e| matches!(e, Edit::Rotate(..)))
+        }).count();
+        assert_eq!(turns, 2, "1.2 rad/s for a second is two 0.5 rad increments");
+        motion.stop();
+        assert!(motion.update([], speeds, snap, dt).is_empty());
+    }
+
+    #[test]
+    fn unsnapped_movement_applies_every_tick() {
+        let mut motion = Motion::new();
+        let edits = motion.update([Action::MoveNear, Action::Grow], Speeds::default(), Snap::default(), 0.5);
+        assert!((moves(&edits)[0].z + 0.3).abs() < 1e-6);
+        assert!(edits.iter().any(|e| matches!(e, Edit::Scale(s) if (s - 0.15).abs() < 1e-6)));
+    }
+}
@@ -1,14 +1,46 @@
+use crate::animation::Animation;
 use crate::geometry::*;
 use crate::operations::*;
 use crate::drawing::*;
+use crate::mesh::{mesh_triangles, Mesh};
+use crate::polyhedra::{self, MAX_ICOSPHERE_LEVEL};
+use crate::primitives::{self, Capsule, Cone, Cylinder, Disk, Torus};
+use crate::particles::Emitter;
+use crate::physics::Body;
+use crate::raytrace::Material;
+
+/// What every kind of object carries besides its shape, pose and color.
+#[derive(Clone, Default)]
+pub struct ObjectState{
+    /// Keys that pose the object over time.
+    pub animation: Animation,
+    /// Mass and motion for the physics simulation.
+    pub body: Body,
+    /// How the ray tracer reflects and refracts light off the object.
+    pub material: Material,
+}
 
+impl ObjectState{
+    /// State of an object pinned in place for the physics simulation.
+    pub fn fixed() -> Self{
+        ObjectState{ body: Body::fixed(), ..ObjectState::default() }
+    }
+}
 
+#[derive(Clone)]
 pub enum Objects{
     Cube(Cube),
     Plane(Plane),
     Triangle(Triangle),
     Pyramid(Pyramid),
-    Sphere(Sphere)
+    Sphere(Sphere),
+    Mesh(Mesh),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Torus(Torus),
+    Capsule(Capsule),
+    Disk(Disk),
+    Emitter(Emitter),
 }
 
 #[derive(Clone)]
@@ -20,6 +52,7 @@ pub struct Cube{
     pub scale: f32,
     pub color: [u8; 3],
     pub selected: bool,
+    pub state: ObjectState,
 }
 
 impl Cube{
@@ -32,6 +65,7 @@ impl Cube{
             scale: 1.0,
             color,
             selected: false,
+            state: ObjectState::default(),
         }
     }
 }
@@ -45,6 +79,7 @@ pub struct Plane{
     pub scale: f32,
     pub color: [u8; 3],
     pub selected: bool,
+    pub state: ObjectState,
 }
 
 impl Plane{
@@ -57,6 +92,7 @@ impl Plane{
             scale: 1.0,
             color,
             selected: false,
+            state: ObjectState::fixed(),
         }
     }
 }
@@ -71,6 +107,7 @@ pub struct Triangle{
     pub scale: f32,
     pub color: [u8; 3],
     pub selected: bool,
+    pub state: ObjectState,
 }
 
 impl Triangle{
@@ -84,21 +121,41 @@ impl Triangle{
             scale: 1.0,
             color,
             selected: false,
+            state: ObjectState::fixed(),
         }
     }
 }
 
+/// How a `Sphere` is broken into triangles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tessellation{
+    /// Latitude/longitude grid of `lat_steps` x `lon_steps` quads.
+    Uv,
+    /// Icosahedron subdivided the given number of times (0 to `MAX_ICOSPHERE_LEVEL`).
+    Icosphere(u32),
+    /// Icosphere whose level follows the sphere's size on screen.
+    Auto,
+}
+
+/// Level used for automatic spheres when no view is at hand, e.g. on export.
+const AUTO_LEVEL: u32 = 3;
+
+/// Longest icosphere edge, in pixels, that automatic level of detail allows.
+const LOD_EDGE_PIXELS: f32 = 12.0;
+
 #[derive(Clone)]
 pub struct Sphere{
     pub center: Point3D,
     pub radius: f32,
     pub lat_steps: i32,
     pub lon_steps: i32,
+    pub tessellation: Tessellation,
     pub angle_x: f32,
     pub angle_y: f32,
     pub scale: f32,
     pub color: [u8; 3],
     pub selected: bool,
+    pub state: ObjectState,
 
 }
 
@@ -109,12 +166,37 @@ impl Sphere{
             radius,
             lat_steps,
             lon_steps,
+            tessellation: Tessellation::Uv,
             angle_x: -0.6,
             angle_y: 0.4,
             scale: 1.0,
             color,
             selected: false,
+            state: ObjectState::default(),
+        }
+    }
+
+    pub fn with_tessellation(mut self, tessellation: Tessellation) -> Self{
+        self.tessellation = tessellation;
+        self
+    }
+
+    /// Icosphere level for this sphere as seen through `view` on a `width` pixel wide frame.
+    ///
+    /// Picks the coarsest level whose edges stay under `LOD_EDGE_PIXELS` on screen.
+    pub fn level_of_detail(&self, view: &View, width: u32) -> u32{
+        let center = rotate_and_translate(self.center, self.angle_x, self.angle_y, self.scale, self.center);
+        let radius = self.radius * self.scale.abs();
+        let depth = view.camera.to_view(center).z;
+        if depth <= radius {
+            // The camera is inside or right next to the sphere.
+            return MAX_ICOSPHERE_LEVEL;
         }
+        let focal = (width as f32 / 2.0) / (view.camera.fov.to_radians() / 2.0).tan();
+        let pixels = focal * radius / depth;
+        // An icosahedron's edge is about 1.05 radii, halved by each subdivision.
+        let level = (1.05 * pixels / LOD_EDGE_PIXELS).log2().ceil();
+        (level.max(0.0) as u32).min(MAX_ICOSPHERE_LEVEL)
     }
 }
 
@@ -128,6 +210,7 @@ pub struct Pyramid{
     pub scale: f32,
     pub color: [u8; 3],
     pub selected: bool,
+    pub state: ObjectState,
 }
 
 impl Pyramid{
@@ -142,6 +225,7 @@ impl Pyramid{
             scale: 1.0,
             color,
             selected: false,
+            state: ObjectState::default(),
         }
     }
 }
@@ -154,6 +238,13 @@ impl Transformable for Objects {
             Objects::Triangle(triangle) => triangle.move_trans(x, y, z),
             Objects::Pyramid(pyramid) => pyramid.move_trans(x, y, z),
             Objects::Sphere(sphere) => sphere.move_trans(x, y, z),
+            Objects::Mesh(mesh) => mesh.move_trans(x, y, z),
+            Objects::Cylinder(cylinder) => cylinder.move_trans(x, y, z),
+            Objects::Cone(cone) => cone.move_trans(x, y, z),
+            Objects::Torus(torus) => torus.move_trans(x, y, z),
+            Objects::Capsule(capsule) => capsule.move_trans(x, y, z),
+            Objects::Disk(disk) => disk.move_trans(x, y, z),
+            Objects::Emitter(emitter) => emitter.move_trans(x, y, z),
         }
     }
     fn rotate(&mut self, ax: f32, ay: f32) {
@@ -163,6 +254,13 @@ impl Transformable for Objects {
             Objects::Triangle(triangle) => triangle.rotate(ax, ay),
             Objects::Pyramid(pyramid) => pyramid.rotate(ax, ay),
             Objects::Sphere(sphere) => sphere.rotate(ax, ay),
+            Objects::Mesh(mesh) => mesh.rotate(ax, ay),
+            Objects::Cylinder(cylinder) => cylinder.rotate(ax, ay),
+            Objects::Cone(cone) => cone.rotate(ax, ay),
+            Objects::Torus(torus) => torus.rotate(ax, ay),
+            Objects::Capsule(capsule) => capsule.rotate(ax, ay),
+            Objects::Disk(disk) => disk.rotate(ax, ay),
+            Objects::Emitter(emitter) => emitter.rotate(ax, ay),
         }
     }
     fn scale(&mut self, s: f32) {
@@ -172,6 +270,13 @@ impl Transformable for Objects {
             Objects::Triangle(triangle) => triangle.scale(s),
             Objects::Pyramid(pyramid) => pyramid.scale(s),
             Objects::Sphere(sphere) => sphere.scale(s),
+            Objects::Mesh(mesh) => mesh.scale(s),
+            Objects::Cylinder(cylinder) => cylinder.scale(s),
+            Objects::Cone(cone) => cone.scale(s),
+            Objects::Torus(torus) => torus.scale(s),
+            Objects::Capsule(capsule) => capsule.scale(s),
+            Objects::Disk(disk) => disk.scale(s),
+            Objects::Emitter(emitter) => emitter.scale(s),
         }
     }
     fn select(&mut self) {
@@ -181,6 +286,13 @@ impl Transformable for Objects {
             Objects::Triangle(triangle) => triangle.select(),
             Objects::Pyramid(pyramid) => pyramid.select(),
             Objects::Sphere(sphere) => sphere.select(),
+            Objects::Mesh(mesh) => mesh.select(),
+            Objects::Cylinder(cylinder) => cylinder.select(),
+            Objects::Cone(cone) => cone.select(),
+            Objects::Torus(torus) => torus.select(),
+            Objects::Capsule(capsule) => capsule.select(),
+            Objects::Disk(disk) => disk.select(),
+            Objects::Emitter(emitter) => emitter.select(),
         }
     }
     fn deselect(&mut self) {
@@ -190,6 +302,212 @@ impl Transformable for Objects {
             Objects::Triangle(triangle) => triangle.deselect(),
             Objects::Pyramid(pyramid) => pyramid.deselect(),
             Objects::Sphere(sphere) => sphere.deselect(),
+            Objects::Mesh(mesh) => mesh.deselect(),
+            Objects::Cylinder(cylinder) => cylinder.deselect(),
+            Objects::Cone(cone) => cone.deselect(),
+            Objects::Torus(torus) => torus.deselect(),
+            Objects::Capsule(capsule) => capsule.deselect(),
+            Objects::Disk(disk) => disk.deselect(),
+            Objects::Emitter(emitter) => emitter.deselect(),
+        }
+    }
+    fn is_selected(&self) -> bool {
+        match self {
+            Objects::Cube(cube) => cube.is_selected(),
+            Objects::Plane(plane) => plane.is_selected(),
+            Objects::Triangle(triangle) => triangle.is_selected(),
+            Objects::Pyramid(pyramid) => pyramid.is_selected(),
+            Objects::Sphere(sphere) => sphere.is_selected(),
+            Objects::Mesh(mesh) => mesh.is_selected(),
+            Objects::Cylinder(cylinder) => cylinder.is_selected(),
+            Objects::Cone(cone) => cone.is_selected(),
+            Objects::Torus(torus) => torus.is_selected(),
+            Objects::Capsule(capsule) => capsule.is_selected(),
+            Objects::Disk(disk) => disk.is_selected(),
+            Objects::Emitter(emitter) => emitter.is_selected(),
+        }
+    }
+}
+
+impl Objects {
+    /// Moves the object by `delta` in world space.
+    ///
+    /// Shapes placed with `rotate_and_translate` land at `(1 + scale)` times their
+    /// stored position, so `move_trans` is scaled down to match.
+    pub fn move_world(&mut self, delta: Point3D) {
+        let gain = match self {
+            Objects::Mesh(_) => 1.0,
+            _ => 1.0 + self.scale_factor(),
+        };
+        if gain.abs() > 1e-3 {
+            let step = delta * (1.0 / gain);
+            self.move_trans(step.x, step.y, step.z);
+        }
+    }
+
+    /// Name of the object's type, e.g. "Cube".
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Objects::Cube(_) => "Cube",
+            Objects::Plane(_) => "Plane",
+            Objects::Triangle(_) => "Triangle",
+            Objects::Pyramid(_) => "Pyramid",
+            Objects::Sphere(_) => "Sphere",
+            Objects::Mesh(_) => "Mesh",
+            Objects::Cylinder(_) => "Cylinder",
+            Objects::Cone(_) => "Cone",
+            Objects::Torus(_) => "Torus",
+            Objects::Capsule(_) => "Capsule",
+            Objects::Disk(_) => "Disk",
+            Objects::Emitter(_) => "Emitter",
+        }
+    }
+
+    /// Center of the world bounding box, where the gizmo sits.
+    pub fn position(&self) -> Point3D {
+        self.aabb().center()
+    }
+
+    pub fn set_position(&mut self, position: Point3D) {
+        self.move_world(position - self.position());
+    }
+
+    pub fn set_angles(&mut self, angle_x: f32, angle_y: f32) {
+        let (x, y) = self.angles();
+        self.rotate(angle_x - x, angle_y - y);
+    }
+
+    /// Sets the scale factor, keeping the object where it is; `scale` alone also
+    /// moves every object but meshes.
+    pub fn set_scale_factor(&mut self, scale: f32) {
+        let position = self.position();
+        self.scale(scale - self.scale_factor());
+        self.set_position(position);
+    }
+
+    /// `angle_x` and `angle_y`, in radians.
+    pub fn angles(&self) -> (f32, f32) {
+        let (angle_x, angle_y, _) = self.pose();
+        (angle_x, angle_y)
+    }
+
+    pub fn scale_factor(&self) -> f32 {
+        self.pose().2
+    }
+
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Objects::Cube(o) => o.color,
+            Objects::Plane(o) => o.color,
+            Objects::Triangle(o) => o.color,
+            Objects::Pyramid(o) => o.color,
+            Objects::Sphere(o) => o.color,
+            Objects::Mesh(o) => o.color,
+            Objects::Cylinder(o) => o.color,
+            Objects::Cone(o) => o.color,
+            Objects::Torus(o) => o.color,
+            Objects::Capsule(o) => o.color,
+            Objects::Disk(o) => o.color,
+            Objects::Emitter(o) => o.color,
+        }
+    }
+
+    pub fn set_color(&mut self, color: [u8; 3]) {
+        match self {
+            Objects::Cube(o) => o.color = color,
+            Objects::Plane(o) => o.color = color,
+            Objects::Triangle(o) => o.color = color,
+            Objects::Pyramid(o) => o.color = color,
+            Objects::Sphere(o) => o.color = color,
+            Objects::Mesh(o) => o.color = color,
+            Objects::Cylinder(o) => o.color = color,
+            Objects::Cone(o) => o.color = color,
+            Objects::Torus(o) => o.color = color,
+            Objects::Capsule(o) => o.color = color,
+            Objects::Disk(o) => o.color = color,
+            Objects::Emitter(o) => o.color = color,
+        }
+    }
+
+    pub fn state(&self) -> &ObjectState {
+        match self {
+            Objects::Cube(o) => &o.state,
+            Objects::Plane(o) => &o.state,
+            Objects::Triangle(o) => &o.state,
+            Objects::Pyramid(o) => &o.state,
+            Objects::Sphere(o) => &o.state,
+            Objects::Mesh(o) => &o.state,
+            Objects::Cylinder(o) => &o.state,
+            Objects::Cone(o) => &o.state,
+            Objects::Torus(o) => &o.state,
+            Objects::Capsule(o) => &o.state,
+            Objects::Disk(o) => &o.state,
+            Objects::Emitter(o) => &o.state,
+        }
+    }
+
+    pub fn state_mut(&mut self) -> &mut ObjectState {
+        match self {
+            Objects::Cube(o) => &mut o.state,
+            Objects::Plane(o) => &mut o.state,
+            Objects::Triangle(o) => &mut o.state,
+            Objects::Pyramid(o) => &mut o.state,
+            Objects::Sphere(o) => &mut o.state,
+            Objects::Mesh(o) => &mut o.state,
+            Objects::Cylinder(o) => &mut o.state,
+            Objects::Cone(o) => &mut o.state,
+            Objects::Torus(o) => &mut o.state,
+            Objects::Capsule(o) => &mut o.state,
+            Objects::Disk(o) => &mut o.state,
+            Objects::Emitter(o) => &mut o.state,
+        }
+    }
+
+    pub fn default_body(&self) -> Body {
+        match self {
+            Objects::Plane(_) | Objects::Triangle(_) | Objects::Disk(_) | Objects::Emitter(_) => Body::fixed(),
+            _ => Body::default(),
+        }
+    }
+
+    /// Whether the object takes part in collisions; particle emitters do not.
+    pub fn collides(&self) -> bool {
+        !matches!(self, Objects::Emitter(_))
+    }
+
+    fn pose(&self) -> (f32, f32, f32) {
+        match self {
+            Objects::Cube(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Plane(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Triangle(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Pyramid(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Sphere(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Mesh(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Cylinder(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Cone(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Torus(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Capsule(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Disk(o) => (o.angle_x, o.angle_y, o.scale),
+            Objects::Emitter(o) => (o.angle_x, o.angle_y, o.scale),
+        }
+    }
+
+    /// The object's triangles in world space, as they are rasterized.
+    pub fn triangles(&self) -> Vec<Triangle3D> {
+        match self {
+            Objects::Cube(cube) => cube_triangles(cube),
+            Objects::Plane(plane) => plane_triangles(plane),
+            Objects::Triangle(triangle) => triangle_triangles(triangle),
+            Objects::Pyramid(pyramid) => pyramid_triangles(pyramid),
+            Objects::Sphere(sphere) => sphere_triangles(sphere),
+            Objects::Mesh(mesh) => mesh_triangles(mesh),
+            Objects::Cylinder(cylinder) => primitives::cylinder_triangles(cylinder),
+            Objects::Cone(cone) => primitives::cone_triangles(cone),
+            Objects::Torus(torus) => primitives::torus_triangles(torus),
+            Objects::Capsule(capsule) => primitives::capsule_triangles(capsule),
+            Objects::Disk(disk) => primitives::disk_triangles(disk),
+            // Particles are drawn as screen squares, not triangles.
+            Objects::Emitter(_) => Vec::new(),
         }
     }
 }
@@ -200,6 +518,7 @@ pub trait Transformable {
     fn scale(&mut self, scale_delta: f32);
     fn select(&mut self);
     fn deselect(&mut self);
+    fn is_selected(&self) -> bool;
 }
 
 impl Transformable for Cube {
@@ -223,6 +542,9 @@ impl Transformable for Cube {
     fn deselect(&mut self) {
         self.selected = false;
     }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
 }
 
 impl Transformable for Plane {
@@ -246,6 +568,9 @@ impl Transformable for Plane {
     fn deselect(&mut self) {
         self.selected = false;
     }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
 }
 
 impl Transformable for Triangle {
@@ -269,16 +594,17 @@ impl Transformable for Triangle {
     fn deselect(&mut self) {
         self.selected = false;
     }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
 }
 
 
 impl Transformable for Sphere {
     fn move_trans(&mut self, x: f32, y: f32, z: f32) {
-        for p in [&mut self.center] {
-            p.x += x;
-            p.y += y;
-            p.z += z;
-        }
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
     }
     fn rotate(&mut self, ax: f32, ay: f32) {
         self.angle_x += ax;
@@ -293,6 +619,9 @@ impl Transformable for Sphere {
     fn deselect(&mut self) {
         self.selected = false;
     }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
 }
 
 impl Transformable for Pyramid {
@@ -316,10 +645,14 @@ impl Transformable for Pyramid {
     fn deselect(&mut self) {
         self.selected = false;
     }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
 }
 
 
-pub fn draw_cube(object: &Cube, light: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]){
+pub fn cube_triangles(object: &Cube) -> Vec<Triangle3D>{
+    let mut triangles = Vec::new();
     let e1 = object.e1;
     let e2 = object.e2;
     let angle_x = object.angle_x;
@@ -338,26 +671,29 @@ pub fn draw_cube(object: &Cube, light: Point3D, framebuffer: &mut [u8], zbuffer:
     let p8 = rotate_and_translate(Point3D::new(e1.x, e2.y, e1.z + side_length), angle_x, angle_y, object.scale, center);
 
     //Front
-    triangle_3d_fill(Triangle3D::new(p1, p2, p3, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p1, p3, p4, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p1, p2, p3, object.color));
+    triangles.push(Triangle3D::new(p1, p3, p4, object.color));
     //Top
-    triangle_3d_fill(Triangle3D::new(p6, p2, p1, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p1, p5, p6, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p6, p2, p1, object.color));
+    triangles.push(Triangle3D::new(p1, p5, p6, object.color));
     //Left
-    triangle_3d_fill(Triangle3D::new(p1, p4, p5, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p4,p8, p5, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p1, p4, p5, object.color));
+    triangles.push(Triangle3D::new(p4,p8, p5, object.color));
     //Right
-    triangle_3d_fill(Triangle3D::new(p7, p3, p2, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p2,p6, p7, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p7, p3, p2, object.color));
+    triangles.push(Triangle3D::new(p2,p6, p7, object.color));
     //Bottom
-    triangle_3d_fill(Triangle3D::new(p8, p4, p3, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p3,p7, p8, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p8, p4, p3, object.color));
+    triangles.push(Triangle3D::new(p3,p7, p8, object.color));
     //Back
-    triangle_3d_fill(Triangle3D::new(p7, p6, p5, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p8,p7, p5, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p7, p6, p5, object.color));
+    triangles.push(Triangle3D::new(p8,p7, p5, object.color));
+
+    triangles
 }
 
-pub fn draw_plane(object: &Plane, light: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]){
+pub fn plane_triangles(object: &Plane) -> Vec<Triangle3D>{
+    let mut triangles = Vec::new();
     let e1 = object.e1;
     let e2 = object.e2;
     let angle_x = object.angle_x;
@@ -371,11 +707,14 @@ pub fn draw_plane(object: &Plane, light: Point3D, framebuffer: &mut [u8], zbuffe
     let p3 = rotate_and_translate(Point3D::new(e2.x, e2.y, e1.z), angle_x, angle_y, object.scale, center);
     let p4 = rotate_and_translate(Point3D::new(e1.x, e2.y, e1.z), angle_x, angle_y, object.scale, center);
 
-    triangle_3d_fill(Triangle3D::new(p1, p2, p3, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p1, p3, p4, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p1, p2, p3, object.color));
+    triangles.push(Triangle3D::new(p1, p3, p4, object.color));
+
+    triangles
 }
 
-pub fn draw_triangle(object: &Triangle, light: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]){
+pub fn triangle_triangles(object: &Triangle) -> Vec<Triangle3D>{
+    let mut triangles = Vec::new();
     let e1 = object.e1;
     let e2 = object.e2;
     let e3 = object.e3;
@@ -392,10 +731,13 @@ pub fn draw_triangle(object: &Triangle, light: Point3D, framebuffer: &mut [u8],
     let p2 = rotate_and_translate(Point3D::new(e2.x, e2.y, e2.z), angle_x, angle_y, object.scale, center);
     let p3 = rotate_and_translate(Point3D::new(e3.x, e3.y, e3.z), angle_x, angle_y, object.scale, center);
 
-    triangle_3d_fill(Triangle3D::new(p3, p2, p1, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p3, p2, p1, object.color));
+
+    triangles
 }
 
-pub fn draw_pyramid(object: &Pyramid, light: Point3D, framebuffer: &mut [u8], zbuffer: &mut [f32]){
+pub fn pyramid_triangles(object: &Pyramid) -> Vec<Triangle3D>{
+    let mut triangles = Vec::new();
     let e1 = object.e1;
     let e2 = object.e2;
     let e3 = object.e3;
@@ -412,17 +754,28 @@ pub fn draw_pyramid(object: &Pyramid, light: Point3D, framebuffer: &mut [u8], zb
     let p5 = rotate_and_translate(Point3D::new(e3.x, e3.y, e3.z), angle_x, angle_y, object.scale, center);
 
     //Bottom
-    triangle_3d_fill(Triangle3D::new(p3, p2, p1, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p4, p2, p3, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p3, p2, p1, object.color));
+    triangles.push(Triangle3D::new(p4, p2, p3, object.color));
 
     //Sides
-    triangle_3d_fill(Triangle3D::new(p1, p2, p5, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p5, p2, p4, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p4, p3, p5, object.color), object.selected, light, framebuffer, zbuffer);
-    triangle_3d_fill(Triangle3D::new(p3, p1, p5, object.color), object.selected, light, framebuffer, zbuffer);
+    triangles.push(Triangle3D::new(p1, p2, p5, object.color));
+    triangles.push(Triangle3D::new(p5, p2, p4, object.color));
+    triangles.push(Triangle3D::new(p4, p3, p5, object.color));
+    triangles.push(Triangle3D::new(p3, p1, p5, object.color));
+
+    triangles
+}
+
+pub fn sphere_triangles(object: &Sphere) -> Vec<Triangle3D>{
+    match object.tessellation {
+        Tessellation::Uv => uv_sphere_triangles(object),
+        Tessellation::Icosphere(level) => icosphere_triangles(object, level),
+        Tessellation::Auto => icosphere_triangles(object, AUTO_LEVEL),
+    }
 }
 
-pub fn draw_sphere(object: &Sphere,light: Point3D,framebuffer: &mut [u8],zbuffer: &mut [f32]) {
+fn uv_sphere_triangles(object: &Sphere) -> Vec<Triangle3D>{
+    let mut triangles = Vec::new();
     let angle_x = object.angle_x;
     let angle_y = object.angle_y;
     let center = object.center;
@@ -462,8 +815,59 @@ pub fn draw_sphere(object: &Sphere,light: Point3D,framebuffer: &mut [u8],zbuffer
             let p3 = rotate_and_translate(p3, angle_x, angle_y, object.scale, center);
             let p4 = rotate_and_translate(p4, angle_x, angle_y, object.scale, center);
 
-            triangle_3d_fill(Triangle3D::new(p1, p2, p3, object.color), object.selected, light, framebuffer, zbuffer);
-            triangle_3d_fill(Triangle3D::new(p1, p3, p4, object.color), object.selected, light, framebuffer, zbuffer);
+            triangles.push(Triangle3D::new(p1, p2, p3, object.color));
+            triangles.push(Triangle3D::new(p1, p3, p4, object.color));
         }
     }
+
+    triangles
+}
+
+/// Smooth-shaded icosphere triangles at subdivision `level`.
+pub fn icosphere_triangles(object: &Sphere, level: u32) -> Vec<Triangle3D>{
+    let sphere = polyhedra::icosphere(level);
+    let origin = Point3D::new(0.0, 0.0, 0.0);
+    let vertices: Vec<(Point3D, Point3D)> = sphere.vertices.iter().map(|&n| (
+        rotate_and_translate(object.center + n * object.radius, object.angle_x, object.angle_y, object.scale, object.center),
+        rotate_x(rotate_y(n, object.angle_y, origin), object.angle_x, origin),
+    )).collect();
+
+    sphere.faces.iter().map(|face| {
+        let [a, b, c] = [vertices[face[0]], vertices[face[1]], vertices[face[2]]];
+        Triangle3D::new(a.0, b.0, c.0, object.color).with_normals([a.1, b.1, c.1])
+    }).collect()
+}
+
+pub fn draw_cube(object: &Cube, view: &View, frame: &mut Frame){
+    for triangle in cube_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_plane(object: &Plane, view: &View, frame: &mut Frame){
+    for triangle in plane_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_triangle(object: &Triangle, view: &View, frame: &mut Frame){
+    for triangle in triangle_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_pyramid(object: &Pyramid, view: &View, frame: &mut Frame){
+    for triangle in pyramid_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_sphere(object: &Sphere, view: &View, frame: &mut Frame){
+    let triangles = match object.tessellation {
+        Tessellation::Auto => icosphere_triangles(object, object.level_of_detail(view, frame.width)),
+        _ => sphere_triangles(object),
+    };
+    for triangle in triangles {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
 }
@@ -1,6 +1,4 @@
 use crate::geometry::*;
-use crate::HEIGHT;
-use crate::WIDTH;
 
 pub fn rotate_and_translate(offset: Point3D, angle_x: f32, angle_y: f32, scale: f32, center: Point3D) -> Point3D {
     let rotated = rotate_x(rotate_y(offset, angle_y, center), angle_x, center);
@@ -8,14 +6,22 @@ pub fn rotate_and_translate(offset: Point3D, angle_x: f32, angle_y: f32, scale:
     scale_object(translated, scale, center)
 }
 
+/// Rotates and scales `offset` about the origin, then places it relative to `center`.
+pub fn transform_point(offset: Point3D, angle_x: f32, angle_y: f32, scale: f32, center: Point3D) -> Point3D {
+    let origin = Point3D::new(0.0, 0.0, 0.0);
+    let rotated = rotate_x(rotate_y(offset, angle_y, origin), angle_x, origin);
+    center + rotated * scale
+}
 
-pub fn project(p: Point3D) -> Point {
-    let fov = 2.0;
-    let scale = WIDTH as f32 / (fov * p.z.max(0.01));
+/// Perspective-projects a camera-space point onto a `width`x`height` screen;
+/// `fov` is the horizontal field of view in degrees.
+pub fn project(p: Point3D, fov: f32, width: u32, height: u32) -> Point {
+    let focal = (width as f32 / 2.0) / (fov.to_radians() / 2.0).tan();
+    let scale = focal / p.z.max(0.01);
 
     Point {
-        x: (p.x * scale + WIDTH as f32 / 2.0) as u32,
-        y: (p.y * scale + HEIGHT as f32 / 2.0) as u32,
+        x: (p.x * scale + width as f32 / 2.0) as u32,
+        y: (p.y * scale + height as f32 / 2.0) as u32,
     }
 }
 
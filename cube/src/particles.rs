@@ -0,0 +1,347 @@
+use std::f32::consts::PI;
+
+use crate::bounds::NEAR;
+use crate::geometry::*;
+use crate::operations::*;
+use crate::drawing::*;
+use crate::objec::{ObjectState, Transformable};
+use crate::render::draw_segment;
+
+/// Most particles one emitter shows at once; the oldest beyond it are dropped.
+pub const MAX_PARTICLES: usize = 10_000;
+
+/// Half the length of the arms of the cross marking an emitter, before scaling.
+const MARKER_SIZE: f32 = 0.08;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParticleStyle {
+    /// Squares facing the camera, `size` across.
+    Quads,
+    /// Single pixels, whatever their size.
+    Points,
+}
+
+impl ParticleStyle {
+    pub fn parse(name: &str) -> Option<ParticleStyle> {
+        match name {
+            "quads" => Some(ParticleStyle::Quads),
+            "points" => Some(ParticleStyle::Points),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ParticleStyle::Quads => "quads",
+            ParticleStyle::Points => "points",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+    /// Particles cover what is behind them, like smoke.
+    Opaque,
+    /// Particles add their color to what is behind them, like sparks and fire.
+    Additive,
+}
+
+impl Blend {
+    pub fn parse(name: &str) -> Option<Blend> {
+        match name {
+            "opaque" => Some(Blend::Opaque),
+            "additive" => Some(Blend::Additive),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Blend::Opaque => "opaque",
+            Blend::Additive => "additive",
+        }
+    }
+}
+
+/// Source of particles that launch from `center` into a cone around the
+/// emitter's up axis (-Y, turned by its angles) and fall with `gravity`.
+///
+/// Particles are not stored: where each one is follows from `time`, so playing,
+/// scrubbing and recording the timeline all show the same particles.
+#[derive(Clone)]
+pub struct Emitter{
+    pub center: Point3D,
+    /// Particles launched per second.
+    pub rate: f32,
+    /// Seconds each particle lives.
+    pub lifetime: f32,
+    /// Launch speed in units per second.
+    pub speed: f32,
+    /// Angle in radians between the emitter's axis and the edge of the launch cone.
+    pub spread: f32,
+    /// Acceleration in units per second squared; positive falls, as +Y is down.
+    pub gravity: f32,
+    /// Color at launch, turning into `end_color` over each particle's life.
+    pub color: [u8; 3],
+    pub end_color: [u8; 3],
+    /// Quad size at launch, turning into `end_size` over each particle's life.
+    pub size: f32,
+    pub end_size: f32,
+    pub style: ParticleStyle,
+    pub blend: Blend,
+    /// Picks the launch directions; emitters with the same seed spray alike.
+    pub seed: u32,
+    /// Seconds the emitter has been running, set from the timeline or the viewer's clock.
+    pub time: f32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Emitter{
+    pub fn new(center: Point3D, rate: f32, lifetime: f32, speed: f32, color: [u8; 3]) -> Self{
+        Emitter{
+            center,
+            rate,
+            lifetime,
+            speed,
+            spread: 0.3,
+            gravity: 9.8,
+            color,
+            end_color: color,
+            size: 0.05,
+            end_size: 0.05,
+            style: ParticleStyle::Quads,
+            blend: Blend::Opaque,
+            seed: 0,
+            time: 0.0,
+            angle_x: 0.0,
+            angle_y: 0.0,
+            scale: 1.0,
+            selected: false,
+            // Emitters never collide, so nothing would move them.
+            state: ObjectState::fixed(),
+        }
+    }
+
+    /// Where particles launch from, in world space.
+    pub fn origin(&self) -> Point3D {
+        rotate_and_translate(self.center, self.angle_x, self.angle_y, self.scale, self.center)
+    }
+
+    /// Furthest any particle gets from `origin`, before scaling.
+    pub fn reach(&self) -> f32 {
+        let life = self.lifetime.max(0.0);
+        self.speed.abs() * life + 0.5 * self.gravity.abs() * life * life + self.size.max(self.end_size) / 2.0
+    }
+
+    /// The particles alive at `time`, oldest first.
+    pub fn particles(&self) -> Vec<Particle> {
+        if self.rate <= 0.0 || self.lifetime <= 0.0 || self.time < 0.0 {
+            return Vec::new();
+        }
+        // Particle `i` is launched at `i / rate` seconds.
+        let newest = (self.time * self.rate).floor() as u64;
+        let oldest = ((self.time - self.lifetime) * self.rate).max(0.0).ceil() as u64;
+        let oldest = oldest.max((newest + 1).saturating_sub(MAX_PARTICLES as u64));
+
+        let origin = Point3D::new(0.0, 0.0, 0.0);
+        let turn = |p: Point3D| rotate_x(rotate_y(p, self.angle_y, origin), self.angle_x, origin);
+        let start = self.origin();
+        (oldest..=newest).filter_map(|i| {
+            let age = self.time - i as f32 / self.rate;
+            if !(0.0..self.lifetime).contains(&age) {
+                return None;
+            }
+            // Even over the cone's cap, not bunched up around its axis.
+            let cos = 1.0 - noise(self.seed, i, 0) * (1.0 - self.spread.clamp(0.0, PI).cos());
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+            let around = 2.0 * PI * noise(self.seed, i, 1);
+            let direction = turn(Point3D::new(sin * around.cos(), -cos, sin * around.sin()));
+            let offset = direction * (self.speed * age) + Point3D::new(0.0, 0.5 * self.gravity * age * age, 0.0);
+
+            let life = age / self.lifetime;
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * life).round() as u8;
+            Some(Particle{
+                position: start + offset * self.scale,
+                size: (self.size + (self.end_size - self.size) * life) * self.scale.abs(),
+                color: [0, 1, 2].map(|k| mix(self.color[k], self.end_color[k])),
+                life,
+            })
+        }).collect()
+    }
+}
+
+/// One particle as it is at the emitter's `time`.
+#[derive(Clone, Copy, Debug)]
+pub struct Particle{
+    pub position: Point3D,
+    pub size: f32,
+    pub color: [u8; 3],
+    /// How far through its life it is: 0 at launch, nearly 1 when it dies.
+    pub life: f32,
+}
+
+impl Transformable for Emitter {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+/// Number in [0, 1) that only depends on its arguments.
+fn noise(seed: u32, index: u64, channel: u64) -> f32 {
+    // SplitMix64's finalizer.
+    let mut x = ((seed as u64) << 32) ^ index.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ channel.wrapping_mul(0xD1B5_4A32_D192_ED03);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Draws the emitter's particles, depth-tested against the scene, and a small
+/// cross where they launch from so an idle emitter can still be seen and picked.
+pub fn draw_emitter(object: &Emitter, view: &View, frame: &mut Frame){
+    let camera = &view.camera;
+    let focal = (frame.width as f32 / 2.0) / (camera.fov.to_radians() / 2.0).tan();
+    for particle in object.particles() {
+        let p = camera.to_view(particle.position);
+        if p.z < NEAR {
+            continue;
+        }
+        let x = p.x * focal / p.z + frame.width as f32 / 2.0;
+        let y = p.y * focal / p.z + frame.height as f32 / 2.0;
+        // A quad facing the camera sits at one depth, so it is a square on screen.
+        let half = match object.style {
+            ParticleStyle::Quads => particle.size * focal / p.z / 2.0,
+            ParticleStyle::Points => 0.0,
+        };
+        let (left, right) = ((x - half).round(), (x + half).round().max((x - half).round()));
+        let (top, bottom) = ((y - half).round(), (y + half).round().max((y - half).round()));
+        if right < 0.0 || bottom < 0.0 || left >= frame.width as f32 || top >= frame.height as f32 {
+            continue;
+        }
+        let [r, g, b] = particle.color;
+        for py in top.max(0.0) as u32..=(bottom as u32).min(frame.height - 1) {
+            for px in left.max(0.0) as u32..=(right as u32).min(frame.width - 1) {
+                match object.blend {
+                    Blend::Opaque => set_pixel(frame, px, py, p.z, [r, g, b, 255]),
+                    Blend::Additive => add_pixel(frame, px, py, p.z, [r, g, b, 255]),
+                }
+            }
+        }
+    }
+
+    let color = if object.selected { [255, 255, 255, 255] } else { [object.color[0], object.color[1], object.color[2], 255] };
+    let origin = object.origin();
+    let arm = MARKER_SIZE * object.scale.abs();
+    for axis in [Point3D::new(arm, 0.0, 0.0), Point3D::new(0.0, arm, 0.0), Point3D::new(0.0, 0.0, arm)] {
+        draw_segment(camera.to_view(origin - axis), camera.to_view(origin + axis), camera.fov, frame, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter() -> Emitter {
+        let mut emitter = Emitter::new(Point3D::new(0.5, -1.0, 2.0), 10.0, 1.0, 2.0, [255, 0, 40]);
+        emitter.time = 2.0;
+        emitter
+    }
+
+    fn distance(a: Point3D, b: Point3D) -> f32 {
+        let d = a - b;
+        d.dot(d).sqrt()
+    }
+
+    #[test]
+    fn count_follows_rate_and_lifetime() {
+        // Launched at 1.1 s to 2.0 s; the one launched at 1.0 s just died.
+        let particles = emitter().particles();
+        assert_eq!(particles.len(), 10);
+        assert!(particles.windows(2).all(|w| w[0].life > w[1].life), "not oldest first");
+        assert_eq!(particles.last().unwrap().life, 0.0);
+
+        let mut early = emitter();
+        early.time = 0.25;
+        assert_eq!(early.particles().len(), 3, "launched at 0, 0.1 and 0.2 s");
+        let idle: [fn(&mut Emitter); 3] = [|e| e.rate = 0.0, |e| e.lifetime = 0.0, |e| e.time = -1.0];
+        for change in idle {
+            let mut idle = emitter();
+            change(&mut idle);
+            assert!(idle.particles().is_empty());
+        }
+    }
+
+    #[test]
+    fn count_is_capped() {
+        let mut dense = emitter();
+        dense.rate = 100_000.0;
+        let particles = dense.particles();
+        assert_eq!(particles.len(), MAX_PARTICLES);
+        // The newest are kept.
+        assert_eq!(particles.last().unwrap().life, 0.0);
+    }
+
+    #[test]
+    fn same_seed_sprays_alike() {
+        let (a, mut b) = (emitter(), emitter());
+        let same = |a: &Emitter, b: &Emitter| a.particles().iter().zip(b.particles())
+            .all(|(p, q)| distance(p.position, q.position) == 0.0);
+        assert!(same(&a, &b));
+        b.seed = 7;
+        assert!(!same(&a, &b));
+    }
+
+    #[test]
+    fn color_and_size_turn_over_life() {
+        let mut emitter = emitter();
+        emitter.end_color = [0, 255, 40];
+        emitter.size = 0.1;
+        emitter.end_size = 0.3;
+        emitter.scale = 2.0;
+        for particle in emitter.particles() {
+            let life = particle.life;
+            assert!((0.0..1.0).contains(&life));
+            assert!((particle.size - (0.1 + 0.2 * life) * 2.0).abs() < 1e-5, "size {} at {}", particle.size, life);
+            assert_eq!(particle.color, [(255.0 * (1.0 - life)).round() as u8, (255.0 * life).round() as u8, 40]);
+        }
+    }
+
+    #[test]
+    fn particles_stay_within_reach() {
+        let mut emitter = emitter();
+        emitter.spread = 1.2;
+        emitter.angle_x = 0.7;
+        emitter.angle_y = -0.4;
+        emitter.scale = 1.5;
+        emitter.end_size = 0.4;
+        let (origin, reach) = (emitter.origin(), emitter.reach() * emitter.scale);
+        for time in [0.3, 1.0, 4.5] {
+            emitter.time = time;
+            for particle in emitter.particles() {
+                assert!(distance(particle.position, origin) <= reach + 1e-4, "{:?} out of reach", particle.position);
+            }
+        }
+    }
+}
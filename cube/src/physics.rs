@@ -0,0 +1,637 @@
+use crate::bounds::{Aabb, BoundingSphere, Obb};
+use crate::geometry::*;
+use crate::objec::{Objects, Transformable};
+use crate::operations::*;
+use crate::render::GROUND_LEVEL;
+use crate::scene::Scene;
+
+/// Velocity passes over all contacts per step; more settle stacks better.
+const ITERATIONS: usize = 12;
+
+/// Share of the remaining overlap pushed apart each step.
+const BAUMGARTE: f32 = 0.2;
+
+/// Overlap left alone, so resting contacts do not jitter between touching and not.
+const SLOP: f32 = 0.005;
+
+/// Points this close to touching already count as contacts.
+const MARGIN: f32 = 0.02;
+
+/// Extra overlap an edge crossing needs to win over a face as the contact normal.
+const EDGE_BIAS: f32 = 1e-3;
+
+/// Slower impacts than this do not bounce, so resting objects settle.
+const BOUNCE_THRESHOLD: f32 = 0.5;
+
+/// Objects in contact slower than this, in units or radians per second, stop.
+const REST_SPEED: f32 = 0.05;
+
+/// Share of velocity lost per second to air, so spinning and sliding die down.
+const DAMPING: f32 = 0.1;
+
+/// Mass and motion of an object, used while the simulation runs.
+#[derive(Clone, Copy, Debug)]
+pub struct Body{
+    /// Zero pins the object in place, e.g. a floor or a ramp.
+    pub mass: f32,
+    /// World units per second.
+    pub velocity: Point3D,
+    /// Radians per second about the world axes.
+    pub angular_velocity: Point3D,
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Body::new(1.0)
+    }
+}
+
+impl Body{
+    pub fn new(mass: f32) -> Self {
+        let zero = Point3D::new(0.0, 0.0, 0.0);
+        Body{ mass, velocity: zero, angular_velocity: zero }
+    }
+
+    /// A body that does not move, the default for flat objects.
+    pub fn fixed() -> Self {
+        Body::new(0.0)
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.mass <= 0.0
+    }
+}
+
+/// Gravity and contact response of the simulation.
+#[derive(Clone, Copy, Debug)]
+pub struct Physics{
+    /// Acceleration in units per second squared; +Y points down.
+    pub gravity: f32,
+    /// Share of the speed into a contact that bounces back, from 0 to 1.
+    pub restitution: f32,
+    /// Coulomb friction coefficient between any two surfaces.
+    pub friction: f32,
+    /// Height of an endless floor, or `None` for objects to fall forever.
+    pub ground: Option<f32>,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Physics{ gravity: 9.8, restitution: 0.3, friction: 0.5, ground: Some(GROUND_LEVEL) }
+    }
+}
+
+/// What an object collides as.
+#[derive(Clone, Copy, Debug)]
+pub enum Shape{
+    Sphere(BoundingSphere),
+    /// Its oriented bounding box; planes and disks are boxes without thickness.
+    Box(Obb),
+}
+
+impl Shape{
+    pub fn of(object: &Objects) -> Shape {
+        match object {
+            Objects::Sphere(_) => Shape::Sphere(object.bounding_sphere()),
+            _ => Shape::Box(object.obb()),
+        }
+    }
+
+    pub fn center(&self) -> Point3D {
+        match self {
+            Shape::Sphere(s) => s.center,
+            Shape::Box(b) => b.center,
+        }
+    }
+}
+
+/// Where two shapes touch: `normal` points from the second shape to the first,
+/// which has to move `depth` along it to stop overlapping.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact{
+    pub point: Point3D,
+    pub normal: Point3D,
+    pub depth: f32,
+}
+
+/// Contacts between shapes `a` and `b`, including ones up to `MARGIN` apart;
+/// empty when they are further apart.
+pub fn contacts(a: &Shape, b: &Shape) -> Vec<Contact> {
+    match (a, b) {
+        (Shape::Sphere(a), Shape::Sphere(b)) => sphere_sphere(a, b).into_iter().collect(),
+        (Shape::Sphere(a), Shape::Box(b)) => sphere_box(a, b).into_iter().collect(),
+        (Shape::Box(a), Shape::Sphere(b)) => sphere_box(b, a).into_iter().map(flip).collect(),
+        (Shape::Box(a), Shape::Box(b)) => box_box(a, b),
+    }
+}
+
+/// Contacts of `shape` with the floor at height `ground`, whose normal points up (-Y).
+pub fn ground_contacts(shape: &Shape, ground: f32) -> Vec<Contact> {
+    let up = Point3D::new(0.0, -1.0, 0.0);
+    let points = match shape {
+        Shape::Sphere(s) => vec![s.center + Point3D::new(0.0, s.radius, 0.0)],
+        Shape::Box(b) => b.corners().to_vec(),
+    };
+    points.into_iter()
+        .map(|p| Contact{ point: p, normal: up, depth: p.y - ground })
+        .filter(|c| c.depth > -MARGIN)
+        .collect()
+}
+
+fn flip(contact: Contact) -> Contact {
+    Contact{ normal: contact.normal * -1.0, ..contact }
+}
+
+fn sphere_sphere(a: &BoundingSphere, b: &BoundingSphere) -> Option<Contact> {
+    let d = a.center - b.center;
+    let distance = d.dot(d).sqrt();
+    let depth = a.radius + b.radius - distance;
+    if depth <= -MARGIN {
+        return None;
+    }
+    // Concentric spheres push apart along any direction; up is as good as any.
+    let normal = if distance > 1e-6 { d * (1.0 / distance) } else { Point3D::new(0.0, -1.0, 0.0) };
+    Some(Contact{ point: b.center + normal * (b.radius - depth / 2.0), normal, depth })
+}
+
+fn sphere_box(sphere: &BoundingSphere, obb: &Obb) -> Option<Contact> {
+    let d = sphere.center - obb.center;
+    let local: [f32; 3] = std::array::from_fn(|k| d.dot(obb.axes[k]));
+    let clamped: [f32; 3] = std::array::from_fn(|k| local[k].clamp(-obb.half[k], obb.half[k]));
+    let closest = (0..3).fold(obb.center, |p, k| p + obb.axes[k] * clamped[k]);
+    let out = sphere.center - closest;
+    let distance = out.dot(out).sqrt();
+
+    if distance > 1e-6 {
+        let depth = sphere.radius - distance;
+        return (depth > -MARGIN).then(|| Contact{ point: closest, normal: out * (1.0 / distance), depth });
+    }
+    // The center is inside the box: leave through the nearest face.
+    let k = (0..3).min_by(|&i, &j| (obb.half[i] - local[i].abs()).total_cmp(&(obb.half[j] - local[j].abs()))).unwrap_or(0);
+    let sign = if local[k] < 0.0 { -1.0 } else { 1.0 };
+    let normal = obb.axes[k] * sign;
+    Some(Contact{ point: sphere.center, normal, depth: sphere.radius + obb.half[k] - local[k].abs() })
+}
+
+/// Half the width of `obb` along unit `axis`.
+fn reach(obb: &Obb, axis: Point3D) -> f32 {
+    (0..3).map(|k| obb.half[k] * obb.axes[k].dot(axis).abs()).sum()
+}
+
+fn inside(obb: &Obb, p: Point3D, margin: f32) -> bool {
+    let d = p - obb.center;
+    (0..3).all(|k| d.dot(obb.axes[k]).abs() <= obb.half[k] + margin)
+}
+
+/// Separating axis test over the face normals and edge crossings of both boxes.
+/// Contacts are the corners of each box inside the other, or where the boxes reach
+/// furthest into each other when only edges cross.
+fn box_box(a: &Obb, b: &Obb) -> Vec<Contact> {
+    let mut axes: Vec<Point3D> = a.axes.iter().chain(&b.axes).copied().collect();
+    for ea in a.axes {
+        for eb in b.axes {
+            let cross = ea.cross(eb);
+            // Parallel edges give no axis of their own.
+            if cross.dot(cross) > 1e-6 {
+                axes.push(cross.normalize());
+            }
+        }
+    }
+
+    let d = a.center - b.center;
+    // Biased overlap, overlap and normal of the axis the boxes overlap least along.
+    let mut best: Option<(f32, f32, Point3D)> = None;
+    for (i, axis) in axes.into_iter().enumerate() {
+        let overlap = reach(a, axis) + reach(b, axis) - d.dot(axis).abs();
+        if overlap <= -MARGIN {
+            return Vec::new();
+        }
+        // Favor the six face axes a little, as resting boxes otherwise flip between
+        // a face and an edge crossing that overlap about as much.
+        let biased = if i < 6 { overlap } else { overlap + EDGE_BIAS };
+        if best.is_none_or(|(least, _, _)| biased < least) {
+            let normal = if d.dot(axis) < 0.0 { axis * -1.0 } else { axis };
+            best = Some((biased, overlap, normal));
+        }
+    }
+    let Some((_, depth, normal)) = best else {
+        return Vec::new();
+    };
+
+    // How far each box reaches past the other's face along the normal.
+    let b_top = b.center.dot(normal) + reach(b, normal);
+    let a_bottom = a.center.dot(normal) - reach(a, normal);
+    let mut found: Vec<Contact> = a.corners().into_iter()
+        .filter(|&p| inside(b, p, MARGIN))
+        .map(|p| Contact{ point: p, normal, depth: b_top - p.dot(normal) })
+        .chain(b.corners().into_iter()
+            .filter(|&p| inside(a, p, MARGIN))
+            .map(|p| Contact{ point: p, normal, depth: p.dot(normal) - a_bottom }))
+        .collect();
+    if found.is_empty() {
+        let deepest = |obb: &Obb, direction: Point3D| obb.corners().into_iter()
+            .max_by(|p, q| p.dot(direction).total_cmp(&q.dot(direction)))
+            .unwrap_or(obb.center);
+        let point = (deepest(a, normal * -1.0) + deepest(b, normal)) * 0.5;
+        found.push(Contact{ point, normal, depth });
+    }
+    found
+}
+
+/// One object's state during a step.
+struct Rigid{
+    center: Point3D,
+    inverse_mass: f32,
+    /// Inverse moments of inertia about the box axes.
+    inverse_inertia: [f32; 3],
+    axes: [Point3D; 3],
+    /// The world axis the object has no angle for, which it cannot turn about.
+    locked: Point3D,
+    velocity: Point3D,
+    angular_velocity: Point3D,
+    touching: bool,
+}
+
+impl Rigid{
+    fn new(object: &Objects, shape: &Shape) -> Rigid {
+        let body = &object.state().body;
+        let obb = object.obb();
+        // Whatever doesn't collide would only fall forever.
+        let (inverse_mass, inverse_inertia) = if body.is_fixed() || !object.collides() {
+            (0.0, [0.0; 3])
+        } else {
+            let moments = match shape {
+                Shape::Sphere(s) => [0.4 * body.mass * s.radius * s.radius; 3],
+                Shape::Box(b) => {
+                    let [x, y, z] = b.half.map(|h| h * h);
+                    [y + z, x + z, x + y].map(|m| body.mass * m / 3.0)
+                }
+            };
+            (1.0 / body.mass, moments.map(|m| 1.0 / m.max(1e-4)))
+        };
+        let (turn_x, turn_y) = turn_axes(object.angles().0);
+        Rigid{
+            center: shape.center(),
+            inverse_mass,
+            inverse_inertia,
+            axes: obb.axes,
+            locked: turn_x.cross(turn_y),
+            velocity: body.velocity,
+            angular_velocity: body.angular_velocity,
+            touching: false,
+        }
+    }
+
+    /// Change of angular velocity from an angular impulse, leaving out the locked axis.
+    fn turn_per_torque(&self, torque: Point3D) -> Point3D {
+        let unlocked = |v: Point3D| v - self.locked * self.locked.dot(v);
+        let torque = unlocked(torque);
+        unlocked((0..3).fold(Point3D::new(0.0, 0.0, 0.0), |sum, k| sum + self.axes[k] * (self.axes[k].dot(torque) * self.inverse_inertia[k])))
+    }
+
+    fn velocity_at(&self, arm: Point3D) -> Point3D {
+        self.velocity + self.angular_velocity.cross(arm)
+    }
+
+    fn push(&mut self, impulse: Point3D, arm: Point3D) {
+        self.velocity = self.velocity + impulse * self.inverse_mass;
+        self.angular_velocity = self.angular_velocity + self.turn_per_torque(arm.cross(impulse));
+    }
+}
+
+/// A contact between object `a` and object `b`, or the ground, with what the
+/// solver has pushed through it so far.
+struct Joint{
+    a: usize,
+    b: Option<usize>,
+    normal: Point3D,
+    arm_a: Point3D,
+    arm_b: Point3D,
+    tangents: [Point3D; 2],
+    /// Normal speed the contact aims for: apart by the bounce plus the overlap correction.
+    target: f32,
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+    normal_impulse: f32,
+    tangent_impulse: [f32; 2],
+}
+
+/// Advances the scene's objects by `dt` seconds: gravity, then collisions, then motion.
+///
+/// Objects only turn about the two axes they have angles for; collisions treat
+/// the third as locked, and spin about it given in a scene file is dropped.
+pub fn step(scene: &mut Scene, physics: &Physics, dt: f32) {
+    if dt <= 0.0 {
+        return;
+    }
+    let shapes: Vec<Shape> = scene.objects.iter().map(Shape::of).collect();
+    let mut rigids: Vec<Rigid> = scene.objects.iter().zip(&shapes).map(|(o, s)| Rigid::new(o, s)).collect();
+    for rigid in rigids.iter_mut().filter(|r| r.inverse_mass > 0.0) {
+        rigid.velocity.y += physics.gravity * dt;
+    }
+
+    let mut joints = Vec::new();
+    let boxes: Vec<_> = scene.objects.iter().map(|o| o.aabb()).collect();
+    for i in 0..shapes.len() {
+        if let Some(ground) = physics.ground.filter(|_| rigids[i].inverse_mass > 0.0) {
+            for contact in ground_contacts(&shapes[i], ground) {
+                joints.push(joint(&rigids, i, None, contact, physics, dt));
+            }
+        }
+        for j in i + 1..shapes.len() {
+            let both_fixed = rigids[i].inverse_mass == 0.0 && rigids[j].inverse_mass == 0.0;
+            if both_fixed || !scene.objects[i].collides() || !scene.objects[j].collides() || !grown(&boxes[i]).intersects(&boxes[j]) {
+                continue;
+            }
+            for contact in contacts(&shapes[i], &shapes[j]) {
+                joints.push(joint(&rigids, i, Some(j), contact, physics, dt));
+            }
+        }
+    }
+
+    for _ in 0..ITERATIONS {
+        for joint in &mut joints {
+            solve(joint, &mut rigids, physics.friction);
+        }
+    }
+
+    let damping = (1.0 - DAMPING * dt).max(0.0);
+    for (object, rigid) in scene.objects.iter_mut().zip(&mut rigids) {
+        if rigid.inverse_mass == 0.0 {
+            continue;
+        }
+        let (angle_x, _) = object.angles();
+        let (turn_x, turn_y) = turn_axes(angle_x);
+        let (spin_x, spin_y) = (rigid.angular_velocity.dot(turn_x), rigid.angular_velocity.dot(turn_y));
+        let mut velocity = rigid.velocity * damping;
+        let mut angular_velocity = (turn_x * spin_x + turn_y * spin_y) * damping;
+        let still = |v: Point3D| v.dot(v) < REST_SPEED * REST_SPEED;
+        if rigid.touching && still(velocity) && still(angular_velocity) {
+            velocity = Point3D::new(0.0, 0.0, 0.0);
+            angular_velocity = velocity;
+        }
+
+        let center = rigid.center + velocity * dt;
+        let (spin_x, spin_y) = (angular_velocity.dot(turn_x), angular_velocity.dot(turn_y));
+        object.rotate(spin_x * dt, spin_y * dt);
+        // Turning pivots some objects about another point than their middle.
+        let moved = center - Shape::of(object).center();
+        object.move_world(moved);
+        let body = &mut object.state_mut().body;
+        body.velocity = velocity;
+        body.angular_velocity = angular_velocity;
+    }
+}
+
+/// World axes that raising `angle_x` and `angle_y` turn about, at `angle_x`:
+/// objects turn by `angle_y` first, then by `angle_x` about world X.
+fn turn_axes(angle_x: f32) -> (Point3D, Point3D) {
+    let origin = Point3D::new(0.0, 0.0, 0.0);
+    let y = rotate_x(Point3D::new(0.0, 1.0, 0.0), angle_x, origin);
+    (Point3D::new(1.0, 0.0, 0.0), y * -1.0)
+}
+
+fn grown(aabb: &Aabb) -> Aabb {
+    let margin = Point3D::new(MARGIN, MARGIN, MARGIN);
+    Aabb::new(aabb.min - margin, aabb.max + margin)
+}
+
+fn joint(rigids: &[Rigid], a: usize, b: Option<usize>, contact: Contact, physics: &Physics, dt: f32) -> Joint {
+    let normal = contact.normal;
+    let arm_a = contact.point - rigids[a].center;
+    let arm_b = b.map_or(Point3D::new(0.0, 0.0, 0.0), |b| contact.point - rigids[b].center);
+    let helper = if normal.x.abs() < 0.9 { Point3D::new(1.0, 0.0, 0.0) } else { Point3D::new(0.0, 1.0, 0.0) };
+    let first = normal.cross(helper).normalize();
+    let tangents = [first, normal.cross(first)];
+
+    let mass_along = |direction: Point3D| {
+        let mut k = rigids[a].inverse_mass + rigids[a].turn_per_torque(arm_a.cross(direction)).cross(arm_a).dot(direction);
+        if let Some(b) = b {
+            k += rigids[b].inverse_mass + rigids[b].turn_per_torque(arm_b.cross(direction)).cross(arm_b).dot(direction);
+        }
+        if k > 0.0 { 1.0 / k } else { 0.0 }
+    };
+
+    let relative = relative_velocity(rigids, a, b, arm_a, arm_b).dot(normal);
+    let bounce = if relative < -BOUNCE_THRESHOLD { -physics.restitution * relative } else { 0.0 };
+    // A contact not quite touching yet lets the gap close within this step, so
+    // its target is an approach speed and must not be raised to zero.
+    let correction = if contact.depth < 0.0 { contact.depth / dt } else { BAUMGARTE / dt * (contact.depth - SLOP).max(0.0) };
+    let target = if bounce > 0.0 { bounce.max(correction) } else { correction };
+    Joint{
+        a,
+        b,
+        normal,
+        arm_a,
+        arm_b,
+        tangents,
+        target,
+        normal_mass: mass_along(normal),
+        tangent_mass: tangents.map(mass_along),
+        normal_impulse: 0.0,
+        tangent_impulse: [0.0; 2],
+    }
+}
+
+fn relative_velocity(rigids: &[Rigid], a: usize, b: Option<usize>, arm_a: Point3D, arm_b: Point3D) -> Point3D {
+    let va = rigids[a].velocity_at(arm_a);
+    match b {
+        Some(b) => va - rigids[b].velocity_at(arm_b),
+        None => va,
+    }
+}
+
+fn apply(rigids: &mut [Rigid], joint: &Joint, impulse: Point3D) {
+    rigids[joint.a].push(impulse, joint.arm_a);
+    if let Some(b) = joint.b {
+        rigids[b].push(impulse * -1.0, joint.arm_b);
+    }
+}
+
+/// One sequential impulse pass over `joint`: the normal impulse never pulls, and
+/// friction holds back at most `friction` times the normal impulse.
+fn solve(joint: &mut Joint, rigids: &mut [Rigid], friction: f32) {
+    let speed = relative_velocity(rigids, joint.a, joint.b, joint.arm_a, joint.arm_b).dot(joint.normal);
+    let total = (joint.normal_impulse + (joint.target - speed) * joint.normal_mass).max(0.0);
+    let change = total - joint.normal_impulse;
+    joint.normal_impulse = total;
+    apply(rigids, joint, joint.normal * change);
+    if total > 0.0 {
+        rigids[joint.a].touching = true;
+        if let Some(b) = joint.b {
+            rigids[b].touching = true;
+        }
+    }
+
+    let limit = friction * joint.normal_impulse;
+    for k in 0..2 {
+        let tangent = joint.tangents[k];
+        let slide = relative_velocity(rigids, joint.a, joint.b, joint.arm_a, joint.arm_b).dot(tangent);
+        let total = (joint.tangent_impulse[k] - slide * joint.tangent_mass[k]).clamp(-limit, limit);
+        let change = total - joint.tangent_impulse[k];
+        joint.tangent_impulse[k] = total;
+        apply(rigids, joint, tangent * change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objec::{Cube, Sphere};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn p(x: f32, y: f32, z: f32) -> Point3D {
+        Point3D::new(x, y, z)
+    }
+
+    fn close(a: Point3D, b: Point3D, eps: f32) -> bool {
+        (a.x - b.x).abs() <= eps && (a.y - b.y).abs() <= eps && (a.z - b.z).abs() <= eps
+    }
+
+    /// Unturned unit cube with its top at height `top`.
+    fn cube(x: f32, top: f32) -> Objects {
+        let mut cube = Objects::Cube(Cube::new(p(x, 0.0, 4.0), p(x + 1.0, 1.0, 4.0), [200, 0, 0]));
+        cube.set_angles(0.0, 0.0);
+        let lift = top - cube.aabb().min.y;
+        cube.move_world(p(0.0, lift, 0.0));
+        cube
+    }
+
+    fn settle(scene: &mut Scene, seconds: f32) {
+        for _ in 0..(seconds / DT) as usize {
+            step(scene, &Physics::default(), DT);
+        }
+    }
+
+    fn at_rest(object: &Objects) -> bool {
+        let body = &object.state().body;
+        close(body.velocity, p(0.0, 0.0, 0.0), 0.0) && close(body.angular_velocity, p(0.0, 0.0, 0.0), 0.0)
+    }
+
+    fn unit_box(center: Point3D) -> Obb {
+        Obb{ center, axes: [p(1.0, 0.0, 0.0), p(0.0, 1.0, 0.0), p(0.0, 0.0, 1.0)], half: [0.5; 3] }
+    }
+
+    #[test]
+    fn dropped_cube_comes_to_rest_on_the_ground() {
+        let mut scene = Scene::default();
+        scene.objects.push(cube(0.0, -2.0));
+        settle(&mut scene, 4.0);
+        let object = &scene.objects[0];
+        assert!((object.aabb().max.y - GROUND_LEVEL).abs() < 2.0 * SLOP, "bottom at {}", object.aabb().max.y);
+        assert!(at_rest(object), "still moving at {:?}", object.state().body.velocity);
+        let (angle_x, angle_y) = object.angles();
+        assert!(angle_x.abs() < 1e-3 && angle_y.abs() < 1e-3, "tipped over to {}, {}", angle_x, angle_y);
+    }
+
+    #[test]
+    fn dropped_sphere_comes_to_rest_on_the_ground() {
+        let mut scene = Scene::default();
+        scene.objects.push(Objects::Sphere(Sphere::new(p(0.0, -1.0, 4.0), 0.5, 8, 12, [0, 0, 200])));
+        settle(&mut scene, 4.0);
+        let sphere = scene.objects[0].bounding_sphere();
+        assert!((sphere.center.y + sphere.radius - GROUND_LEVEL).abs() < 2.0 * SLOP, "bottom at {}", sphere.center.y + sphere.radius);
+        assert!(at_rest(&scene.objects[0]));
+    }
+
+    #[test]
+    fn stacked_cubes_settle_on_each_other() {
+        let mut scene = Scene::default();
+        for k in 0..3 {
+            scene.objects.push(cube(0.0, GROUND_LEVEL - 1.0 - 1.05 * k as f32));
+        }
+        let start: Vec<Point3D> = scene.objects.iter().map(|o| o.aabb().min).collect();
+        settle(&mut scene, 5.0);
+        let mut floor = GROUND_LEVEL;
+        for (k, object) in scene.objects.iter().enumerate() {
+            let aabb = object.aabb();
+            assert!((aabb.max.y - floor).abs() < 3.0 * SLOP, "cube {} rests at {}, not {}", k, aabb.max.y, floor);
+            assert!((aabb.min.x - start[k].x).abs() < 0.01 && (aabb.min.z - start[k].z).abs() < 0.01, "cube {} slid to {:?}", k, aabb.min);
+            assert!(at_rest(object), "cube {} still moving", k);
+            floor = aabb.min.y;
+        }
+    }
+
+    #[test]
+    fn pinned_objects_stay_put() {
+        let mut scene = Scene::default();
+        scene.objects.push(cube(0.0, -2.0));
+        scene.objects[0].state_mut().body = Body::fixed();
+        let before = scene.objects[0].position();
+        settle(&mut scene, 1.0);
+        assert!(close(scene.objects[0].position(), before, 0.0));
+    }
+
+    #[test]
+    fn sphere_contacts_point_from_the_second_shape() {
+        let a = Shape::Sphere(BoundingSphere{ center: p(1.5, 0.0, 0.0), radius: 1.0 });
+        let b = Shape::Sphere(BoundingSphere{ center: p(0.0, 0.0, 0.0), radius: 1.0 });
+        let found = contacts(&a, &b);
+        assert_eq!(found.len(), 1);
+        assert!(close(found[0].normal, p(1.0, 0.0, 0.0), 1e-6));
+        assert!((found[0].depth - 0.5).abs() < 1e-6);
+        assert!(close(contacts(&b, &a)[0].normal, p(-1.0, 0.0, 0.0), 1e-6));
+        let far = Shape::Sphere(BoundingSphere{ center: p(3.0, 0.0, 0.0), radius: 0.5 });
+        assert!(contacts(&far, &b).is_empty());
+
+        // Against the top face of a box, from outside and from just inside.
+        let floor = Shape::Box(unit_box(p(0.0, 0.0, 0.0)));
+        let ball = Shape::Sphere(BoundingSphere{ center: p(0.1, -0.8, 0.0), radius: 0.5 });
+        let found = contacts(&ball, &floor);
+        assert!(close(found[0].normal, p(0.0, -1.0, 0.0), 1e-6));
+        assert!((found[0].depth - 0.2).abs() < 1e-5);
+        let sunk = Shape::Sphere(BoundingSphere{ center: p(0.0, -0.4, 0.0), radius: 0.5 });
+        let found = contacts(&floor, &sunk);
+        assert!(close(found[0].normal, p(0.0, 1.0, 0.0), 1e-6), "flipped for box first");
+        assert!((found[0].depth - 0.6).abs() < 1e-5);
+    }
+
+    #[test]
+    fn box_contacts_take_the_axis_of_least_overlap() {
+        let below = Shape::Box(unit_box(p(0.0, 0.0, 0.0)));
+        let above = Shape::Box(unit_box(p(0.2, -0.9, 0.1)));
+        let found = contacts(&above, &below);
+        assert!(!found.is_empty());
+        for contact in &found {
+            assert!(close(contact.normal, p(0.0, -1.0, 0.0), 1e-6));
+            assert!((contact.depth - 0.1).abs() < 1e-5, "depth {}", contact.depth);
+        }
+        let apart = Shape::Box(unit_box(p(0.0, -1.5, 0.0)));
+        assert!(contacts(&apart, &below).is_empty());
+
+        // A box turned 45 degrees about Z, resting a corner-edge on the top face.
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let turned = Obb{ center: p(0.0, -0.5 - s + 0.05, 0.0), axes: [p(s, s, 0.0), p(-s, s, 0.0), p(0.0, 0.0, 1.0)], half: [0.5; 3] };
+        let found = contacts(&Shape::Box(turned), &below);
+        assert!(!found.is_empty());
+        for contact in &found {
+            assert!(close(contact.normal, p(0.0, -1.0, 0.0), 1e-5), "normal {:?}", contact.normal);
+            assert!((contact.depth - 0.05).abs() < 1e-4, "depth {}", contact.depth);
+        }
+    }
+
+    #[test]
+    fn ground_contacts_are_the_lowest_points() {
+        let ball = Shape::Sphere(BoundingSphere{ center: p(0.0, 0.6, 0.0), radius: 0.5 });
+        let found = ground_contacts(&ball, 1.0);
+        assert_eq!(found.len(), 1);
+        assert!((found[0].depth - 0.1).abs() < 1e-6);
+        assert!(close(found[0].normal, p(0.0, -1.0, 0.0), 0.0));
+        let resting = ground_contacts(&Shape::Box(unit_box(p(0.0, 0.5, 0.0))), 1.0);
+        assert_eq!(resting.len(), 4, "the four bottom corners");
+        assert!(ground_contacts(&Shape::Box(unit_box(p(0.0, 0.0, 0.0))), 1.0).is_empty());
+    }
+
+    #[test]
+    fn fast_impacts_still_bounce() {
+        let mut scene = Scene::default();
+        scene.objects.push(Objects::Sphere(Sphere::new(p(0.0, -2.0, 4.0), 0.5, 8, 12, [0, 0, 200])));
+        let physics = Physics{ restitution: 0.8, ..Physics::default() };
+        let rebound = (0..120).map(|_| {
+            step(&mut scene, &physics, DT);
+            scene.objects[0].state().body.velocity.y
+        }).fold(0.0, f32::min);
+        assert!(rebound < -2.0, "bounced up at only {}", -rebound);
+    }
+}
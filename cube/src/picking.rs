@@ -0,0 +1,85 @@
+use crate::bounds::BoundingSphere;
+use crate::geometry::*;
+use crate::objec::Objects;
+
+/// Half-line from `origin` along `direction` (not necessarily unit length).
+#[derive(Clone, Copy, Debug)]
+pub struct Ray{
+    pub origin: Point3D,
+    pub direction: Point3D,
+}
+
+impl Ray{
+    pub fn new(origin: Point3D, direction: Point3D) -> Self{
+        Ray{ origin, direction }
+    }
+
+    pub fn at(&self, t: f32) -> Point3D{
+        self.origin + self.direction * t
+    }
+
+    /// Distance along the ray to where it enters `sphere`, or 0 from inside.
+    pub fn hits_sphere(&self, sphere: &BoundingSphere) -> Option<f32>{
+        let to_center = sphere.center - self.origin;
+        let a = self.direction.dot(self.direction);
+        let b = to_center.dot(self.direction);
+        let c = to_center.dot(to_center) - sphere.radius * sphere.radius;
+        let discriminant = b * b - a * c;
+        if a == 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        if c <= 0.0 {
+            Some(0.0)
+        } else if b - root >= 0.0 {
+            Some((b - root) / a)
+        } else {
+            None
+        }
+    }
+
+    /// Möller–Trumbore intersection; hits either side of the triangle.
+    pub fn hits_triangle(&self, triangle: &Triangle3D) -> Option<f32>{
+        self.hits_triangle_at(triangle).map(|(t, _, _)| t)
+    }
+
+    /// Distance to the hit and its weights `u` and `v` for `p2` and `p3`
+    /// (`p1` gets the rest), for blending what the corners carry.
+    pub fn hits_triangle_at(&self, triangle: &Triangle3D) -> Option<(f32, f32, f32)>{
+        let edge1 = triangle.p2 - triangle.p1;
+        let edge2 = triangle.p3 - triangle.p1;
+        let p = self.direction.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < 1e-9 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let s = self.origin - triangle.p1;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(q) * inv_det;
+        (t > 0.0).then_some((t, u, v))
+    }
+
+    /// Nearest hit on any triangle of `object`.
+    pub fn hits_object(&self, object: &Objects) -> Option<f32>{
+        self.hits_sphere(&object.bounding_sphere())?;
+        object.triangles().iter()
+            .filter_map(|t| self.hits_triangle(t))
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+/// Index of the object nearest along `ray` and the distance to it.
+pub fn pick(objects: &[Objects], ray: &Ray) -> Option<(usize, f32)>{
+    objects.iter().enumerate()
+        .filter_map(|(i, object)| ray.hits_object(object).map(|t| (i, t)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::geometry::*;
+use crate::mesh::Mesh;
+
+/// Polyhedron with polygonal faces, centered on the origin.
+///
+/// Faces list vertex indices counter-clockwise as seen from outside, so the
+/// Conway operators can walk around vertices and edges consistently.
+#[derive(Clone, Debug)]
+pub struct Polyhedron{
+    pub vertices: Vec<Point3D>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl Polyhedron{
+    pub fn new(vertices: Vec<Point3D>, faces: Vec<Vec<usize>>) -> Self{
+        let mut polyhedron = Polyhedron{ vertices, faces };
+        polyhedron.orient();
+        polyhedron
+    }
+
+    pub fn tetrahedron() -> Self{
+        Polyhedron::new(
+            points(&[[1.0, 1.0, 1.0], [1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [-1.0, -1.0, 1.0]]),
+            vec![vec![0, 1, 2], vec![0, 2, 3], vec![0, 3, 1], vec![1, 3, 2]],
+        )
+    }
+
+    pub fn cube() -> Self{
+        Polyhedron::new(
+            points(&[
+                [-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [-1.0, 1.0, -1.0],
+                [-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0],
+            ]),
+            vec![
+                vec![0, 3, 2, 1], vec![4, 5, 6, 7], vec![0, 1, 5, 4],
+                vec![2, 3, 7, 6], vec![1, 2, 6, 5], vec![0, 4, 7, 3],
+            ],
+        )
+    }
+
+    pub fn octahedron() -> Self{
+        Polyhedron::cube().dual()
+    }
+
+    pub fn icosahedron() -> Self{
+        let g = (1.0 + 5f32.sqrt()) / 2.0;
+        Polyhedron::new(
+            points(&[
+                [-1.0, g, 0.0], [1.0, g, 0.0], [-1.0, -g, 0.0], [1.0, -g, 0.0],
+                [0.0, -1.0, g], [0.0, 1.0, g], [0.0, -1.0, -g], [0.0, 1.0, -g],
+                [g, 0.0, -1.0], [g, 0.0, 1.0], [-g, 0.0, -1.0], [-g, 0.0, 1.0],
+            ]),
+            vec![
+                vec![0, 11, 5], vec![0, 5, 1], vec![0, 1, 7], vec![0, 7, 10], vec![0, 10, 11],
+                vec![1, 5, 9], vec![5, 11, 4], vec![11, 10, 2], vec![10, 7, 6], vec![7, 1, 8],
+                vec![3, 9, 4], vec![3, 4, 2], vec![3, 2, 6], vec![3, 6, 8], vec![3, 8, 9],
+                vec![4, 9, 5], vec![2, 4, 11], vec![6, 2, 10], vec![8, 6, 7], vec![9, 8, 1],
+            ],
+        )
+    }
+
+    pub fn dodecahedron() -> Self{
+        Polyhedron::icosahedron().dual()
+    }
+
+    /// Builds a polyhedron from Conway notation such as `"tI"` or `"dkD"`.
+    ///
+    /// Seeds are `T`, `C`, `O`, `D` and `I`; operators (applied right to left) are
+    /// `d`ual, `t`runcate, `k`is, `a`mbo, `s`nub, `g`yro and `e`xpand.
+    pub fn from_notation(notation: &str) -> Option<Self>{
+        let mut chars = notation.trim().chars().rev();
+        let mut polyhedron = match chars.next()? {
+            'T' => Polyhedron::tetrahedron(),
+            'C' => Polyhedron::cube(),
+            'O' => Polyhedron::octahedron(),
+            'D' => Polyhedron::dodecahedron(),
+            'I' => Polyhedron::icosahedron(),
+            _ => return None,
+        };
+        for op in chars {
+            polyhedron = match op {
+                'd' => polyhedron.dual(),
+                't' => polyhedron.truncate(),
+                'k' => polyhedron.kis(),
+                'a' => polyhedron.ambo(),
+                's' => polyhedron.snub(),
+                'g' => polyhedron.gyro(),
+                'e' => polyhedron.expand(),
+                _ => return None,
+            };
+        }
+        Some(polyhedron)
+    }
+
+    /// Swaps faces and vertices: each face becomes a vertex at its center and each
+    /// vertex becomes a face joining the centers of the faces around it.
+    pub fn dual(&self) -> Self{
+        let vertices = self.faces.iter().map(|f| self.face_center(f)).collect();
+        let faces = (0..self.vertices.len())
+            .map(|v| self.faces_around(v))
+            .filter(|ring| ring.len() >= 3)
+            .collect();
+        Polyhedron::new(vertices, faces).rescaled_like(self)
+    }
+
+    /// Raises a pyramid on every face.
+    pub fn kis(&self) -> Self{
+        let mut vertices = self.vertices.clone();
+        let mut faces = Vec::new();
+        for face in &self.faces {
+            let apex = vertices.len();
+            let center = self.face_center(face);
+            // Lift the apex to the mean vertex distance so the pyramids are not flat.
+            let lift = face.iter().map(|&v| length(self.vertices[v])).sum::<f32>() / face.len() as f32;
+            vertices.push(center * (lift / length(center).max(f32::EPSILON)));
+            for (a, b) in edges(face) {
+                faces.push(vec![a, b, apex]);
+            }
+        }
+        Polyhedron::new(vertices, faces)
+    }
+
+    /// Cuts every vertex off: `t = dkd`.
+    pub fn truncate(&self) -> Self{
+        self.dual().kis().dual()
+    }
+
+    /// Places vertices at edge midpoints, keeping a face for every old face and vertex.
+    pub fn ambo(&self) -> Self{
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut vertices = Vec::new();
+        let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Point3D>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                vertices.push((self.vertices[a] + self.vertices[b]) * 0.5);
+                vertices.len() - 1
+            })
+        };
+
+        let mut faces = Vec::new();
+        for face in &self.faces {
+            faces.push(edges(face).map(|(a, b)| midpoint(a, b, &mut vertices)).collect());
+        }
+        for v in 0..self.vertices.len() {
+            let ring: Vec<usize> = self.neighbors_around(v).into_iter().map(|w| midpoint(v, w, &mut vertices)).collect();
+            if ring.len() >= 3 {
+                faces.push(ring);
+            }
+        }
+        Polyhedron::new(vertices, faces)
+    }
+
+    /// Turns every n-gon into n pentagons swirling around its center.
+    pub fn gyro(&self) -> Self{
+        let mut vertices = self.vertices.clone();
+        let mut thirds: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut third = |a: usize, b: usize, vertices: &mut Vec<Point3D>| {
+            // The point on edge a-b one third of the way from a.
+            *thirds.entry((a, b)).or_insert_with(|| {
+                vertices.push(self.vertices[a] + (self.vertices[b] - self.vertices[a]) * (1.0 / 3.0));
+                vertices.len() - 1
+            })
+        };
+
+        let mut faces = Vec::new();
+        for face in &self.faces {
+            let center = vertices.len();
+            vertices.push(self.face_center(face));
+            let n = face.len();
+            for i in 0..n {
+                let (v1, v2, v3) = (face[i], face[(i + 1) % n], face[(i + 2) % n]);
+                faces.push(vec![
+                    center,
+                    third(v1, v2, &mut vertices),
+                    third(v2, v1, &mut vertices),
+                    v2,
+                    third(v2, v3, &mut vertices),
+                ]);
+            }
+        }
+        Polyhedron::new(vertices, faces).spherize()
+    }
+
+    /// Snub: `s = dg`.
+    pub fn snub(&self) -> Self{
+        self.gyro().dual()
+    }
+
+    /// Pulls faces apart and fills the gaps: `e = aa`.
+    pub fn expand(&self) -> Self{
+        self.ambo().ambo()
+    }
+
+    /// Projects every vertex onto the sphere through the farthest one, the usual
+    /// last step when building geodesic domes.
+    pub fn spherize(&self) -> Self{
+        let radius = self.radius();
+        let vertices = self.vertices.iter().map(|&v| v * (radius / length(v).max(f32::EPSILON))).collect();
+        Polyhedron::new(vertices, self.faces.clone())
+    }
+
+    pub fn radius(&self) -> f32{
+        self.vertices.iter().map(|&v| length(v)).fold(0.0, f32::max)
+    }
+
+    /// Fan-triangulates the faces into a mesh centered on `center`, sized so the
+    /// farthest vertex lies at `radius`.
+    pub fn to_mesh(&self, center: Point3D, radius: f32, color: [u8; 3]) -> Mesh{
+        let factor = radius / self.radius().max(f32::EPSILON);
+        let vertices = self.vertices.iter().map(|&v| center + v * factor).collect();
+        let faces = self.faces.iter()
+            .flat_map(|f| (1..f.len().saturating_sub(1)).map(move |i| [f[0], f[i], f[i + 1]]))
+            .collect();
+        let mut mesh = Mesh::new(vertices, faces, color);
+        // Keep the pivot on the polyhedron's center rather than its bounding box.
+        for v in &mut mesh.vertices {
+            *v = *v + mesh.center - center;
+        }
+        mesh.center = center;
+        mesh
+    }
+
+    fn face_center(&self, face: &[usize]) -> Point3D{
+        let sum = face.iter().fold(Point3D::new(0.0, 0.0, 0.0), |sum, &v| sum + self.vertices[v]);
+        sum * (1.0 / face.len() as f32)
+    }
+
+    /// Indices of the faces around vertex `v`, in winding order.
+    fn faces_around(&self, v: usize) -> Vec<usize>{
+        let mut edge_face = HashMap::new();
+        for (i, face) in self.faces.iter().enumerate() {
+            for (a, b) in edges(face) {
+                edge_face.insert((a, b), i);
+            }
+        }
+        let start = match self.faces.iter().position(|f| f.contains(&v)) {
+            Some(f) => f,
+            None => return Vec::new(),
+        };
+
+        let mut ring = vec![start];
+        let mut face = start;
+        loop {
+            // Step across the edge leaving `v` to the face on its other side.
+            let f = &self.faces[face];
+            let i = f.iter().position(|&x| x == v).unwrap_or(0);
+            let next = f[(i + 1) % f.len()];
+            face = match edge_face.get(&(next, v)) {
+                Some(&other) => other,
+                None => break,
+            };
+            if face == start || ring.len() > self.faces.len() {
+                break;
+            }
+            ring.push(face);
+        }
+        ring.reverse();
+        ring
+    }
+
+    /// Neighbors of vertex `v`, in the same order as `faces_around`.
+    fn neighbors_around(&self, v: usize) -> Vec<usize>{
+        self.faces_around(v).into_iter().map(|face| {
+            let f = &self.faces[face];
+            let i = f.iter().position(|&x| x == v).unwrap_or(0);
+            f[(i + 1) % f.len()]
+        }).collect()
+    }
+
+    /// Makes every face wind counter-clockwise seen from outside. All polyhedra here
+    /// are star-shaped around the origin, so the face center points outwards.
+    fn orient(&mut self){
+        for face in &mut self.faces {
+            let center = face.iter().fold(Point3D::new(0.0, 0.0, 0.0), |sum, &v| sum + self.vertices[v]);
+            if newell_normal(&self.vertices, face).dot(center) < 0.0 {
+                face.reverse();
+            }
+        }
+    }
+
+    fn rescaled_like(mut self, other: &Polyhedron) -> Self{
+        let factor = other.radius() / self.radius().max(f32::EPSILON);
+        for v in &mut self.vertices {
+            *v = *v * factor;
+        }
+        self
+    }
+}
+
+fn points(coords: &[[f32; 3]]) -> Vec<Point3D>{
+    coords.iter().map(|c| Point3D::new(c[0], c[1], c[2])).collect()
+}
+
+fn length(p: Point3D) -> f32{
+    p.dot(p).sqrt()
+}
+
+/// Consecutive vertex pairs around a face, wrapping to the start.
+fn edges(face: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_{
+    (0..face.len()).map(move |i| (face[i], face[(i + 1) % face.len()]))
+}
+
+fn newell_normal(vertices: &[Point3D], face: &[usize]) -> Point3D{
+    edges(face).fold(Point3D::new(0.0, 0.0, 0.0), |n, (a, b)| {
+        let (p, q) = (vertices[a], vertices[b]);
+        Point3D::new(
+            n.x + (p.y - q.y) * (p.z + q.z),
+            n.y + (p.z - q.z) * (p.x + q.x),
+            n.z + (p.x - q.x) * (p.y + q.y),
+        )
+    })
+}
+
+/// Deepest icosphere subdivision; level 5 already has 20480 triangles.
+pub const MAX_ICOSPHERE_LEVEL: u32 = 5;
+
+/// Unit-radius icosphere: an icosahedron whose triangles are split into four
+/// `level` times, with the new vertices pushed out onto the sphere.
+///
+/// The result is cached per level since spheres ask for it every frame.
+pub fn icosphere(level: u32) -> &'static Polyhedron{
+    static CACHE: [OnceLock<Polyhedron>; MAX_ICOSPHERE_LEVEL as usize + 1] = [const { OnceLock::new() }; MAX_ICOSPHERE_LEVEL as usize + 1];
+    let level = level.min(MAX_ICOSPHERE_LEVEL);
+    CACHE[level as usize].get_or_init(|| {
+        let mut sphere = Polyhedron::icosahedron();
+        for v in &mut sphere.vertices {
+            *v = *v * (1.0 / length(*v));
+        }
+        for _ in 0..level {
+            sphere = subdivide(&sphere);
+        }
+        sphere
+    })
+}
+
+fn subdivide(sphere: &Polyhedron) -> Polyhedron{
+    let mut vertices = sphere.vertices.clone();
+    let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut midpoint = |a: usize, b: usize, vertices: &mut Vec<Point3D>| {
+        *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            let m = vertices[a] + vertices[b];
+            vertices.push(m * (1.0 / length(m)));
+            vertices.len() - 1
+        })
+    };
+
+    let mut faces = Vec::with_capacity(sphere.faces.len() * 4);
+    for face in &sphere.faces {
+        let (a, b, c) = (face[0], face[1], face[2]);
+        let ab = midpoint(a, b, &mut vertices);
+        let bc = midpoint(b, c, &mut vertices);
+        let ca = midpoint(c, a, &mut vertices);
+        faces.extend([vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]);
+    }
+    Polyhedron{ vertices, faces }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashSet};
+
+    /// Vertex, edge and face counts, then how many faces have each number of sides.
+    type Census = (usize, usize, usize, Vec<(usize, usize)>);
+
+    fn census(p: &Polyhedron) -> Census {
+        let edges: usize = p.faces.iter().map(|f| f.len()).sum::<usize>() / 2;
+        let mut sizes = BTreeMap::new();
+        for face in &p.faces {
+            *sizes.entry(face.len()).or_insert(0) += 1;
+        }
+        (p.vertices.len(), edges, p.faces.len(), sizes.into_iter().collect())
+    }
+
+    /// Closed and consistently wound: every directed edge appears once, opposite its reverse,
+    /// every vertex is used, and faces turn counter-clockwise seen from outside.
+    fn assert_closed(notation: &str, p: &Polyhedron) {
+        let mut directed = HashSet::new();
+        for face in &p.faces {
+            for edge in edges(face) {
+                assert!(directed.insert(edge), "{}: edge {:?} repeats", notation, edge);
+            }
+        }
+        for &(a, b) in &directed {
+            assert!(directed.contains(&(b, a)), "{}: edge {:?} has no twin", notation, (a, b));
+        }
+        let used: HashSet<usize> = p.faces.iter().flatten().copied().collect();
+        assert_eq!(used.len(), p.vertices.len(), "{}: unused vertices", notation);
+        for face in &p.faces {
+            let centroid = face.iter().fold(Point3D::new(0.0, 0.0, 0.0), |sum, &i| sum + p.vertices[i]) * (1.0 / face.len() as f32);
+            let normal = newell_normal(&p.vertices, face);
+            assert!(normal.x * centroid.x + normal.y * centroid.y + normal.z * centroid.z > 0.0, "{}: face {:?} faces inward", notation, face);
+        }
+    }
+
+    #[test]
+    fn conway_operators_give_the_expected_solids() {
+        let expected: Vec<(&str, Census)> = vec![
+            ("T", (4, 6, 4, vec![(3, 4)])),
+            ("C", (8, 12, 6, vec![(4, 6)])),
+            ("O", (6, 12, 8, vec![(3, 8)])),
+            ("D", (20, 30, 12, vec![(5, 12)])),
+            ("I", (12, 30, 20, vec![(3, 20)])),
+            ("tT", (12, 18, 8, vec![(3, 4), (6, 4)])),
+            ("tI", (60, 90, 32, vec![(5, 12), (6, 20)])),
+            ("aC", (12, 24, 14, vec![(3, 8), (4, 6)])),
+            ("eD", (60, 120, 62, vec![(3, 20), (4, 30), (5, 12)])),
+            ("sC", (24, 60, 38, vec![(3, 32), (4, 6)])),
+            ("sD", (60, 150, 92, vec![(3, 80), (5, 12)])),
+            ("kD", (32, 90, 60, vec![(3, 60)])),
+            ("dtI", (32, 90, 60, vec![(3, 60)])),
+            ("gC", (38, 60, 24, vec![(5, 24)])),
+            ("tC", (24, 36, 14, vec![(3, 8), (8, 6)])),
+            ("eC", (24, 48, 26, vec![(3, 8), (4, 18)])),
+            ("aaC", (24, 48, 26, vec![(3, 8), (4, 18)])),
+            ("ttI", (180, 270, 92, vec![(3, 60), (10, 12), (12, 20)])),
+            ("sT", (12, 30, 20, vec![(3, 20)])),
+        ];
+        for (notation, counts) in expected {
+            let p = Polyhedron::from_notation(notation).unwrap();
+            let (v, e, f, _) = counts;
+            assert_eq!(census(&p), counts, "{}", notation);
+            assert_eq!(v as isize - e as isize + f as isize, 2, "{}: Euler characteristic", notation);
+            assert_closed(notation, &p);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_notation() {
+        for notation in ["", "X", "qC", "Ct"] {
+            assert!(Polyhedron::from_notation(notation).is_none(), "{:?}", notation);
+        }
+    }
+
+    #[test]
+    fn icospheres_are_closed_unit_spheres() {
+        for level in 0..=3 {
+            let sphere = icosphere(level);
+            assert_eq!(sphere.faces.len(), 20 * 4usize.pow(level));
+            assert!(sphere.vertices.iter().all(|&v| (length(v) - 1.0).abs() < 1e-5));
+            assert_closed(&format!("icosphere {}", level), sphere);
+        }
+        assert_eq!(icosphere(99).faces.len(), icosphere(MAX_ICOSPHERE_LEVEL).faces.len());
+    }
+
+    #[test]
+    fn mesh_fits_the_radius() {
+        let mesh = Polyhedron::from_notation("tC").unwrap().to_mesh(Point3D::new(0.0, 0.0, 3.0), 0.5, [1, 2, 3]);
+        assert_eq!(mesh.faces.len(), 8 + 6 * 6);
+        let farthest = mesh.vertices.iter().map(|&v| length(v)).fold(0.0, f32::max);
+        assert!((farthest - 0.5).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,488 @@
+use std::f32::consts::PI;
+
+use crate::geometry::*;
+use crate::operations::*;
+use crate::drawing::*;
+use crate::objec::{ObjectState, Transformable};
+
+// All curved primitives are built around the local Y axis, centered on `center`.
+
+#[derive(Clone)]
+pub struct Cylinder{
+    pub center: Point3D,
+    pub radius: f32,
+    pub height: f32,
+    pub segments: i32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Cylinder{
+    pub fn new(center: Point3D, radius: f32, height: f32, segments: i32, color: [u8; 3]) -> Self{
+        Cylinder{
+            center,
+            radius,
+            height,
+            segments,
+            angle_x: -0.6,
+            angle_y: 0.4,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Cone{
+    pub center: Point3D,
+    pub radius: f32,
+    pub height: f32,
+    pub segments: i32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Cone{
+    pub fn new(center: Point3D, radius: f32, height: f32, segments: i32, color: [u8; 3]) -> Self{
+        Cone{
+            center,
+            radius,
+            height,
+            segments,
+            angle_x: -0.6,
+            angle_y: 0.4,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Torus{
+    pub center: Point3D,
+    /// Distance from the center to the middle of the tube.
+    pub major_radius: f32,
+    pub minor_radius: f32,
+    pub segments: i32,
+    /// Steps around the tube.
+    pub sides: i32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Torus{
+    pub fn new(center: Point3D, major_radius: f32, minor_radius: f32, segments: i32, sides: i32, color: [u8; 3]) -> Self{
+        Torus{
+            center,
+            major_radius,
+            minor_radius,
+            segments,
+            sides,
+            angle_x: -0.6,
+            angle_y: 0.4,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Capsule{
+    pub center: Point3D,
+    pub radius: f32,
+    /// Length of the straight part between the two hemispheres.
+    pub height: f32,
+    pub segments: i32,
+    /// Latitude steps per hemisphere.
+    pub rings: i32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Capsule{
+    pub fn new(center: Point3D, radius: f32, height: f32, segments: i32, rings: i32, color: [u8; 3]) -> Self{
+        Capsule{
+            center,
+            radius,
+            height,
+            segments,
+            rings,
+            angle_x: -0.6,
+            angle_y: 0.4,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Disk{
+    pub center: Point3D,
+    pub radius: f32,
+    /// Radius of the hole; 0 gives a full disk.
+    pub inner_radius: f32,
+    pub segments: i32,
+    pub angle_x: f32,
+    pub angle_y: f32,
+    pub scale: f32,
+    pub color: [u8; 3],
+    pub selected: bool,
+    pub state: ObjectState,
+}
+
+impl Disk{
+    pub fn new(center: Point3D, radius: f32, inner_radius: f32, segments: i32, color: [u8; 3]) -> Self{
+        Disk{
+            center,
+            radius,
+            inner_radius,
+            segments,
+            angle_x: -0.6,
+            angle_y: 0.4,
+            scale: 1.0,
+            color,
+            selected: false,
+            state: ObjectState::fixed(),
+        }
+    }
+}
+
+impl Transformable for Cylinder {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+impl Transformable for Cone {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+impl Transformable for Torus {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+impl Transformable for Capsule {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+impl Transformable for Disk {
+    fn move_trans(&mut self, x: f32, y: f32, z: f32) {
+        self.center.x += x;
+        self.center.y += y;
+        self.center.z += z;
+    }
+    fn rotate(&mut self, ax: f32, ay: f32) {
+        self.angle_x += ax;
+        self.angle_y += ay;
+    }
+    fn scale(&mut self, scale: f32) {
+        self.scale += scale;
+    }
+    fn select(&mut self) {
+        self.selected = true;
+    }
+    fn deselect(&mut self) {
+        self.selected = false;
+    }
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+}
+
+/// Collects world-space triangles from local-space vertices with normals, placing them
+/// the same way `rotate_and_translate` places the built-in shapes.
+struct Surface{
+    center: Point3D,
+    angle_x: f32,
+    angle_y: f32,
+    scale: f32,
+    color: [u8; 3],
+    triangles: Vec<Triangle3D>,
+}
+
+impl Surface{
+    fn new(center: Point3D, angle_x: f32, angle_y: f32, scale: f32, color: [u8; 3]) -> Self{
+        Surface{ center, angle_x, angle_y, scale, color, triangles: Vec::new() }
+    }
+
+    fn triangle(&mut self, vertices: [(Point3D, Point3D); 3]) {
+        let origin = Point3D::new(0.0, 0.0, 0.0);
+        let [a, b, c] = vertices.map(|(p, n)| (
+            rotate_and_translate(self.center + p, self.angle_x, self.angle_y, self.scale, self.center),
+            rotate_x(rotate_y(n, self.angle_y, origin), self.angle_x, origin),
+        ));
+        if (b.0 - a.0).cross(c.0 - a.0).dot(a.1 + b.1 + c.1) < 0.0 {
+            // Keep the winding consistent with the normals so flat shading agrees.
+            self.triangles.push(Triangle3D::new(a.0, c.0, b.0, self.color).with_normals([a.1, c.1, b.1]));
+        } else {
+            self.triangles.push(Triangle3D::new(a.0, b.0, c.0, self.color).with_normals([a.1, b.1, c.1]));
+        }
+    }
+
+    /// Two triangles spanning `a b c d` in order around the quad.
+    fn quad(&mut self, a: (Point3D, Point3D), b: (Point3D, Point3D), c: (Point3D, Point3D), d: (Point3D, Point3D)) {
+        self.triangle([a, b, c]);
+        self.triangle([a, c, d]);
+    }
+}
+
+fn ring_point(angle: f32, radius: f32, y: f32) -> Point3D {
+    Point3D::new(radius * angle.cos(), y, radius * angle.sin())
+}
+
+fn segment_angle(i: i32, segments: i32) -> f32 {
+    2.0 * PI * i as f32 / segments.max(3) as f32
+}
+
+/// Flat ring or disk at height `y` whose faces all point along `normal`.
+fn cap(surface: &mut Surface, radius: f32, inner_radius: f32, y: f32, normal: Point3D, segments: i32) {
+    let segments = segments.max(3);
+    for i in 0..segments {
+        let (t1, t2) = (segment_angle(i, segments), segment_angle(i + 1, segments));
+        let outer = (ring_point(t1, radius, y), ring_point(t2, radius, y));
+        if inner_radius > 0.0 {
+            let inner = (ring_point(t1, inner_radius, y), ring_point(t2, inner_radius, y));
+            surface.quad((outer.0, normal), (outer.1, normal), (inner.1, normal), (inner.0, normal));
+        } else {
+            surface.triangle([(Point3D::new(0.0, y, 0.0), normal), (outer.0, normal), (outer.1, normal)]);
+        }
+    }
+}
+
+pub fn cylinder_triangles(object: &Cylinder) -> Vec<Triangle3D>{
+    let mut surface = Surface::new(object.center, object.angle_x, object.angle_y, object.scale, object.color);
+    let (r, h) = (object.radius, object.height / 2.0);
+    let segments = object.segments.max(3);
+
+    for i in 0..segments {
+        let (t1, t2) = (segment_angle(i, segments), segment_angle(i + 1, segments));
+        let (n1, n2) = (ring_point(t1, 1.0, 0.0), ring_point(t2, 1.0, 0.0));
+        surface.quad(
+            (ring_point(t1, r, -h), n1),
+            (ring_point(t2, r, -h), n2),
+            (ring_point(t2, r, h), n2),
+            (ring_point(t1, r, h), n1),
+        );
+    }
+    cap(&mut surface, r, 0.0, -h, Point3D::new(0.0, -1.0, 0.0), segments);
+    cap(&mut surface, r, 0.0, h, Point3D::new(0.0, 1.0, 0.0), segments);
+
+    surface.triangles
+}
+
+pub fn cone_triangles(object: &Cone) -> Vec<Triangle3D>{
+    let mut surface = Surface::new(object.center, object.angle_x, object.angle_y, object.scale, object.color);
+    let (r, h) = (object.radius, object.height / 2.0);
+    let segments = object.segments.max(3);
+    // The side normal leans away from the apex by the cone's slope.
+    let slope = r / object.height.max(f32::EPSILON);
+    let apex = Point3D::new(0.0, -h, 0.0);
+
+    for i in 0..segments {
+        let (t1, t2) = (segment_angle(i, segments), segment_angle(i + 1, segments));
+        let tm = (t1 + t2) / 2.0;
+        let normal = |t: f32| Point3D::new(t.cos(), -slope, t.sin()).normalize();
+        surface.triangle([
+            (apex, normal(tm)),
+            (ring_point(t1, r, h), normal(t1)),
+            (ring_point(t2, r, h), normal(t2)),
+        ]);
+    }
+    cap(&mut surface, r, 0.0, h, Point3D::new(0.0, 1.0, 0.0), segments);
+
+    surface.triangles
+}
+
+pub fn torus_triangles(object: &Torus) -> Vec<Triangle3D>{
+    let mut surface = Surface::new(object.center, object.angle_x, object.angle_y, object.scale, object.color);
+    let (segments, sides) = (object.segments.max(3), object.sides.max(3));
+    let point = |i: i32, j: i32| {
+        let (u, v) = (segment_angle(i, segments), segment_angle(j, sides));
+        let normal = Point3D::new(v.cos() * u.cos(), v.sin(), v.cos() * u.sin());
+        let ring = ring_point(u, object.major_radius, 0.0);
+        (ring + normal * object.minor_radius, normal)
+    };
+
+    for i in 0..segments {
+        for j in 0..sides {
+            surface.quad(point(i, j), point(i + 1, j), point(i + 1, j + 1), point(i, j + 1));
+        }
+    }
+
+    surface.triangles
+}
+
+pub fn capsule_triangles(object: &Capsule) -> Vec<Triangle3D>{
+    let mut surface = Surface::new(object.center, object.angle_x, object.angle_y, object.scale, object.color);
+    let (r, h) = (object.radius, object.height / 2.0);
+    let (segments, rings) = (object.segments.max(3), object.rings.max(1));
+
+    // Latitude rows from the top pole to the bottom one; the two equator rows are
+    // offset by the straight part, which joins them with the cylinder wall.
+    let rows: Vec<(f32, f32)> = (0..=rings).map(|k| -PI / 2.0 + PI / 2.0 * k as f32 / rings as f32)
+        .map(|phi| (phi, -h))
+        .chain((0..=rings).map(|k| PI / 2.0 * k as f32 / rings as f32).map(|phi| (phi, h)))
+        .collect();
+    let point = |row: (f32, f32), i: i32| {
+        let (phi, offset) = row;
+        let theta = segment_angle(i, segments);
+        let normal = Point3D::new(phi.cos() * theta.cos(), phi.sin(), phi.cos() * theta.sin());
+        (normal * r + Point3D::new(0.0, offset, 0.0), normal)
+    };
+
+    for pair in rows.windows(2) {
+        for i in 0..segments {
+            let (a, b, c, d) = (point(pair[0], i), point(pair[0], i + 1), point(pair[1], i + 1), point(pair[1], i));
+            // Rows that meet at a pole collapse to a single triangle.
+            if pair[0].0 <= -PI / 2.0 + f32::EPSILON {
+                surface.triangle([a, c, d]);
+            } else if pair[1].0 >= PI / 2.0 - f32::EPSILON {
+                surface.triangle([a, b, c]);
+            } else {
+                surface.quad(a, b, c, d);
+            }
+        }
+    }
+
+    surface.triangles
+}
+
+pub fn disk_triangles(object: &Disk) -> Vec<Triangle3D>{
+    let mut surface = Surface::new(object.center, object.angle_x, object.angle_y, object.scale, object.color);
+    let inner = object.inner_radius.clamp(0.0, object.radius);
+    cap(&mut surface, object.radius, inner, 0.0, Point3D::new(0.0, -1.0, 0.0), object.segments);
+
+    surface.triangles
+}
+
+pub fn draw_cylinder(object: &Cylinder, view: &View, frame: &mut Frame){
+    for triangle in cylinder_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_cone(object: &Cone, view: &View, frame: &mut Frame){
+    for triangle in cone_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_torus(object: &Torus, view: &View, frame: &mut Frame){
+    for triangle in torus_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_capsule(object: &Capsule, view: &View, frame: &mut Frame){
+    for triangle in capsule_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
+
+pub fn draw_disk(object: &Disk, view: &View, frame: &mut Frame){
+    for triangle in disk_triangles(object) {
+        triangle_3d_fill(triangle, object.selected, view, frame);
+    }
+}
@@ -0,0 +1,468 @@
+use std::thread;
+
+use crate::bounds::BoundingSphere;
+use crate::drawing::Frame;
+use crate::geometry::*;
+use crate::objec::Objects;
+use crate::particles::Particle;
+use crate::picking::Ray;
+use crate::scene::Scene;
+
+/// Share of the light every surface gets, even in shadow; as dark as the rasterizer shades.
+const AMBIENT: f32 = 0.2;
+
+/// Hits closer than this to where a ray starts are the surface it leaves, not another one.
+const BIAS: f32 = 1e-3;
+
+/// Sharpness of highlights on reflective and transparent surfaces.
+const SHININESS: f32 = 40.0;
+
+/// Most surfaces a shadow ray passes through before the light counts as blocked.
+const SHADOW_LAYERS: usize = 8;
+
+/// How light bounces off and passes through an object, for the ray tracer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material{
+    /// Share of the color mirrored from around the object, 0 to 1.
+    pub reflect: f32,
+    /// Share of the color seen through the object, 0 to 1.
+    pub transparency: f32,
+    /// Index of refraction of the object's inside, e.g. 1.5 for glass.
+    pub ior: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material{ reflect: 0.0, transparency: 0.0, ior: 1.5 }
+    }
+}
+
+/// Ray tracing settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Tracer{
+    /// Rays per pixel along each side, for `samples` squared rays per pixel.
+    pub samples: u32,
+    /// Reflections and refractions followed from each camera ray.
+    pub bounces: u32,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer{ samples: 2, bounces: 5 }
+    }
+}
+
+impl Tracer{
+    /// Rays per pixel, one per pass.
+    pub fn passes(&self) -> u32 {
+        self.samples.max(1).pow(2)
+    }
+
+    /// Where in the pixel the ray of `pass` goes: the middle of a cell of an
+    /// even grid, so the passes together cover the pixel.
+    fn offset(&self, pass: u32) -> (f32, f32) {
+        let side = self.samples.max(1);
+        (((pass % side) as f32 + 0.5) / side as f32, ((pass / side) as f32 + 0.5) / side as f32)
+    }
+}
+
+/// Ray traces `scene` into `frame` with every pass at once, e.g. for images.
+pub fn trace_scene(scene: &Scene, tracer: Tracer, frame: &mut Frame) {
+    let mut progressive = Progressive::new(tracer);
+    while !progressive.done() {
+        progressive.refine(scene, frame);
+    }
+}
+
+/// An image that gets one more ray per pixel each `refine`, so a first rough
+/// frame shows right away and smooths out over the next ones.
+pub struct Progressive{
+    tracer: Tracer,
+    pass: u32,
+    /// Sum of the colors traced so far, per pixel.
+    sums: Vec<[f32; 3]>,
+    /// Depth and object ID of what the middle of each pixel sees, from the first pass.
+    depth: Vec<f32>,
+    ids: Vec<u32>,
+}
+
+impl Progressive{
+    pub fn new(tracer: Tracer) -> Self {
+        Progressive{ tracer, pass: 0, sums: Vec::new(), depth: Vec::new(), ids: Vec::new() }
+    }
+
+    /// Starts over, e.g. after the scene or camera changed.
+    pub fn reset(&mut self) {
+        self.pass = 0;
+    }
+
+    /// Passes traced since the last reset.
+    pub fn pass(&self) -> u32 {
+        self.pass
+    }
+
+    pub fn done(&self) -> bool {
+        self.pass >= self.tracer.passes()
+    }
+
+    /// Traces the next pass, unless all are done, and puts the average of the
+    /// passes so far into `frame`, with depth and IDs for overlays and picking.
+    pub fn refine(&mut self, scene: &Scene, frame: &mut Frame) {
+        let (width, height) = (frame.width as usize, frame.height as usize);
+        if self.sums.len() != width * height {
+            self.pass = 0;
+        }
+        if self.pass == 0 {
+            self.sums = vec![[0.0; 3]; width * height];
+            self.depth = vec![f32::INFINITY; width * height];
+            self.ids = vec![0; width * height];
+        }
+        if !self.done() {
+            self.trace_pass(scene, frame.width, frame.height);
+            self.pass += 1;
+        }
+
+        frame.clear();
+        let scale = 255.0 / self.pass.max(1) as f32;
+        for (i, sum) in self.sums.iter().enumerate() {
+            let [r, g, b] = sum.map(|c| (c * scale).round().clamp(0.0, 255.0) as u8);
+            frame.color[i * 4..i * 4 + 4].copy_from_slice(&[r, g, b, 255]);
+        }
+        frame.depth.copy_from_slice(&self.depth);
+        if let Some(ids) = &mut frame.ids {
+            ids.copy_from_slice(&self.ids);
+        }
+    }
+
+    /// Adds one ray per pixel to the sums, splitting the rows between threads.
+    fn trace_pass(&mut self, scene: &Scene, width: u32, height: u32) {
+        let world = World::new(scene);
+        let (dx, dy) = self.tracer.offset(self.pass);
+        let first = self.pass == 0;
+        let bounces = self.tracer.bounces;
+        let camera = scene.camera;
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let band = (height as usize).div_ceil(threads).max(1) * width as usize;
+
+        thread::scope(|s| {
+            let bands = self.sums.chunks_mut(band).zip(self.depth.chunks_mut(band)).zip(self.ids.chunks_mut(band));
+            for (n, ((sums, depth), ids)) in bands.enumerate() {
+                let world = &world;
+                s.spawn(move || {
+                    for (i, sum) in sums.iter_mut().enumerate() {
+                        let pixel = n * band + i;
+                        let (x, y) = ((pixel % width as usize) as f32, (pixel / width as usize) as f32);
+                        let ray = camera.ray(x + dx, y + dy, width, height);
+                        let ray = Ray::new(ray.origin, ray.direction.normalize());
+                        let (color, hit) = world.trace(&ray, bounces, false);
+                        for k in 0..3 {
+                            sum[k] += color[k];
+                        }
+                        if let Some(hit) = hit.filter(|_| first) {
+                            depth[i] = camera.to_view(hit.point).z;
+                            ids[i] = world.items[hit.item].index as u32 + 1;
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// The scene's objects, prepared for tracing.
+struct World{
+    items: Vec<Item>,
+    light: Point3D,
+}
+
+struct Item{
+    /// Index of the object in the scene.
+    index: usize,
+    bounds: BoundingSphere,
+    shape: Shape,
+    material: Material,
+}
+
+enum Shape{
+    Sphere{ center: Point3D, radius: f32, color: [f32; 3] },
+    Triangles(Vec<Triangle3D>),
+    /// Glowing balls that light nothing and cast no shadows.
+    Particles(Vec<Particle>),
+}
+
+/// Where a ray meets an item.
+#[derive(Clone, Copy)]
+struct Hit{
+    t: f32,
+    point: Point3D,
+    /// Unit surface normal, on whichever side the surface was drawn with.
+    normal: Point3D,
+    color: [f32; 3],
+    item: usize,
+}
+
+impl World{
+    fn new(scene: &Scene) -> World {
+        let items = scene.objects.iter().enumerate().map(|(index, object)| {
+            let shape = match object {
+                Objects::Sphere(o) => {
+                    let sphere = object.bounding_sphere();
+                    Shape::Sphere{ center: sphere.center, radius: sphere.radius, color: unit_color(o.color) }
+                }
+                Objects::Emitter(o) => Shape::Particles(o.particles()),
+                _ => Shape::Triangles(object.triangles()),
+            };
+            Item{ index, bounds: object.bounding_sphere(), shape, material: object.state().material }
+        }).collect();
+        World{ items, light: scene.light }
+    }
+
+    /// Nearest hit along unit-length `ray` closer than `max`; shadow rays pass
+    /// through particles.
+    fn hit(&self, ray: &Ray, max: f32, shadow: bool) -> Option<Hit> {
+        let mut best: Option<Hit> = None;
+        for (item_index, item) in self.items.iter().enumerate() {
+            let limit = best.map_or(max, |b| b.t);
+            if ray.hits_sphere(&item.bounds).is_none_or(|t| t > limit) {
+                continue;
+            }
+            let found = match &item.shape {
+                Shape::Sphere{ center, radius, color } => sphere_hit(ray, *center, *radius).map(|t| {
+                    let point = ray.at(t);
+                    (t, point, (point - *center).normalize(), *color)
+                }),
+                Shape::Triangles(triangles) => triangles.iter()
+                    .filter_map(|triangle| ray.hits_triangle_at(triangle).filter(|&(t, _, _)| t > BIAS).map(|hit| (hit, triangle)))
+                    .min_by(|a, b| a.0.0.total_cmp(&b.0.0))
+                    .map(|((t, u, v), triangle)| (t, ray.at(t), triangle_normal(triangle, u, v), triangle_color(triangle, u, v))),
+                Shape::Particles(_) if shadow => None,
+                Shape::Particles(particles) => particles.iter()
+                    .filter_map(|p| sphere_hit(ray, p.position, p.size / 2.0).map(|t| (t, p)))
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .map(|(t, p)| (t, ray.at(t), (ray.at(t) - p.position).normalize(), unit_color(p.color))),
+            };
+            if let Some((t, point, normal, color)) = found.filter(|&(t, ..)| t < limit) {
+                best = Some(Hit{ t, point, normal, color, item: item_index });
+            }
+        }
+        best
+    }
+
+    /// Color seen along unit-length `ray`, and what it hits first. `inside` tells
+    /// whether the ray travels through a transparent object.
+    fn trace(&self, ray: &Ray, bounces: u32, inside: bool) -> ([f32; 3], Option<Hit>) {
+        match self.hit(ray, f32::INFINITY, false) {
+            Some(hit) => (self.shade(ray, &hit, bounces, inside), Some(hit)),
+            None => ([0.0; 3], None),
+        }
+    }
+
+    fn shade(&self, ray: &Ray, hit: &Hit, bounces: u32, inside: bool) -> [f32; 3] {
+        let item = &self.items[hit.item];
+        if matches!(item.shape, Shape::Particles(_)) {
+            return hit.color;
+        }
+        let d = ray.direction;
+        let n = if hit.normal.dot(d) > 0.0 { hit.normal * -1.0 } else { hit.normal };
+
+        let to_light = self.light - hit.point;
+        let distance = to_light.dot(to_light).sqrt();
+        let l = to_light * (1.0 / distance.max(f32::EPSILON));
+        let lit = self.transmission(hit.point, l, distance);
+        let intensity = AMBIENT + (1.0 - AMBIENT) * n.dot(l).max(0.0) * lit;
+
+        let material = item.material;
+        let reflect = material.reflect.clamp(0.0, 1.0);
+        let transparency = material.transparency.clamp(0.0, 1.0 - reflect);
+        let local = hit.color.map(|c| c * intensity);
+        let mirror = d - n * (2.0 * d.dot(n));
+        let highlight = if reflect + transparency > 0.0 {
+            (reflect + transparency) * lit * mirror.dot(l).max(0.0).powf(SHININESS)
+        } else {
+            0.0
+        };
+        let mut color = local.map(|c| c * (1.0 - reflect - transparency) + highlight);
+        let mut add = |weight: f32, light: [f32; 3]| {
+            for k in 0..3 {
+                color[k] += weight * light[k];
+            }
+        };
+
+        if bounces == 0 {
+            // Out of bounces: what would have come from elsewhere looks like the surface.
+            add(reflect + transparency, local);
+            return color;
+        }
+        let bounce = |direction: Point3D, inside: bool| self.trace(&Ray::new(hit.point, direction.normalize()), bounces - 1, inside).0;
+        if reflect > 0.0 {
+            add(reflect, bounce(mirror, inside));
+        }
+        if transparency > 0.0 {
+            let eta = if inside { material.ior } else { 1.0 / material.ior.max(f32::EPSILON) };
+            let cos = -n.dot(d);
+            let k = 1.0 - eta * eta * (1.0 - cos * cos);
+            if k < 0.0 {
+                // Total internal reflection.
+                add(transparency, bounce(mirror, inside));
+            } else {
+                add(transparency, bounce(d * eta + n * (eta * cos - k.sqrt()), !inside));
+            }
+        }
+        color
+    }
+
+    /// Share of the light from `distance` along unit `direction` that reaches
+    /// `point`: transparent objects on the way dim it, opaque ones block it.
+    fn transmission(&self, point: Point3D, direction: Point3D, distance: f32) -> f32 {
+        let mut lit = 1.0;
+        let mut from = point;
+        let mut left = distance;
+        for _ in 0..SHADOW_LAYERS {
+            let Some(hit) = self.hit(&Ray::new(from, direction), left, true) else {
+                return lit;
+            };
+            lit *= self.items[hit.item].material.transparency.clamp(0.0, 1.0);
+            if lit == 0.0 {
+                break;
+            }
+            from = hit.point;
+            left -= hit.t;
+        }
+        lit
+    }
+}
+
+/// Nearest distance past `BIAS` at which unit-length `ray` meets the sphere,
+/// from outside or from inside.
+fn sphere_hit(ray: &Ray, center: Point3D, radius: f32) -> Option<f32> {
+    let to_origin = ray.origin - center;
+    let b = to_origin.dot(ray.direction);
+    let c = to_origin.dot(to_origin) - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [-b - root, -b + root].into_iter().find(|&t| t > BIAS)
+}
+
+fn triangle_normal(triangle: &Triangle3D, u: f32, v: f32) -> Point3D {
+    match triangle.normals {
+        Some([a, b, c]) => (a * (1.0 - u - v) + b * u + c * v).normalize(),
+        None => (triangle.p2 - triangle.p1).cross(triangle.p3 - triangle.p1).normalize(),
+    }
+}
+
+fn triangle_color(triangle: &Triangle3D, u: f32, v: f32) -> [f32; 3] {
+    match triangle.colors {
+        Some([a, b, c]) => {
+            let (a, b, c) = (unit_color(a), unit_color(b), unit_color(c));
+            [0, 1, 2].map(|k| a[k] * (1.0 - u - v) + b[k] * u + c[k] * v)
+        }
+        None => unit_color(triangle.color),
+    }
+}
+
+fn unit_color(color: [u8; 3]) -> [f32; 3] {
+    color.map(|c| c as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::objec::Sphere;
+
+    fn p(x: f32, y: f32, z: f32) -> Point3D {
+        Point3D::new(x, y, z)
+    }
+
+    fn ball(center: Point3D, radius: f32, color: [f32; 3], material: Material) -> Item {
+        Item{ index: 0, bounds: BoundingSphere{ center, radius }, shape: Shape::Sphere{ center, radius, color }, material }
+    }
+
+    fn scene() -> Scene {
+        let mut scene = Scene::default();
+        scene.objects.push(Objects::Sphere(Sphere::new(p(0.0, 0.0, 4.0), 1.0, 8, 12, [200, 120, 40])));
+        scene
+    }
+
+    #[test]
+    fn sphere_hit_from_outside_and_inside() {
+        let ray = Ray::new(p(0.0, 0.0, -5.0), p(0.0, 0.0, 1.0));
+        assert!((sphere_hit(&ray, p(0.0, 0.0, 0.0), 1.0).unwrap() - 4.0).abs() < 1e-5);
+        // From inside, the far side; from the surface, not the surface itself.
+        let inside = Ray::new(p(0.0, 0.0, 0.5), p(0.0, 0.0, 1.0));
+        assert!((sphere_hit(&inside, p(0.0, 0.0, 0.0), 1.0).unwrap() - 0.5).abs() < 1e-5);
+        let leaving = Ray::new(p(0.0, 0.0, -1.0), p(0.0, 0.0, 1.0));
+        assert!((sphere_hit(&leaving, p(0.0, 0.0, 0.0), 1.0).unwrap() - 2.0).abs() < 1e-5);
+        // Behind the ray, or beside it.
+        assert!(sphere_hit(&ray, p(0.0, 0.0, -8.0), 1.0).is_none());
+        assert!(sphere_hit(&ray, p(2.0, 0.0, 0.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn grazing_rays_reflect_inside_dense_spheres() {
+        // A red glass ball, with a green one straight ahead of a ray about to leave it.
+        let world = |ior: f32| World{
+            items: vec![
+                ball(p(0.0, 0.0, 0.0), 1.0, [1.0, 0.0, 0.0], Material{ reflect: 0.0, transparency: 1.0, ior }),
+                ball(p(0.0, 0.9, 3.0), 0.5, [0.0, 1.0, 0.0], Material::default()),
+            ],
+            light: p(0.0, -5.0, 3.0),
+        };
+        let ray = Ray::new(p(0.0, 0.9, -0.3), p(0.0, 0.0, 1.0));
+        let through = world(1.0).trace(&ray, 1, true).0;
+        assert!(through[1] > 0.1 && through[0] < through[1], "{:?} does not see the green ball", through);
+        // Leaving glass at this angle it cannot get out, and sees the glass again.
+        let reflected = world(1.5).trace(&ray, 1, true).0;
+        assert!(reflected[0] > 0.1 && reflected[1] < reflected[0], "{:?} does not see the glass", reflected);
+    }
+
+    #[test]
+    fn progressive_counts_passes_and_starts_over() {
+        let scene = scene();
+        let tracer = Tracer{ samples: 2, bounces: 1 };
+        let mut progressive = Progressive::new(tracer);
+        let mut frame = Frame::new(8, 6);
+        frame.enable_ids();
+        for pass in 1..=tracer.passes() {
+            assert!(!progressive.done());
+            progressive.refine(&scene, &mut frame);
+            assert_eq!(progressive.pass(), pass);
+        }
+        assert!(progressive.done());
+        progressive.refine(&scene, &mut frame);
+        assert_eq!(progressive.pass(), tracer.passes(), "traced past the last pass");
+        assert_eq!(frame.object_at(4, 3), Some(0));
+        assert!(frame.depth[3 * 8 + 4].is_finite());
+
+        progressive.reset();
+        assert_eq!(progressive.pass(), 0);
+        progressive.refine(&scene, &mut frame);
+        assert_eq!(progressive.pass(), 1);
+
+        // A frame of another size starts over with buffers to match.
+        progressive.refine(&scene, &mut frame);
+        let mut larger = Frame::new(10, 7);
+        progressive.refine(&scene, &mut larger);
+        assert_eq!(progressive.pass(), 1);
+        assert!(larger.depth[3 * 10 + 5].is_finite());
+    }
+
+    #[test]
+    fn trace_scene_matches_a_refined_image() {
+        let scene = scene();
+        let tracer = Tracer{ samples: 3, bounces: 2 };
+        let mut traced = Frame::new(12, 9);
+        trace_scene(&scene, tracer, &mut traced);
+
+        let mut progressive = Progressive::new(tracer);
+        let mut refined = Frame::new(12, 9);
+        while !progressive.done() {
+            progressive.refine(&scene, &mut refined);
+        }
+        assert_eq!(traced.color, refined.color);
+        assert_eq!(traced.depth, refined.depth);
+        assert!(traced.color.chunks(4).any(|c| c[..3] != [0, 0, 0]), "nothing traced");
+    }
+}
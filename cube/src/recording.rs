@@ -0,0 +1,253 @@
+use std::f32::consts::PI;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::bounds::Aabb;
+use crate::camera::Camera;
+use crate::drawing::{Frame, RenderMode};
+use crate::geometry::*;
+use crate::gif::GifEncoder;
+use crate::image;
+use crate::raytrace::{trace_scene, Tracer};
+use crate::render::render_scene;
+use crate::scene::{Fields, Scene, SceneError};
+use crate::y4m::Y4mEncoder;
+
+/// Frame rate when none is given.
+pub const DEFAULT_FPS: u32 = 30;
+
+/// Length of a recording with nothing else to go by, e.g. a turntable of a still scene.
+pub const DEFAULT_DURATION: f32 = 4.0;
+
+/// A camera position and the point it looks at, at a time along a path.
+#[derive(Clone, Copy, Debug)]
+pub struct PathPoint{
+    /// Seconds from the start.
+    pub time: f32,
+    pub position: Point3D,
+    pub target: Point3D,
+}
+
+/// Camera motion through points in time, along a Catmull-Rom spline that passes
+/// through every point.
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath{
+    points: Vec<PathPoint>,
+}
+
+impl CameraPath{
+    pub fn new(mut points: Vec<PathPoint>) -> Self {
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        CameraPath{ points }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<CameraPath, SceneError> {
+        CameraPath::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses one `point time=<seconds> position=x,y,z target=x,y,z` line per point,
+    /// in the scene file's syntax.
+    pub fn parse(text: &str) -> Result<CameraPath, SceneError> {
+        let mut points = Vec::new();
+        for (n, raw) in text.lines().enumerate() {
+            let err = |message: String| SceneError::Parse{ line: n + 1, message };
+            let content = raw.split('#').next().unwrap_or("").trim();
+            let mut tokens = content.split_whitespace();
+            match tokens.next() {
+                None => continue,
+                Some("point") => {}
+                Some(kind) => return Err(err(format!("unknown path line '{}'", kind))),
+            }
+            let fields = Fields::parse(tokens).map_err(err)?;
+            let time = fields.float_or("time", -1.0).map_err(err)?;
+            if time < 0.0 {
+                return Err(err("point needs a time from 0".into()));
+            }
+            points.push(PathPoint{ time, position: fields.point("position").map_err(err)?, target: fields.point("target").map_err(err)? });
+        }
+        if points.is_empty() {
+            return Err(SceneError::Parse{ line: text.lines().count(), message: "camera path has no points".into() });
+        }
+        Ok(CameraPath::new(points))
+    }
+
+    /// Time of the last point.
+    pub fn duration(&self) -> f32 {
+        self.points.last().map_or(0.0, |p| p.time)
+    }
+
+    /// Camera position and target at `time`, held before the first point and after the last.
+    pub fn sample(&self, time: f32) -> Option<(Point3D, Point3D)> {
+        let points = &self.points;
+        let last = points.len().checked_sub(1)?;
+        let i = match points.iter().position(|p| p.time > time) {
+            Some(0) => return Some((points[0].position, points[0].target)),
+            None => return Some((points[last].position, points[last].target)),
+            Some(i) => i - 1,
+        };
+        let (a, b) = (points[i], points[i + 1]);
+        let t = (time - a.time) / (b.time - a.time);
+        // The ends repeat, so the spline starts and stops at the first and last points.
+        let before = points[i.saturating_sub(1)];
+        let after = points[(i + 2).min(last)];
+        Some((
+            catmull_rom(before.position, a.position, b.position, after.position, t),
+            catmull_rom(before.target, a.target, b.target, after.target, t),
+        ))
+    }
+}
+
+fn catmull_rom(p0: Point3D, p1: Point3D, p2: Point3D, p3: Point3D, t: f32) -> Point3D {
+    let (t2, t3) = (t * t, t * t * t);
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+/// How the camera moves over a recording.
+#[derive(Clone, Debug)]
+pub enum Shot{
+    /// The scene's camera as it is; only keyed objects and the light move.
+    Still,
+    /// One full orbit around a point, keeping the camera's distance and height.
+    Turntable(Point3D),
+    Path(CameraPath),
+}
+
+impl Shot{
+    /// Seconds the shot lasts on its own: a path's length, else the scene's animation.
+    pub fn duration(&self, scene: &Scene) -> f32 {
+        match self {
+            Shot::Path(path) if path.duration() > 0.0 => path.duration(),
+            _ if scene.animation_end() > 0.0 => scene.animation_end(),
+            _ => DEFAULT_DURATION,
+        }
+    }
+
+    /// The camera at `time` seconds, `progress` of the way from the first frame to one past the last.
+    pub fn camera(&self, base: Camera, time: f32, progress: f32) -> Camera {
+        let mut camera = base;
+        match self {
+            Shot::Still => {}
+            Shot::Turntable(center) => {
+                let offset = base.position - *center;
+                let radius = (offset.x * offset.x + offset.z * offset.z).sqrt().max(0.5);
+                let angle = offset.x.atan2(offset.z) + 2.0 * PI * progress;
+                camera.position = Point3D::new(center.x + radius * angle.sin(), base.position.y, center.z + radius * angle.cos());
+                camera.look_at(*center);
+            }
+            Shot::Path(path) => {
+                if let Some((position, target)) = path.sample(time) {
+                    camera.position = position;
+                    camera.look_at(target);
+                }
+            }
+        }
+        camera
+    }
+}
+
+/// Middle of the bounding box around the objects at `indices`, or all objects when
+/// there are none; what a turntable orbits.
+pub fn turntable_center(scene: &Scene, indices: &[usize]) -> Point3D {
+    let boxes = scene.objects.iter().enumerate()
+        .filter(|(i, _)| indices.is_empty() || indices.contains(i))
+        .map(|(_, o)| o.aabb());
+    boxes.reduce(|a, b| a.union(&b)).map_or(Point3D::new(0.0, 0.0, 2.0), |b: Aabb| b.center())
+}
+
+/// Size, rate and length of a recording.
+#[derive(Clone, Copy, Debug)]
+pub struct Recording{
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub frames: u32,
+    /// Animation time of the first frame.
+    pub start: f32,
+    pub mode: RenderMode,
+    /// Ray trace the frames with these settings instead of rasterizing them.
+    pub tracer: Option<Tracer>,
+}
+
+/// Where recorded frames go, chosen by the output's extension.
+enum Sink{
+    /// Numbered PNG or PPM files.
+    Images{ pattern: String, count: u32 },
+    Gif(GifEncoder<BufWriter<File>>),
+    Y4m(Y4mEncoder<BufWriter<File>>),
+}
+
+impl Sink{
+    fn open(output: &str, recording: &Recording) -> io::Result<Sink> {
+        let extension = Path::new(output).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        Ok(match extension.as_str() {
+            "gif" => {
+                let delay = (100.0 / recording.fps as f32).round().max(1.0) as u16;
+                Sink::Gif(GifEncoder::new(BufWriter::new(File::create(output)?), recording.width, recording.height, delay)?)
+            }
+            "y4m" => Sink::Y4m(Y4mEncoder::new(BufWriter::new(File::create(output)?), recording.width, recording.height, recording.fps)?),
+            _ => Sink::Images{ pattern: output.to_string(), count: 0 },
+        })
+    }
+
+    fn add(&mut self, frame: &Frame) -> io::Result<()> {
+        match self {
+            Sink::Images{ pattern, count } => {
+                *count += 1;
+                image::save(numbered(pattern, *count), frame)
+            }
+            Sink::Gif(gif) => gif.add_frame(frame),
+            Sink::Y4m(y4m) => y4m.add_frame(frame),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Sink::Images{ .. } => Ok(()),
+            Sink::Gif(gif) => gif.finish().map(drop),
+            Sink::Y4m(y4m) => y4m.finish().map(drop),
+        }
+    }
+}
+
+/// The file name of frame `n` of an image sequence: a run of `#` in `pattern`
+/// becomes the zero-padded number, otherwise `_0001` goes before the extension.
+pub fn numbered(pattern: &str, n: u32) -> String {
+    if let Some(start) = pattern.rfind('#') {
+        let run = pattern[..=start].chars().rev().take_while(|&c| c == '#').count();
+        let start = start + 1 - run;
+        return format!("{}{:0width$}{}", &pattern[..start], n, &pattern[start + run..], width = run);
+    }
+    let path = Path::new(pattern);
+    match (path.file_stem(), path.extension()) {
+        (Some(stem), Some(extension)) => {
+            let name = format!("{}_{:04}.{}", stem.to_string_lossy(), n, extension.to_string_lossy());
+            path.with_file_name(name).to_string_lossy().into_owned()
+        }
+        _ => format!("{}_{:04}.png", pattern, n),
+    }
+}
+
+/// Renders the shot frame by frame into `output`: a `.gif`, a `.y4m` video stream or,
+/// for any other extension, numbered `.png` or `.ppm` files.
+pub fn record(scene: &Scene, shot: &Shot, recording: &Recording, output: &str) -> io::Result<()> {
+    let mut scene = scene.clone();
+    scene.deselect_all();
+    let base = scene.camera;
+    let mut sink = Sink::open(output, recording)?;
+    let mut frame = Frame::new(recording.width, recording.height);
+    for i in 0..recording.frames {
+        let time = recording.start + i as f32 / recording.fps as f32;
+        scene.pose(time);
+        scene.camera = shot.camera(base, time, i as f32 / recording.frames as f32);
+        match recording.tracer {
+            Some(tracer) => trace_scene(&scene, tracer, &mut frame),
+            None => render_scene(&scene, recording.mode, &mut frame),
+        }
+        sink.add(&frame)?;
+    }
+    sink.finish()
+}
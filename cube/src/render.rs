@@ -0,0 +1,275 @@
+use crate::bounds::{Frustum, NEAR};
+use crate::camera::Camera;
+use crate::drawing::*;
+use crate::geometry::*;
+use crate::gizmo::{Gizmo, GizmoMode, Handle};
+use crate::mesh;
+use crate::objec::{self, Objects, Transformable};
+use crate::particles;
+use crate::primitives;
+use crate::scene::Scene;
+
+/// Height of the ground grid. +Y points down, so the ground lies below the origin.
+pub const GROUND_LEVEL: f32 = 1.0;
+
+/// Distance between ground grid lines.
+const GRID_SPACING: f32 = 1.0;
+
+/// Ground grid lines and world axes fade out completely this far from the camera.
+const GRID_REACH: f32 = 30.0;
+
+/// Arm length and distance from the frame's corner of the orientation gizmo, in pixels.
+const ORIENTATION_PIXELS: f32 = 30.0;
+
+/// Debug drawings layered over the rendered scene.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Overlays {
+    /// Ground grid around the camera, fading with distance.
+    pub grid: bool,
+    /// World X, Y and Z axes through the origin of the ground.
+    pub axes: bool,
+    /// Directions of the world axes as seen from the camera, in the bottom-left corner.
+    pub orientation: bool,
+    /// World-space bounding box of every object.
+    pub bounds: bool,
+    /// Object to outline, e.g. the one under the cursor. Needs the frame's ID buffer.
+    pub hover: Option<usize>,
+    /// Transform gizmo shown on the selection.
+    pub gizmo: Option<GizmoMode>,
+    /// Gizmo handle to highlight, e.g. the one being dragged.
+    pub handle: Option<Handle>,
+}
+
+/// Clears `frame` and rasterizes every object of the scene into it.
+///
+/// Objects entirely outside the camera's view are skipped before their triangles are built.
+pub fn render_scene(scene: &Scene, mode: RenderMode, frame: &mut Frame){
+    frame.clear();
+
+    let view = View { light: scene.light, camera: scene.camera, mode };
+    let frustum = Frustum::new(&scene.camera, frame.width, frame.height);
+    for (index, object) in scene.objects.iter().enumerate().filter(|(_, o)| frustum.sees(o)){
+        frame.object_id = index as u32 + 1;
+        match object{
+            Objects::Cube(obj) => {
+                objec::draw_cube(obj, &view, frame);
+            }
+            Objects::Plane(obj) => {
+                objec::draw_plane(obj, &view, frame);
+            }
+            Objects::Triangle(obj) => {
+                objec::draw_triangle(obj, &view, frame);
+            }
+            Objects::Pyramid(obj) => {
+                objec::draw_pyramid(obj, &view, frame);
+            }
+            Objects::Sphere(obj) => {
+                objec::draw_sphere(obj, &view, frame);
+            }
+            Objects::Mesh(obj) => {
+                mesh::draw_mesh(obj, &view, frame);
+            }
+            Objects::Cylinder(obj) => {
+                primitives::draw_cylinder(obj, &view, frame);
+            }
+            Objects::Cone(obj) => {
+                primitives::draw_cone(obj, &view, frame);
+            }
+            Objects::Torus(obj) => {
+                primitives::draw_torus(obj, &view, frame);
+            }
+            Objects::Capsule(obj) => {
+                primitives::draw_capsule(obj, &view, frame);
+            }
+            Objects::Disk(obj) => {
+                primitives::draw_disk(obj, &view, frame);
+            }
+            Objects::Emitter(obj) => {
+                particles::draw_emitter(obj, &view, frame);
+            }
+        }
+    }
+    frame.object_id = 0;
+}
+
+/// Draws the enabled `overlays` on top of a frame filled by `render_scene`.
+pub fn draw_overlays(scene: &Scene, overlays: Overlays, frame: &mut Frame){
+    if let Some(index) = overlays.hover {
+        draw_outline(index, frame, [255, 255, 255, 255]);
+    }
+    // Overlay lines must not hide the objects beneath them from picking.
+    let ids = frame.ids.take();
+    // Axes first; the grid lines along them are at the same depth and lose.
+    if overlays.axes {
+        draw_axes(&scene.camera, frame);
+    }
+    if overlays.grid {
+        draw_grid(&scene.camera, frame);
+    }
+    if overlays.bounds {
+        draw_bounds(scene, frame);
+    }
+    if let Some(gizmo) = overlays.gizmo.and_then(|mode| Gizmo::new(scene, mode, frame.width)) {
+        gizmo.draw(&scene.camera, frame, overlays.handle);
+    }
+    if overlays.orientation {
+        draw_orientation(&scene.camera, frame);
+    }
+    frame.ids = ids;
+}
+
+/// Colors of the X, Y and Z axes.
+const AXIS_COLORS: [[u8; 3]; 3] = [[230, 70, 70], [90, 210, 90], [80, 130, 240]];
+
+/// Draws the ground grid, centered below the camera so it seems to go on forever.
+pub fn draw_grid(camera: &Camera, frame: &mut Frame){
+    let lines = (GRID_REACH / GRID_SPACING).ceil() as i32;
+    let snap = |v: f32| (v / GRID_SPACING).round() * GRID_SPACING;
+    let (cx, cz) = (snap(camera.position.x), snap(camera.position.z));
+    let reach = lines as f32 * GRID_SPACING;
+    for i in -lines..=lines {
+        let offset = i as f32 * GRID_SPACING;
+        let x = cx + offset;
+        let z = cz + offset;
+        draw_faded(Point3D::new(x, GROUND_LEVEL, cz - reach), Point3D::new(x, GROUND_LEVEL, cz + reach), camera, frame, [110, 110, 110]);
+        draw_faded(Point3D::new(cx - reach, GROUND_LEVEL, z), Point3D::new(cx + reach, GROUND_LEVEL, z), camera, frame, [110, 110, 110]);
+    }
+}
+
+/// Draws the world axes through the ground's origin: X and Z along the ground, Y across it.
+pub fn draw_axes(camera: &Camera, frame: &mut Frame){
+    let origin = Point3D::new(0.0, GROUND_LEVEL, 0.0);
+    let reach = GRID_REACH + camera.position.x.abs().max(camera.position.z.abs());
+    let [x, y, z] = AXIS_COLORS;
+    draw_faded(origin - Point3D::new(reach, 0.0, 0.0), origin + Point3D::new(reach, 0.0, 0.0), camera, frame, x);
+    draw_faded(origin - Point3D::new(0.0, reach, 0.0), origin + Point3D::new(0.0, reach, 0.0), camera, frame, y);
+    draw_faded(origin - Point3D::new(0.0, 0.0, reach), origin + Point3D::new(0.0, 0.0, reach), camera, frame, z);
+}
+
+/// Draws the world segment `a`-`b` in pieces, each darker the further it is from the
+/// camera, up to invisible at `GRID_REACH`. Depth-tested against the scene.
+fn draw_faded(a: Point3D, b: Point3D, camera: &Camera, frame: &mut Frame, color: [u8; 3]){
+    let pieces = ((b - a).dot(b - a).sqrt() / GRID_SPACING).ceil().max(1.0) as usize;
+    for k in 0..pieces {
+        let start = a + (b - a) * (k as f32 / pieces as f32);
+        let end = a + (b - a) * ((k + 1) as f32 / pieces as f32);
+        let middle = (start + end) * 0.5 - camera.position;
+        let fade = (1.0 - middle.dot(middle).sqrt() / GRID_REACH).clamp(0.0, 1.0);
+        if fade == 0.0 {
+            continue;
+        }
+        // Over the black background, darkening is the same as blending.
+        let [r, g, b] = color.map(|c| (c as f32 * fade * fade) as u8);
+        draw_segment(camera.to_view(start), camera.to_view(end), camera.fov, frame, [r, g, b, 255]);
+    }
+}
+
+/// Draws the directions of the world axes as the camera sees them, in the bottom-left
+/// corner. It stays on top of the scene; its own arms hide each other by depth.
+pub fn draw_orientation(camera: &Camera, frame: &mut Frame){
+    let corner = 2.0 * ORIENTATION_PIXELS;
+    if (frame.width as f32) < 2.0 * corner || (frame.height as f32) < 2.0 * corner {
+        return;
+    }
+    let center = Point3D::new(corner, frame.height as f32 - corner, 0.0);
+    let view = |p: Point3D| camera.to_view(camera.position + p);
+    let units = [Point3D::new(1.0, 0.0, 0.0), Point3D::new(0.0, 1.0, 0.0), Point3D::new(0.0, 0.0, 1.0)];
+    for (unit, color) in units.iter().zip(AXIS_COLORS) {
+        let arm = view(*unit);
+        let tip = center + Point3D::new(arm.x, arm.y, 0.0) * ORIENTATION_PIXELS;
+        // Depths below every scene depth, nearer arms smaller.
+        let depth = -2.0 + arm.z;
+        for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)] {
+            let point = |p: Point3D| Point { x: (p.x + dx) as u32, y: (p.y + dy) as u32 };
+            line(point(center), point(tip), -2.0, depth, frame, [color[0], color[1], color[2], 255]);
+        }
+    }
+}
+
+/// Paints the pixels along the visible silhouette of object `index`, found from the
+/// frame's ID buffer; does nothing when IDs are not recorded.
+pub fn draw_outline(index: usize, frame: &mut Frame, color: [u8; 4]){
+    let ids = match &frame.ids {
+        Some(ids) => ids,
+        None => return,
+    };
+    let id = index as u32 + 1;
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    let edge: Vec<usize> = (0..width * height).filter(|&i| {
+        let (x, y) = (i % width, i / width);
+        ids[i] == id && (x == 0 || y == 0 || x + 1 == width || y + 1 == height
+            || ids[i - 1] != id || ids[i + 1] != id || ids[i - width] != id || ids[i + width] != id)
+    }).collect();
+    for i in edge {
+        frame.color[i * 4..i * 4 + 4].copy_from_slice(&color);
+    }
+}
+
+/// Outlines each object's axis-aligned bounding box, yellow when selected.
+pub fn draw_bounds(scene: &Scene, frame: &mut Frame){
+    const EDGES: [(usize, usize); 12] = [
+        (0, 1), (2, 3), (4, 5), (6, 7),
+        (0, 2), (1, 3), (4, 6), (5, 7),
+        (0, 4), (1, 5), (2, 6), (3, 7),
+    ];
+    for object in &scene.objects {
+        let color = if object.is_selected() { [255, 220, 0, 255] } else { [0, 200, 80, 255] };
+        let corners = object.aabb().corners().map(|p| scene.camera.to_view(p));
+        for (a, b) in EDGES {
+            draw_segment(corners[a], corners[b], scene.camera.fov, frame, color);
+        }
+    }
+}
+
+/// Draws the camera-space segment `a`-`b`, depth-tested against the scene.
+pub fn draw_segment(a: Point3D, b: Point3D, fov: f32, frame: &mut Frame, color: [u8; 4]){
+    if let Some((start, end)) = clip_segment(a, b, fov, frame.width, frame.height) {
+        line(start.0, end.0, start.1, end.1, frame, color);
+    }
+}
+
+/// Screen end points and depths of the camera-space segment `a`-`b`, cut to the part
+/// in front of the camera and on screen so far-off endpoints do not wrap around.
+pub fn clip_segment(a: Point3D, b: Point3D, fov: f32, width: u32, height: u32) -> Option<((Point, f32), (Point, f32))>{
+    // Cut at the near plane first; projecting points behind the camera flips them.
+    let (a, b) = match (a.z >= NEAR, b.z >= NEAR) {
+        (true, true) => (a, b),
+        (false, false) => return None,
+        (true, false) => (a, a + (b - a) * ((a.z - NEAR) / (a.z - b.z))),
+        (false, true) => (b + (a - b) * ((b.z - NEAR) / (b.z - a.z)), b),
+    };
+
+    let focal = (width as f32 / 2.0) / (fov.to_radians() / 2.0).tan();
+    let screen = |p: Point3D| (
+        p.x * focal / p.z + width as f32 / 2.0,
+        p.y * focal / p.z + height as f32 / 2.0,
+    );
+    let (start, end) = (screen(a), screen(b));
+
+    // Liang-Barsky clipping against the frame.
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let (max_x, max_y) = ((width - 1) as f32, (height - 1) as f32);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-dx, start.0), (dx, max_x - start.0), (-dy, start.1), (dy, max_y - start.1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+
+    let at = |t: f32| Point { x: (start.0 + dx * t) as u32, y: (start.1 + dy * t) as u32 };
+    // Depth is interpolated in screen space, close enough for overlay lines.
+    let depth = |t: f32| a.z + (b.z - a.z) * t;
+    Some(((at(t0), depth(t0)), (at(t1), depth(t1))))
+}
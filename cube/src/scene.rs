@@ -0,0 +1,647 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::animation::{self, Easing, Keyframe, Property, Track};
+use crate::camera::Camera;
+use crate::geometry::*;
+use crate::mesh::Mesh;
+use crate::objec::{Cube, Objects, Plane, Pyramid, Sphere, Tessellation, Transformable, Triangle};
+use crate::polyhedra::MAX_ICOSPHERE_LEVEL;
+use crate::particles::{Blend, Emitter, ParticleStyle};
+use crate::primitives::{Capsule, Cone, Cylinder, Disk, Torus};
+use crate::raytrace::Material;
+
+/// Largest segment, side or step count a scene file may ask for.
+const MAX_COUNT: i32 = 1024;
+/// Largest grid of faces a pair of counts may make, so a typo cannot stall loading.
+const MAX_GRID: i32 = 256 * 256;
+
+/// Everything the viewer shows: the objects plus the light and camera they are rendered with.
+#[derive(Clone)]
+pub struct Scene{
+    pub objects: Vec<Objects>,
+    pub light: Point3D,
+    /// Keys of the light position; the objects carry their own keys.
+    pub light_track: Track,
+    pub camera: Camera,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene{
+            objects: Vec::new(),
+            light: Point3D::new(0.0, 0.0, 0.6),
+            light_track: Track::default(),
+            camera: Camera::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError{
+    Io(std::io::Error),
+    Parse{ line: usize, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "i/o error: {}", err),
+            SceneError::Parse{ line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl Scene{
+    /// Indices of the selected objects, in scene order.
+    pub fn selection(&self) -> Vec<usize> {
+        self.objects.iter().enumerate().filter(|(_, o)| o.is_selected()).map(|(i, _)| i).collect()
+    }
+
+    /// Selects the object at `index` alone, or toggles it in the selection when `add`.
+    /// Indices past the end only clear the selection.
+    pub fn select(&mut self, index: usize, add: bool) {
+        if !add {
+            self.deselect_all();
+        }
+        if let Some(object) = self.objects.get_mut(index) {
+            if add && object.is_selected() {
+                object.deselect();
+            } else {
+                object.select();
+            }
+        }
+    }
+
+    /// Copies of the selected objects, moved by `offset` in world space.
+    pub fn copy_selection(&self, offset: Point3D) -> Vec<Objects> {
+        self.objects.iter().filter(|o| o.is_selected()).map(|object| {
+            let mut copy = object.clone();
+            copy.move_world(offset);
+            copy
+        }).collect()
+    }
+
+    pub fn deselect_all(&mut self) {
+        for object in &mut self.objects {
+            object.deselect();
+        }
+    }
+
+    /// Time of the last key of the light or any object.
+    pub fn animation_end(&self) -> f32 {
+        self.objects.iter().map(|o| o.state().animation.end()).fold(self.light_track.end(), f32::max)
+    }
+
+    /// Poses the light and every keyed object as their keys have them at `time`,
+    /// and runs the particle emitters up to it.
+    pub fn pose(&mut self, time: f32) {
+        if let Some([x, y, z]) = self.light_track.sample(time) {
+            self.light = Point3D::new(x, y, z);
+        }
+        for object in &mut self.objects {
+            animation::pose_object(object, time);
+            if let Objects::Emitter(emitter) = object {
+                emitter.time = time;
+            }
+        }
+    }
+
+    pub fn has_emitters(&self) -> bool {
+        self.objects.iter().any(|o| matches!(o, Objects::Emitter(_)))
+    }
+
+    /// Runs the particle emitters on by `dt` seconds, apart from the timeline.
+    pub fn run_emitters(&mut self, dt: f32) {
+        for object in &mut self.objects {
+            if let Objects::Emitter(emitter) = object {
+                emitter.time += dt;
+            }
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+        Scene::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, self.to_text())?;
+        Ok(())
+    }
+
+    /// Serializes the scene as one line per object, with `key=value` fields; mass,
+    /// motion and material only where they differ from a new object's.
+    /// Meshes are followed by their `v` and `f` lines, and anything keyed by its `key` lines.
+    pub fn to_text(&self) -> String {
+        let mut out = String::from("# cube scene\n");
+        let c = &self.camera;
+        let _ = writeln!(out, "camera position={} angle={},{} fov={}", point(c.position), c.angle_x, c.angle_y, c.fov);
+        let _ = writeln!(out, "light position={}", point(self.light));
+        write_keys(&mut out, Property::Position, &self.light_track);
+
+        for object in &self.objects {
+            let (line, common) = match object {
+                Objects::Cube(o) => (format!("cube e1={} e2={}", point(o.e1), point(o.e2)), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Plane(o) => (format!("plane e1={} e2={}", point(o.e1), point(o.e2)), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Triangle(o) => (format!("triangle e1={} e2={} e3={}", point(o.e1), point(o.e2), point(o.e3)), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Pyramid(o) => (format!("pyramid e1={} e2={} e3={}", point(o.e1), point(o.e2), point(o.e3)), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Sphere(o) => {
+                    let ico = match o.tessellation {
+                        Tessellation::Uv => String::new(),
+                        Tessellation::Icosphere(level) => format!(" ico={}", level),
+                        Tessellation::Auto => String::from(" ico=auto"),
+                    };
+                    (format!("sphere center={} radius={} steps={},{}{}", point(o.center), o.radius, o.lat_steps, o.lon_steps, ico), (o.angle_x, o.angle_y, o.scale, o.color))
+                }
+                Objects::Mesh(o) => (format!("mesh center={}", point(o.center)), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Cylinder(o) => (format!("cylinder center={} radius={} height={} segments={}", point(o.center), o.radius, o.height, o.segments), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Cone(o) => (format!("cone center={} radius={} height={} segments={}", point(o.center), o.radius, o.height, o.segments), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Torus(o) => (format!("torus center={} radius={} tube={} segments={},{}", point(o.center), o.major_radius, o.minor_radius, o.segments, o.sides), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Capsule(o) => (format!("capsule center={} radius={} height={} segments={},{}", point(o.center), o.radius, o.height, o.segments, o.rings), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Disk(o) => (format!("disk center={} radius={} inner={} segments={}", point(o.center), o.radius, o.inner_radius, o.segments), (o.angle_x, o.angle_y, o.scale, o.color)),
+                Objects::Emitter(o) => {
+                    let [r, g, b] = o.end_color;
+                    (format!("emitter center={} rate={} lifetime={} speed={} spread={} gravity={} size={},{} end_color={},{},{} style={} blend={} seed={}",
+                        point(o.center), o.rate, o.lifetime, o.speed, o.spread, o.gravity, o.size, o.end_size, r, g, b, o.style.name(), o.blend.name(), o.seed),
+                        (o.angle_x, o.angle_y, o.scale, o.color))
+                }
+            };
+            let (angle_x, angle_y, scale, color) = common;
+            let _ = write!(out, "{} angle={},{} scale={} color={},{},{}", line, angle_x, angle_y, scale, color[0], color[1], color[2]);
+            write_body(&mut out, object);
+            write_material(&mut out, &object.state().material);
+            out.push('\n');
+
+            if let Objects::Mesh(mesh) = object {
+                for (i, v) in mesh.vertices.iter().enumerate() {
+                    let _ = write!(out, "v {} {} {}", v.x, v.y, v.z);
+                    if let Some(colors) = &mesh.colors {
+                        let _ = write!(out, " {} {} {}", colors[i][0], colors[i][1], colors[i][2]);
+                    }
+                    out.push('\n');
+                }
+                for [a, b, c] in &mesh.faces {
+                    let _ = writeln!(out, "f {} {} {}", a, b, c);
+                }
+            }
+            for property in Property::ALL {
+                write_keys(&mut out, property, object.state().animation.track(property));
+            }
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Scene, SceneError> {
+        let mut scene = Scene{ objects: Vec::new(), ..Scene::default() };
+        // Whether `key` lines belong to the light rather than the last object.
+        let mut keying_light = false;
+
+        for (n, raw) in text.lines().enumerate() {
+            let line = n + 1;
+            let err = |message: String| SceneError::Parse{ line, message };
+            let content = raw.split('#').next().unwrap_or("").trim();
+            let mut tokens = content.split_whitespace();
+            let kind = match tokens.next() {
+                Some(k) => k,
+                None => continue,
+            };
+
+            if kind == "v" || kind == "f" {
+                let mesh = match scene.objects.last_mut() {
+                    Some(Objects::Mesh(mesh)) => mesh,
+                    _ => return Err(err(format!("'{}' line outside a mesh", kind))),
+                };
+                let values: Vec<f32> = tokens.map(|t| t.parse::<f32>()).collect::<Result<_, _>>()
+                    .map_err(|_| err("bad number".into()))?;
+                if values.iter().any(|v| !v.is_finite()) {
+                    return Err(err(format!("'{}' line needs finite numbers", kind)));
+                }
+                if kind == "f" {
+                    if values.len() != 3 || values.iter().any(|&i| i < 0.0 || i.fract() != 0.0) {
+                        return Err(err("face needs 3 vertex indices".into()));
+                    }
+                    mesh.faces.push([values[0] as usize, values[1] as usize, values[2] as usize]);
+                } else {
+                    match values.len() {
+                        3 => {}
+                        6 if values[3..].iter().all(|c| (0.0..=255.0).contains(c)) => {
+                            mesh.colors.get_or_insert_with(Vec::new).push([values[3] as u8, values[4] as u8, values[5] as u8]);
+                        }
+                        6 => return Err(err("vertex color channels must be 0-255".into())),
+                        _ => return Err(err("vertex needs 3 coordinates and an optional color".into())),
+                    }
+                    mesh.vertices.push(Point3D::new(values[0], values[1], values[2]));
+                }
+                continue;
+            }
+
+            if kind == "key" {
+                let name = tokens.next().unwrap_or("");
+                let property = Property::parse(name).ok_or_else(|| err(format!("unknown keyed property '{}'", name)))?;
+                let key = parse_key(property, &Fields::parse(tokens).map_err(err)?).map_err(err)?;
+                match scene.objects.last_mut() {
+                    _ if keying_light && property == Property::Position => scene.light_track.insert(key),
+                    _ if keying_light => return Err(err("only the light position can be keyed".into())),
+                    Some(object) => object.state_mut().animation.track_mut(property).insert(key),
+                    None => return Err(err("'key' line before any object or light".into())),
+                }
+                continue;
+            }
+
+            let fields = Fields::parse(tokens).map_err(err)?;
+            keying_light = kind == "light";
+            match kind {
+                "camera" => {
+                    let (angle_x, angle_y) = fields.pair_or("angle", (0.0, 0.0)).map_err(err)?;
+                    scene.camera = Camera::new(fields.point("position").map_err(err)?, angle_x, angle_y, fields.float_or("fov", 90.0).map_err(err)?);
+                }
+                "light" => scene.light = fields.point("position").map_err(err)?,
+                _ => {
+                    let object = parse_object(kind, &fields).map_err(err)?;
+                    check_mesh(scene.objects.last()).map_err(err)?;
+                    scene.objects.push(object);
+                }
+            }
+        }
+        check_mesh(scene.objects.last()).map_err(|message| SceneError::Parse{ line: text.lines().count(), message })?;
+
+        Ok(scene)
+    }
+}
+
+fn parse_object(kind: &str, fields: &Fields) -> Result<Objects, String> {
+    let color = fields.color()?;
+    let mut object = match kind {
+        "cube" => Objects::Cube(Cube::new(fields.point("e1")?, fields.point("e2")?, color)),
+        "plane" => Objects::Plane(Plane::new(fields.point("e1")?, fields.point("e2")?, color)),
+        "triangle" => Objects::Triangle(Triangle::new(fields.point("e1")?, fields.point("e2")?, fields.point("e3")?, color)),
+        "pyramid" => Objects::Pyramid(Pyramid::new(fields.point("e1")?, fields.point("e2")?, fields.point("e3")?, color)),
+        "sphere" => {
+            let (lat, lon) = fields.counts_or("steps", (8, 16))?;
+            let tessellation = match fields.0.get("ico") {
+                None => Tessellation::Uv,
+                Some(&"auto") => Tessellation::Auto,
+                Some(level) => match level.parse::<u32>() {
+                    Ok(level) if level <= MAX_ICOSPHERE_LEVEL => Tessellation::Icosphere(level),
+                    _ => return Err(format!("ico must be auto or a level from 0 to {}", MAX_ICOSPHERE_LEVEL)),
+                },
+            };
+            Objects::Sphere(Sphere::new(fields.point("center")?, fields.float("radius")?, lat, lon, color).with_tessellation(tessellation))
+        }
+        "mesh" => {
+            let mut mesh = Mesh::new(Vec::new(), Vec::new(), color);
+            mesh.center = fields.point("center")?;
+            Objects::Mesh(mesh)
+        }
+        "cylinder" => Objects::Cylinder(Cylinder::new(fields.point("center")?, fields.float("radius")?, fields.float("height")?, fields.count_or("segments", 24)?, color)),
+        "cone" => Objects::Cone(Cone::new(fields.point("center")?, fields.float("radius")?, fields.float("height")?, fields.count_or("segments", 24)?, color)),
+        "torus" => {
+            let (segments, sides) = fields.counts_or("segments", (32, 16))?;
+            Objects::Torus(Torus::new(fields.point("center")?, fields.float("radius")?, fields.float("tube")?, segments, sides, color))
+        }
+        "capsule" => {
+            let (segments, rings) = fields.counts_or("segments", (24, 6))?;
+            Objects::Capsule(Capsule::new(fields.point("center")?, fields.float("radius")?, fields.float("height")?, segments, rings, color))
+        }
+        "disk" => Objects::Disk(Disk::new(fields.point("center")?, fields.float("radius")?, fields.float_or("inner", 0.0)?, fields.count_or("segments", 24)?, color)),
+        "emitter" => Objects::Emitter(parse_emitter(fields, color)?),
+        _ => return Err(format!("unknown object type '{}'", kind)),
+    };
+
+    // Emitters point straight up unless turned; everything else starts tilted to show its shape.
+    let tilt = if kind == "emitter" { (0.0, 0.0) } else { (-0.6, 0.4) };
+    let (angle_x, angle_y) = fields.pair_or("angle", tilt)?;
+    let scale = fields.float_or("scale", 1.0)?;
+    match &mut object {
+        Objects::Cube(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Plane(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Triangle(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Pyramid(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Sphere(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Mesh(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Cylinder(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Cone(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Torus(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Capsule(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Disk(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+        Objects::Emitter(o) => (o.angle_x, o.angle_y, o.scale) = (angle_x, angle_y, scale),
+    }
+
+    let body = &mut object.state_mut().body;
+    body.mass = fields.float_or("mass", body.mass)?;
+    if body.mass < 0.0 {
+        return Err("mass must not be negative".into());
+    }
+    if let Some(v) = fields.numbers("velocity", 3)? {
+        body.velocity = Point3D::new(v[0], v[1], v[2]);
+    }
+    if let Some(w) = fields.numbers("spin", 3)? {
+        body.angular_velocity = Point3D::new(w[0], w[1], w[2]);
+    }
+
+    let material = &mut object.state_mut().material;
+    material.reflect = fields.float_or("reflect", material.reflect)?;
+    material.transparency = fields.float_or("transparency", material.transparency)?;
+    material.ior = fields.float_or("ior", material.ior)?;
+    if !(0.0..=1.0).contains(&material.reflect) || !(0.0..=1.0).contains(&material.transparency) {
+        return Err("reflect and transparency must be from 0 to 1".into());
+    }
+    if material.ior <= 0.0 {
+        return Err("ior must be positive".into());
+    }
+    Ok(object)
+}
+
+fn parse_emitter(fields: &Fields, color: [u8; 3]) -> Result<Emitter, String> {
+    let mut emitter = Emitter::new(fields.point("center")?, fields.float_or("rate", 30.0)?, fields.float_or("lifetime", 1.5)?, fields.float_or("speed", 3.0)?, color);
+    if emitter.rate < 0.0 {
+        return Err("rate must not be negative".into());
+    }
+    if emitter.lifetime <= 0.0 {
+        return Err("lifetime must be positive".into());
+    }
+    emitter.spread = fields.float_or("spread", emitter.spread)?;
+    emitter.gravity = fields.float_or("gravity", emitter.gravity)?;
+    (emitter.size, emitter.end_size) = fields.pair_or("size", (emitter.size, emitter.end_size))?;
+    if emitter.size < 0.0 || emitter.end_size < 0.0 {
+        return Err("size must not be negative".into());
+    }
+    emitter.end_color = fields.color_or("end_color", color)?;
+    if let Some(name) = fields.0.get("style") {
+        emitter.style = ParticleStyle::parse(name).ok_or("style must be quads or points")?;
+    }
+    if let Some(name) = fields.0.get("blend") {
+        emitter.blend = Blend::parse(name).ok_or("blend must be opaque or additive")?;
+    }
+    if let Some(seed) = fields.0.get("seed") {
+        emitter.seed = seed.parse().map_err(|_| format!("bad seed {}", seed))?;
+    }
+    Ok(emitter)
+}
+
+fn write_body(out: &mut String, object: &Objects) {
+    let (body, default) = (&object.state().body, object.default_body());
+    let zero = |p: Point3D| p.x == 0.0 && p.y == 0.0 && p.z == 0.0;
+    if body.mass != default.mass {
+        let _ = write!(out, " mass={}", body.mass);
+    }
+    if !zero(body.velocity) {
+        let _ = write!(out, " velocity={}", point(body.velocity));
+    }
+    if !zero(body.angular_velocity) {
+        let _ = write!(out, " spin={}", point(body.angular_velocity));
+    }
+}
+
+fn write_material(out: &mut String, material: &Material) {
+    let default = Material::default();
+    if material.reflect != default.reflect {
+        let _ = write!(out, " reflect={}", material.reflect);
+    }
+    if material.transparency != default.transparency {
+        let _ = write!(out, " transparency={}", material.transparency);
+    }
+    if material.ior != default.ior {
+        let _ = write!(out, " ior={}", material.ior);
+    }
+}
+
+fn write_keys(out: &mut String, property: Property, track: &Track) {
+    for key in track.keys() {
+        let value: Vec<String> = key.value[..property.components()].iter().map(|v| v.to_string()).collect();
+        let _ = write!(out, "key {} time={} value={} ease={}", property.name(), key.time, value.join(","), key.easing.name());
+        if let Easing::Bezier(x1, y1, x2, y2) = key.easing {
+            let _ = write!(out, " curve={},{},{},{}", x1, y1, x2, y2);
+        }
+        out.push('\n');
+    }
+}
+
+fn parse_key(property: Property, fields: &Fields) -> Result<Keyframe, String> {
+    let time = fields.float("time")?;
+    if time < 0.0 {
+        return Err("key time must not be negative".into());
+    }
+    let numbers = fields.numbers("value", property.components())?.ok_or("missing value")?;
+    let mut value = [0.0; 3];
+    value[..numbers.len()].copy_from_slice(&numbers);
+    let curve = fields.numbers("curve", 4)?.map(|c| [c[0], c[1], c[2], c[3]]);
+    let easing = match fields.0.get("ease") {
+        Some(name) => Easing::parse(name, curve).ok_or_else(|| format!("bad easing '{}', expected step, linear, ease_in, ease_out, ease_in_out or bezier with curve=x1,y1,x2,y2", name))?,
+        None => Easing::Linear,
+    };
+    Ok(Keyframe{ time, value, easing })
+}
+
+/// A finished mesh block must reference only its own vertices and color all or none of them.
+fn check_mesh(object: Option<&Objects>) -> Result<(), String> {
+    if let Some(Objects::Mesh(mesh)) = object {
+        if mesh.faces.iter().flatten().any(|&i| i >= mesh.vertices.len()) {
+            return Err("mesh face index out of range".into());
+        }
+        if mesh.colors.as_ref().is_some_and(|c| c.len() != mesh.vertices.len()) {
+            return Err("mesh colors only some of its vertices".into());
+        }
+    }
+    Ok(())
+}
+
+fn check_count(key: &str, n: f32) -> Result<i32, String> {
+    if n.fract() != 0.0 || !(1.0..=MAX_COUNT as f32).contains(&n) {
+        return Err(format!("{} must be whole numbers from 1 to {}", key, MAX_COUNT));
+    }
+    Ok(n as i32)
+}
+
+fn point(p: Point3D) -> String {
+    format!("{},{},{}", p.x, p.y, p.z)
+}
+
+/// The `key=value` fields of one scene line; values are comma-separated numbers.
+pub(crate) struct Fields<'a>(pub(crate) HashMap<&'a str, &'a str>);
+
+impl<'a> Fields<'a> {
+    pub(crate) fn parse(tokens: impl Iterator<Item = &'a str>) -> Result<Self, String> {
+        let mut map = HashMap::new();
+        for token in tokens {
+            match token.split_once('=') {
+                Some((key, value)) => map.insert(key, value),
+                None => return Err(format!("expected key=value, found '{}'", token)),
+            };
+        }
+        Ok(Fields(map))
+    }
+
+    pub(crate) fn numbers(&self, key: &str, count: usize) -> Result<Option<Vec<f32>>, String> {
+        let value = match self.0.get(key) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let numbers: Vec<f32> = value.split(',').map(|t| t.parse::<f32>()).collect::<Result<_, _>>()
+            .map_err(|_| format!("bad number in {}={}", key, value))?;
+        if numbers.len() != count || numbers.iter().any(|n| !n.is_finite()) {
+            return Err(format!("{} needs {} finite numbers", key, count));
+        }
+        Ok(Some(numbers))
+    }
+
+    pub(crate) fn point(&self, key: &str) -> Result<Point3D, String> {
+        match self.numbers(key, 3)? {
+            Some(n) => Ok(Point3D::new(n[0], n[1], n[2])),
+            None => Err(format!("missing {}", key)),
+        }
+    }
+
+    fn float(&self, key: &str) -> Result<f32, String> {
+        self.numbers(key, 1)?.map(|n| n[0]).ok_or_else(|| format!("missing {}", key))
+    }
+
+    pub(crate) fn float_or(&self, key: &str, default: f32) -> Result<f32, String> {
+        Ok(self.numbers(key, 1)?.map_or(default, |n| n[0]))
+    }
+
+    fn pair_or(&self, key: &str, default: (f32, f32)) -> Result<(f32, f32), String> {
+        Ok(self.numbers(key, 2)?.map_or(default, |n| (n[0], n[1])))
+    }
+
+    /// Tessellation counts: whole numbers from 1 to `MAX_COUNT`.
+    fn count_or(&self, key: &str, default: i32) -> Result<i32, String> {
+        match self.numbers(key, 1)? {
+            Some(n) => check_count(key, n[0]),
+            None => Ok(default),
+        }
+    }
+
+    /// Count pairs that make a grid of faces, like sphere steps, also cap the grid's size.
+    fn counts_or(&self, key: &str, default: (i32, i32)) -> Result<(i32, i32), String> {
+        match self.numbers(key, 2)? {
+            Some(n) => {
+                let counts = (check_count(key, n[0])?, check_count(key, n[1])?);
+                if counts.0 * counts.1 > MAX_GRID {
+                    return Err(format!("{} must make at most {} faces", key, MAX_GRID));
+                }
+                Ok(counts)
+            }
+            None => Ok(default),
+        }
+    }
+
+    fn color(&self) -> Result<[u8; 3], String> {
+        self.color_or("color", crate::import::DEFAULT_COLOR)
+    }
+
+    pub(crate) fn color_or(&self, key: &str, default: [u8; 3]) -> Result<[u8; 3], String> {
+        match self.numbers(key, 3)? {
+            Some(n) if n.iter().all(|c| (0.0..=255.0).contains(c)) => Ok([n[0] as u8, n[1] as u8, n[2] as u8]),
+            Some(_) => Err(format!("{} channels must be 0-255", key)),
+            None => Ok(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENE: &str = "camera position=0,0,-1 angle=0.1,0.2 fov=75\n\
+        light position=1,2,3\n\
+        cube e1=0,0,2 e2=1,1,3 angle=-0.6,0.4 scale=1.5 color=255,0,0 mass=2 velocity=1,0,0\n\
+        sphere center=0,1,4 radius=0.5 steps=6,12 ico=2 color=1,2,3 reflect=0.5\n\
+        mesh center=0,0,3 color=10,20,30\n\
+        v 0 0 0 255 0 0\nv 1 0 0 0 255 0\nv 0 1 0 0 0 255\nf 0 1 2\n\
+        torus center=0,0,5 radius=1 tube=0.2 segments=16,8\n";
+
+    #[test]
+    fn save_load_round_trip() {
+        let scene = Scene::parse(SCENE).unwrap();
+        assert_eq!(scene.objects.len(), 4);
+        let text = scene.to_text();
+        assert_eq!(Scene::parse(&text).unwrap().to_text(), text);
+
+        let path = std::env::temp_dir().join(format!("cube-scene-{}.scene", std::process::id()));
+        scene.save(&path).unwrap();
+        let loaded = Scene::load(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap().to_text(), text);
+    }
+
+    #[test]
+    fn keys_round_trip_with_their_easing() {
+        let text = "light position=0,0,1\n\
+            key position time=0 value=0,0,1 ease=linear\n\
+            key position time=2 value=1,0,1 ease=step\n\
+            cube e1=0,0,2 e2=1,1,3\n\
+            key position time=0 value=0,0,2 ease=bezier curve=0.25,0.1,0.25,1\n\
+            key scale time=1.5 value=2 ease=ease_in_out\n\
+            key color time=1 value=255,0,0 ease=ease_out\n";
+        let scene = Scene::parse(text).unwrap();
+        assert_eq!(scene.light_track.keys().len(), 2);
+        let animation = &scene.objects[0].state().animation;
+        assert_eq!(animation.position.keys()[0].easing, Easing::Bezier(0.25, 0.1, 0.25, 1.0));
+        assert_eq!(animation.scale.keys()[0].value[0], 2.0);
+        assert_eq!(scene.animation_end(), 2.0);
+
+        let reloaded = Scene::parse(&scene.to_text()).unwrap();
+        assert_eq!(reloaded.light_track, scene.light_track);
+        assert_eq!(&reloaded.objects[0].state().animation, animation);
+    }
+
+    #[test]
+    fn rejects_bad_keys() {
+        for text in [
+            "key position time=0 value=0,0,0\n",
+            "light position=0,0,1\nkey scale time=0 value=1\n",
+            "cube e1=0,0,2 e2=1,1,3\nkey spin time=0 value=1\n",
+            "cube e1=0,0,2 e2=1,1,3\nkey scale time=-1 value=1\n",
+            "cube e1=0,0,2 e2=1,1,3\nkey scale time=0 value=1,2\n",
+            "cube e1=0,0,2 e2=1,1,3\nkey scale time=0 value=1 ease=wobble\n",
+            "cube e1=0,0,2 e2=1,1,3\nkey scale time=0 value=1 ease=bezier\n",
+        ] {
+            assert!(Scene::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn mesh_blocks_keep_vertices_and_colors() {
+        let scene = Scene::parse(SCENE).unwrap();
+        let Objects::Mesh(mesh) = &scene.objects[2] else { panic!("expected a mesh") };
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+        assert_eq!(mesh.colors.as_ref().unwrap()[1], [0, 255, 0]);
+    }
+
+    #[test]
+    fn malformed_lines_report_their_line() {
+        for (text, line) in [
+            ("cube e1=0,0,2\n", 1),
+            ("light position=0,0,1\nwidget center=0,0,0\n", 2),
+            ("sphere center=0,0,2 radius=1 steps=1000000000,16\n", 1),
+            ("sphere center=0,0,2 radius=1 steps=1024,1024\n", 1),
+            ("sphere center=0,0,2 radius=1 steps=2.5,16\n", 1),
+            ("cube e1=0,0,2 e2=1,1,NaN\n", 1),
+            ("mesh center=0,0,0\nv 0 0 NaN\n", 2),
+            ("mesh center=0,0,0\nv 0 inf 0\n", 2),
+            ("mesh center=0,0,0\nv 0 0 0 300 0 0\n", 2),
+            ("mesh center=0,0,0\nv 0 0 0\nf 0 0 -1\n", 3),
+            ("mesh center=0,0,0\nv 0 0 0\nf 0 0 1\n", 3),
+            ("v 0 0 0\n", 1),
+            ("cube e1=0,0,2 e2=1,1,3 mass=-1\n", 1),
+        ] {
+            match Scene::parse(text) {
+                Err(SceneError::Parse{ line: l, .. }) => assert_eq!(l, line, "{:?}", text),
+                _ => panic!("{:?} should not parse", text),
+            }
+        }
+    }
+}
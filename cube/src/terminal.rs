@@ -0,0 +1,536 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::drawing::Frame;
+
+/// Alternate screen, hidden cursor, SGR mouse reports of every motion and
+/// application keypad mode, so numpad keys can be told from the digits.
+const ENTER: &str = "\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h\x1b=\x1b[2J";
+/// Undoes `ENTER`, back to the screen as it was before.
+const LEAVE: &str = "\x1b[0m\x1b>\x1b[?1006l\x1b[?1003l\x1b[?25h\x1b[?1049l";
+
+/// Longest escape sequence waited for; anything longer is dropped as garbage.
+const MAX_SEQUENCE: usize = 32;
+
+/// How long to wait for the rest of a sequence after `ESC` before taking it as
+/// the Escape key.
+pub const ESCAPE_WAIT: Duration = Duration::from_millis(50);
+
+/// Brightness ramp of the ASCII palette, darkest first.
+const RAMP: &[u8] = b" .:-=+*#%@";
+
+/// How frames are turned into characters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    /// Upper half blocks in 24-bit color, the top pixel in front and the bottom
+    /// one behind, so each character cell shows two square pixels.
+    Truecolor,
+    /// Characters by brightness, for terminals without 24-bit color.
+    Ascii,
+}
+
+impl Palette {
+    /// Truecolor when `COLORTERM` says the terminal has it, else ASCII.
+    pub fn detect() -> Palette {
+        match std::env::var("COLORTERM") {
+            Ok(value) if value == "truecolor" || value == "24bit" => Palette::Truecolor,
+            _ => Palette::Ascii,
+        }
+    }
+}
+
+/// Input read from the terminal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TermEvent {
+    /// A key press, with the modifiers the terminal could tell and the character it types.
+    /// Terminals do not report releases.
+    Key{ key: VirtualKeyCode, ctrl: bool, shift: bool, text: Option<char> },
+    /// A character no key code stands for, e.g. `!`.
+    Text(char),
+    /// A mouse button pressed or released over a cell, counted from 0.
+    Button{ button: MouseButton, pressed: bool, column: u16, row: u16, ctrl: bool, shift: bool },
+    /// The pointer moved over a cell.
+    Pointer{ column: u16, row: u16 },
+    /// Wheel notches, positive away from the user.
+    Wheel(f32),
+}
+
+/// Puts the terminal in raw mode on the alternate screen while it lives.
+pub struct Terminal {
+    original: libc::termios,
+}
+
+impl Terminal {
+    /// Takes over the terminal on standard input and output, failing if either is not one.
+    pub fn open() -> io::Result<Terminal> {
+        // SAFETY: plain calls on the standard descriptors with buffers of the right types.
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0 || libc::isatty(libc::STDOUT_FILENO) == 0 {
+                return Err(io::Error::other("standard input and output must be a terminal"));
+            }
+            let mut original: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // No echo, line buffering or signal keys: Ctrl+C and Ctrl+Z arrive as keys.
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let terminal = Terminal{ original };
+            terminal.write(ENTER)?;
+            Ok(terminal)
+        }
+    }
+
+    /// Columns and rows of character cells.
+    pub fn size(&self) -> io::Result<(u16, u16)> {
+        // SAFETY: TIOCGWINSZ fills in a `winsize`.
+        unsafe {
+            let mut size: libc::winsize = std::mem::zeroed();
+            if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((size.ws_col, size.ws_row))
+        }
+    }
+
+    /// Waits up to `timeout` for input, or for ever given `None`, and returns the bytes that arrived.
+    pub fn read(&self, timeout: Option<Duration>) -> io::Result<Vec<u8>> {
+        // Rounded up, so waiting for a deadline does not wake just before it.
+        let millis = timeout.map_or(-1, |t| t.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32);
+        let mut poll = libc::pollfd{ fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        let mut buffer = [0u8; 4096];
+        // SAFETY: one `pollfd`, and `read` writes at most the buffer's length.
+        unsafe {
+            match libc::poll(&mut poll, 1, millis) {
+                0 => return Ok(Vec::new()),
+                n if n < 0 => {
+                    let err = io::Error::last_os_error();
+                    // A resize signal interrupts the wait; the caller looks at the size again.
+                    return if err.kind() == io::ErrorKind::Interrupted { Ok(Vec::new()) } else { Err(err) };
+                }
+                _ => {}
+            }
+            match libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) {
+                0 => Err(io::ErrorKind::UnexpectedEof.into()),
+                n if n < 0 => Err(io::Error::last_os_error()),
+                n => Ok(buffer[..n as usize].to_vec()),
+            }
+        }
+    }
+
+    pub fn write(&self, text: &str) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        out.write_all(text.as_bytes())?;
+        out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.write(LEAVE);
+        // SAFETY: puts back the settings read in `open`.
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Splits terminal input into events, holding on to an escape sequence cut off
+/// at the end of a read until the rest arrives or `flush` gives up on it.
+#[derive(Clone, Debug, Default)]
+pub struct Decoder {
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Decoder::default()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<TermEvent> {
+        self.pending.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+        while let Some((length, event)) = decode(&self.pending[start..]) {
+            events.extend(event);
+            start += length;
+        }
+        self.pending.drain(..start);
+        events
+    }
+
+    /// Whether a cut off sequence waits for more input.
+    pub fn waiting(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Called when no more input came within `ESCAPE_WAIT`: the `ESC` waiting for
+    /// the rest of a sequence was the Escape key, and what follows it was typed.
+    pub fn flush(&mut self) -> Vec<TermEvent> {
+        if self.pending.first() != Some(&0x1b) {
+            return Vec::new();
+        }
+        self.pending.remove(0);
+        let mut events = vec![key(VirtualKeyCode::Escape, 1)];
+        events.extend(self.feed(&[]));
+        events
+    }
+}
+
+/// The event at the start of `bytes` and how many bytes it takes, or `None`
+/// if there is nothing there or it is cut off.
+fn decode(bytes: &[u8]) -> Option<(usize, Option<TermEvent>)> {
+    use VirtualKeyCode as K;
+
+    match bytes {
+        // Escape alone, or the start of a sequence still on its way.
+        [] | [0x1b] => None,
+        [0x1b, 0x1b, ..] => Some((1, Some(key(K::Escape, 1)))),
+        [0x1b, b'[', rest @ ..] => csi(rest).map(|(length, event)| (length + 2, event)),
+        [0x1b, b'O'] => None,
+        [0x1b, b'O', c, ..] => Some((3, ss3(*c))),
+        // Alt with a key; nothing is bound to Alt, so the key goes through alone.
+        [0x1b, ..] => Some((1, None)),
+        [c, ..] => Some((1, byte(*c))),
+    }
+}
+
+/// A key with the modifiers of an xterm modifier parameter: one more than the bits
+/// shift 1, alt 2 and ctrl 4.
+fn key(key: VirtualKeyCode, modifiers: u16) -> TermEvent {
+    let bits = modifiers.saturating_sub(1);
+    TermEvent::Key{ key, ctrl: bits & 4 != 0, shift: bits & 1 != 0, text: None }
+}
+
+/// A control sequence after `ESC [`.
+fn csi(bytes: &[u8]) -> Option<(usize, Option<TermEvent>)> {
+    use VirtualKeyCode as K;
+
+    let Some(end) = bytes.iter().position(|b| (0x40..=0x7e).contains(b)) else {
+        return (bytes.len() > MAX_SEQUENCE).then_some((bytes.len(), None));
+    };
+    let length = end + 1;
+    let (body, last) = (&bytes[..end], bytes[end]);
+    if let Some(mouse) = body.strip_prefix(b"<") {
+        return Some((length, sgr_mouse(mouse, last == b'M')));
+    }
+    let params: Vec<u16> = std::str::from_utf8(body).unwrap_or("").split(';').map(|p| p.parse().unwrap_or(1)).collect();
+    let modifiers = params.get(1).copied().unwrap_or(1);
+    let code = match last {
+        b'~' => match params[0] {
+            1 | 7 => K::Home,
+            2 => K::Insert,
+            3 => K::Delete,
+            4 | 8 => K::End,
+            5 => K::PageUp,
+            6 => K::PageDown,
+            n @ 11..=15 => [K::F1, K::F2, K::F3, K::F4, K::F5][n as usize - 11],
+            n @ 17..=21 => [K::F6, K::F7, K::F8, K::F9, K::F10][n as usize - 17],
+            23 => K::F11,
+            24 => K::F12,
+            _ => return Some((length, None)),
+        },
+        b'Z' => return Some((length, Some(key(K::Tab, 2)))),
+        c => match letter_key(c) {
+            Some(code) => code,
+            None => return Some((length, None)),
+        },
+    };
+    Some((length, Some(key(code, modifiers))))
+}
+
+/// The key of a sequence after `ESC O`: arrows, Home, End and F1-F4, or the
+/// numpad in application keypad mode.
+fn ss3(c: u8) -> Option<TermEvent> {
+    use VirtualKeyCode as K;
+
+    const NUMPAD: [VirtualKeyCode; 10] = [
+        K::Numpad0, K::Numpad1, K::Numpad2, K::Numpad3, K::Numpad4,
+        K::Numpad5, K::Numpad6, K::Numpad7, K::Numpad8, K::Numpad9,
+    ];
+    let code = match c {
+        b'M' => K::NumpadEnter,
+        b'k' => K::NumpadAdd,
+        b'm' => K::NumpadSubtract,
+        b'p'..=b'y' => NUMPAD[(c - b'p') as usize],
+        c => letter_key(c)?,
+    };
+    Some(key(code, 1))
+}
+
+/// Keys named by the final letter of their sequence.
+fn letter_key(c: u8) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode as K;
+
+    Some(match c {
+        b'A' => K::Up,
+        b'B' => K::Down,
+        b'C' => K::Right,
+        b'D' => K::Left,
+        b'H' => K::Home,
+        b'F' => K::End,
+        b'P' => K::F1,
+        b'Q' => K::F2,
+        b'R' => K::F3,
+        b'S' => K::F4,
+        _ => return None,
+    })
+}
+
+/// An SGR mouse report `button;column;row`, counted from 1.
+fn sgr_mouse(body: &[u8], pressed: bool) -> Option<TermEvent> {
+    let params: Vec<u16> = std::str::from_utf8(body).ok()?.split(';').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let [code, column, row] = params[..] else {
+        return None;
+    };
+    let (column, row) = (column.saturating_sub(1), row.saturating_sub(1));
+    if code & 64 != 0 {
+        return pressed.then_some(TermEvent::Wheel(if code & 1 == 0 { 1.0 } else { -1.0 }));
+    }
+    if code & 32 != 0 {
+        return Some(TermEvent::Pointer{ column, row });
+    }
+    let button = match code & 3 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        2 => MouseButton::Right,
+        _ => return None,
+    };
+    Some(TermEvent::Button{ button, pressed, column, row, ctrl: code & 16 != 0, shift: code & 4 != 0 })
+}
+
+/// A key typed as a single byte.
+fn byte(c: u8) -> Option<TermEvent> {
+    use VirtualKeyCode as K;
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        K::A, K::B, K::C, K::D, K::E, K::F, K::G, K::H, K::I, K::J, K::K, K::L, K::M,
+        K::N, K::O, K::P, K::Q, K::R, K::S, K::T, K::U, K::V, K::W, K::X, K::Y, K::Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] = [K::Key0, K::Key1, K::Key2, K::Key3, K::Key4, K::Key5, K::Key6, K::Key7, K::Key8, K::Key9];
+
+    let text = Some(c as char);
+    let (key, ctrl, shift) = match c {
+        b'\r' | b'\n' => return Some(TermEvent::Key{ key: K::Return, ctrl: false, shift: false, text: None }),
+        b'\t' => (K::Tab, false, false),
+        0x7f | 0x08 => (K::Back, false, false),
+        0 => (K::Space, true, false),
+        1..=26 => (LETTERS[(c - 1) as usize], true, false),
+        b'a'..=b'z' => (LETTERS[(c - b'a') as usize], false, false),
+        b'A'..=b'Z' => (LETTERS[(c - b'A') as usize], false, true),
+        b'0'..=b'9' => (DIGITS[(c - b'0') as usize], false, false),
+        b' ' => (K::Space, false, false),
+        b',' => (K::Comma, false, false),
+        b'.' => (K::Period, false, false),
+        b'-' => (K::Minus, false, false),
+        b'=' => (K::Equals, false, false),
+        b'/' => (K::Slash, false, false),
+        b';' => (K::Semicolon, false, false),
+        b'\'' => (K::Apostrophe, false, false),
+        b'[' => (K::LBracket, false, false),
+        b']' => (K::RBracket, false, false),
+        b'<' => (K::Comma, false, true),
+        b'>' => (K::Period, false, true),
+        b'_' => (K::Minus, false, true),
+        b'+' => (K::Equals, false, true),
+        b'?' => (K::Slash, false, true),
+        b':' => (K::Semicolon, false, true),
+        b'"' => (K::Apostrophe, false, true),
+        b'{' => (K::LBracket, false, true),
+        b'}' => (K::RBracket, false, true),
+        0x21..=0x7e => return Some(TermEvent::Text(c as char)),
+        // Other control bytes, and UTF-8, which no binding or typed value needs.
+        _ => return None,
+    };
+    Some(TermEvent::Key{ key, ctrl, shift, text: text.filter(|c| !c.is_control()) })
+}
+
+/// What one character cell shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    glyph: char,
+    foreground: [u8; 3],
+    background: [u8; 3],
+}
+
+/// Draws frames into the terminal, keeping what it drew last so that only the
+/// cells that changed are sent again.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    palette: Palette,
+    columns: u32,
+    cells: Vec<Cell>,
+    status: String,
+}
+
+impl Screen {
+    pub fn new(palette: Palette) -> Self {
+        Screen{ palette, columns: 0, cells: Vec::new(), status: String::new() }
+    }
+
+    /// Size of the frame that fills a terminal of `columns`x`rows` cells, less
+    /// the bottom row, which is kept for the status line.
+    pub fn frame_size(columns: u16, rows: u16) -> (u32, u32) {
+        (columns.max(1) as u32, rows.saturating_sub(1).max(1) as u32 * 2)
+    }
+
+    /// Appends to `out` what brings the terminal from the last frame drawn to
+    /// `frame`, with `status` on the row below it.
+    pub fn draw(&mut self, frame: &Frame, status: &str, out: &mut String) {
+        let (columns, rows) = (frame.width, frame.height.div_ceil(2));
+        let count = (columns * rows) as usize;
+        if self.columns != columns || self.cells.len() != count {
+            // After a resize the old cells mean nothing; start from a blank screen.
+            out.push_str("\x1b[0m\x1b[2J");
+            let blank = Cell{ glyph: ' ', foreground: [0; 3], background: [0; 3] };
+            self.columns = columns;
+            self.cells = vec![blank; count];
+            self.status.clear();
+        }
+
+        let pixel = |x: u32, y: u32| -> [u8; 3] {
+            if y >= frame.height {
+                return [0; 3];
+            }
+            let i = ((y * frame.width + x) * 4) as usize;
+            [frame.color[i], frame.color[i + 1], frame.color[i + 2]]
+        };
+        // Where the terminal's cursor is and which colors it draws with; unknown at first.
+        let mut cursor = None;
+        let mut foreground = None;
+        let mut background = None;
+        for row in 0..rows {
+            for column in 0..columns {
+                let (top, bottom) = (pixel(column, row * 2), pixel(column, row * 2 + 1));
+                let cell = match self.palette {
+                    Palette::Truecolor if top == bottom => Cell{ glyph: ' ', foreground: [0; 3], background: top },
+                    Palette::Truecolor => Cell{ glyph: '▀', foreground: top, background: bottom },
+                    Palette::Ascii => {
+                        let light = (luminance(top) + luminance(bottom)) / 2.0;
+                        let glyph = RAMP[(light * (RAMP.len() - 1) as f32).round() as usize] as char;
+                        Cell{ glyph, foreground: [0; 3], background: [0; 3] }
+                    }
+                };
+                let index = (row * columns + column) as usize;
+                if self.cells[index] == cell {
+                    continue;
+                }
+                self.cells[index] = cell;
+                if cursor != Some((column, row)) {
+                    let _ = write!(out, "\x1b[{};{}H", row + 1, column + 1);
+                }
+                if self.palette == Palette::Truecolor {
+                    // A blank cell only shows its background.
+                    if cell.glyph != ' ' && foreground != Some(cell.foreground) {
+                        let [r, g, b] = cell.foreground;
+                        let _ = write!(out, "\x1b[38;2;{};{};{}m", r, g, b);
+                        foreground = Some(cell.foreground);
+                    }
+                    if background != Some(cell.background) {
+                        let [r, g, b] = cell.background;
+                        let _ = write!(out, "\x1b[48;2;{};{};{}m", r, g, b);
+                        background = Some(cell.background);
+                    }
+                }
+                out.push(cell.glyph);
+                cursor = Some((column + 1, row));
+            }
+        }
+
+        let status: String = status.chars().take(columns as usize).collect();
+        if status != self.status {
+            let _ = write!(out, "\x1b[{};1H\x1b[0m{}\x1b[K", rows + 1, status);
+            self.status = status;
+        } else if !out.is_empty() {
+            out.push_str("\x1b[0m");
+        }
+    }
+}
+
+/// Perceived brightness from 0 to 1.
+fn luminance([r, g, b]: [u8; 3]) -> f32 {
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use VirtualKeyCode as K;
+
+    fn plain(key: VirtualKeyCode) -> TermEvent {
+        TermEvent::Key{ key, ctrl: false, shift: false, text: None }
+    }
+
+    #[test]
+    fn sequences_split_across_reads() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.feed(b"a\x1b"), [TermEvent::Key{ key: K::A, ctrl: false, shift: false, text: Some('a') }]);
+        assert!(decoder.waiting());
+        assert!(decoder.feed(b"[1;5").is_empty());
+        assert_eq!(decoder.feed(b"A"), [TermEvent::Key{ key: K::Up, ctrl: true, shift: false, text: None }]);
+        assert!(!decoder.waiting());
+
+        // SS3, in application keypad mode.
+        assert!(decoder.feed(b"\x1bO").is_empty());
+        assert_eq!(decoder.feed(b"q\x1bOM"), [plain(K::Numpad1), plain(K::NumpadEnter)]);
+
+        // SGR mouse, cut off inside its parameters.
+        assert!(decoder.feed(b"\x1b[<0;1").is_empty());
+        let events = decoder.feed(b"2;5M\x1b[<35;3;4M\x1b[<65;1;1M\x1b[<0;12;5m");
+        assert_eq!(events, [
+            TermEvent::Button{ button: MouseButton::Left, pressed: true, column: 11, row: 4, ctrl: false, shift: false },
+            TermEvent::Pointer{ column: 2, row: 3 },
+            TermEvent::Wheel(-1.0),
+            TermEvent::Button{ button: MouseButton::Left, pressed: false, column: 11, row: 4, ctrl: false, shift: false },
+        ]);
+        assert!(decoder.flush().is_empty(), "nothing was waiting");
+    }
+
+    #[test]
+    fn escape_is_told_apart_once_input_stops() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.feed(b"\x1b").is_empty());
+        assert_eq!(decoder.flush(), [plain(K::Escape)]);
+        assert!(!decoder.waiting());
+
+        // Escape, then a `[` typed too slowly to start a sequence.
+        assert!(decoder.feed(b"\x1b[").is_empty());
+        assert_eq!(decoder.flush(), [plain(K::Escape), TermEvent::Key{ key: K::LBracket, ctrl: false, shift: false, text: Some('[') }]);
+
+        // Escape twice in one read needs no wait for the first.
+        assert_eq!(decoder.feed(b"\x1b\x1b"), [plain(K::Escape)]);
+        assert_eq!(decoder.flush(), [plain(K::Escape)]);
+    }
+
+    #[test]
+    fn draw_sends_only_changed_cells() {
+        let mut screen = Screen::new(Palette::Ascii);
+        let mut frame = Frame::new(4, 4);
+        let mut out = String::new();
+        screen.draw(&frame, "ok", &mut out);
+        // Black cells are blank already, so only the status line goes out.
+        assert!(out.starts_with("\x1b[0m\x1b[2J"), "first frame does not clear the screen");
+        assert!(out.ends_with("\x1b[3;1H\x1b[0mok\x1b[K"), "{:?}", out);
+
+        out.clear();
+        screen.draw(&frame, "ok", &mut out);
+        assert_eq!(out, "", "an unchanged frame sends nothing");
+
+        // White in the bottom pixel of the second cell of the second row: half lit.
+        frame.color[(3 * 4 + 1) * 4..(3 * 4 + 1) * 4 + 3].fill(255);
+        out.clear();
+        screen.draw(&frame, "ok", &mut out);
+        assert_eq!(out, "\x1b[2;2H+\x1b[0m");
+
+        // A new size starts over.
+        out.clear();
+        screen.draw(&Frame::new(5, 4), "ok", &mut out);
+        assert!(out.starts_with("\x1b[0m\x1b[2J") && out.ends_with("ok\x1b[K"));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::geometry::*;
+
+/// Shift multiplies speeds by this, for fine adjustments.
+pub const FINE: f32 = 0.1;
+
+/// Ctrl multiplies speeds by this, for coarse adjustments.
+pub const COARSE: f32 = 10.0;
+
+/// How fast the selection moves, turns or scales while a key is held, per second.
+#[derive(Clone, Copy, Debug)]
+pub struct Speeds {
+    /// World units per second.
+    pub translate: f32,
+    /// Radians per second.
+    pub rotate: f32,
+    pub scale: f32,
+}
+
+impl Default for Speeds {
+    fn default() -> Self {
+        Speeds { translate: 0.6, rotate: 70f32.to_radians(), scale: 0.3 }
+    }
+}
+
+impl Speeds {
+    /// The speeds for the held modifiers; Ctrl wins over Shift.
+    pub fn with_modifiers(self, fine: bool, coarse: bool) -> Speeds {
+        let factor = if coarse {
+            COARSE
+        } else if fine {
+            FINE
+        } else {
+            1.0
+        };
+        Speeds { translate: self.translate * factor, rotate: self.rotate * factor, scale: self.scale * factor }
+    }
+}
+
+/// Snapping of positions to a grid and of angles to fixed increments.
+#[derive(Clone, Copy, Debug)]
+pub struct Snap {
+    pub enabled: bool,
+    /// Grid spacing in world units.
+    pub grid: f32,
+    /// Angle increment in radians.
+    pub angle: f32,
+}
+
+impl Default for Snap {
+    fn default() -> Self {
+        Snap { enabled: false, grid: 0.25, angle: 15f32.to_radians() }
+    }
+}
+
+impl Snap {
+    /// Nearest grid point to `p`, or `p` itself when snapping is off.
+    pub fn position(&self, p: Point3D) -> Point3D {
+        if !self.enabled {
+            return p;
+        }
+        Point3D::new(nearest(p.x, self.grid), nearest(p.y, self.grid), nearest(p.z, self.grid))
+    }
+
+    /// Nearest angle increment to `angle`, or `angle` itself when snapping is off.
+    pub fn angle(&self, angle: f32) -> f32 {
+        if !self.enabled {
+            return angle;
+        }
+        nearest(angle, self.angle)
+    }
+
+    /// Where a key press moving by `delta` takes an object at `p`. With snapping on,
+    /// that is the next grid line along every axis `delta` moves on, or as many lines
+    /// further as `delta` has whole grid steps.
+    pub fn step_position(&self, p: Point3D, delta: Point3D) -> Point3D {
+        if !self.enabled {
+            return p + delta;
+        }
+        Point3D::new(
+            next_line(p.x, delta.x, self.grid),
+            next_line(p.y, delta.y, self.grid),
+            next_line(p.z, delta.z, self.grid),
+        )
+    }
+
+    /// Where a key press turning by `delta` takes `angle`; the next increment when snapping.
+    pub fn step_angle(&self, angle: f32, delta: f32) -> f32 {
+        if !self.enabled {
+            return angle + delta;
+        }
+        next_line(angle, delta, self.angle)
+    }
+
+    /// Splits `amount` into the whole increments of `step` to apply now and the
+    /// remainder to carry over, e.g. between updates of a drag.
+    pub fn quantize(&self, amount: f32, step: f32) -> (f32, f32) {
+        if !self.enabled || step <= 0.0 {
+            return (amount, 0.0);
+        }
+        let applied = (amount / step).trunc() * step;
+        (applied, amount - applied)
+    }
+}
+
+fn nearest(value: f32, step: f32) -> f32 {
+    if step <= 0.0 {
+        return value;
+    }
+    (value / step).round() * step
+}
+
+/// The multiple of `step` that `delta` moves `value` to, counting the first line
+/// past `value` as one step, so a value already on a line moves a whole step and a
+/// `delta` shorter than `step` still reaches the next line; `value` when `delta` is zero.
+fn next_line(value: f32, delta: f32, step: f32) -> f32 {
+    if delta == 0.0 || step <= 0.0 {
+        return value;
+    }
+    // Tolerance against values a rounding error short of a line.
+    let units = value / step;
+    let lines = (delta.abs() / step).trunc().max(1.0);
+    if delta > 0.0 {
+        ((units + 1e-3).floor() + lines) * step
+    } else {
+        ((units - 1e-3).ceil() - lines) * step
+    }
+}
+
+/// A value typed for the selected objects, e.g. `pos 0,1,3`, `rot 90,45` (degrees)
+/// or `scale 1.5`.
+#[derive(Clone, Copy, Debug)]
+pub enum Exact {
+    Position(Point3D),
+    /// `angle_x` and `angle_y`, in radians.
+    Angles(f32, f32),
+    Scale(f32),
+}
+
+impl Exact {
+    pub fn parse(text: &str) -> Result<Exact, String> {
+        let text = text.trim();
+        let (name, values) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let numbers = values.split(',')
+            .map(|v| v.trim().parse::<f32>().ok().filter(|n| n.is_finite()))
+            .collect::<Option<Vec<f32>>>();
+        match (name, numbers.as_deref()) {
+            ("pos" | "p", Some(&[x, y, z])) => Ok(Exact::Position(Point3D::new(x, y, z))),
+            ("rot" | "r", Some(&[x, y])) => Ok(Exact::Angles(x.to_radians(), y.to_radians())),
+            ("scale" | "s", Some(&[s])) => Ok(Exact::Scale(s)),
+            ("pos" | "p", _) => Err(format!("bad position '{}', expected x,y,z", values)),
+            ("rot" | "r", _) => Err(format!("bad rotation '{}', expected x,y in degrees", values)),
+            ("scale" | "s", _) => Err(format!("bad scale '{}', expected a number", values)),
+            _ => Err(format!("unknown value '{}', expected pos, rot or scale", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on() -> Snap {
+        Snap { enabled: true, grid: 0.5, angle: 0.25 }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn parses_typed_values() {
+        let Ok(Exact::Position(p)) = Exact::parse(" pos 1, -2.5,3 ") else { panic!("position not parsed") };
+        assert_eq!((p.x, p.y, p.z), (1.0, -2.5, 3.0));
+        let Ok(Exact::Angles(x, y)) = Exact::parse("r 90,-45") else { panic!("rotation not parsed") };
+        assert!(close(x, std::f32::consts::FRAC_PI_2) && close(y, -std::f32::consts::FRAC_PI_4));
+        assert!(matches!(Exact::parse("scale 1.5"), Ok(Exact::Scale(s)) if s == 1.5));
+        assert!(matches!(Exact::parse("s 2"), Ok(Exact::Scale(s)) if s == 2.0));
+    }
+
+    #[test]
+    fn rejects_bad_arity_and_non_finite_values() {
+        for text in ["pos 1,2", "pos 1,2,3,4", "rot 90", "rot 1,2,3", "scale 1,2", "scale", "pos", "pos a,b,c"] {
+            assert!(Exact::parse(text).is_err(), "'{}' parsed", text);
+        }
+        for text in ["pos nan,0,0", "rot inf,0", "scale -inf", "scale NaN", "pos 1e39,0,0"] {
+            assert!(Exact::parse(text).is_err(), "'{}' parsed", text);
+        }
+        assert!(Exact::parse("size 2").unwrap_err().contains("unknown value 'size'"));
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_grid_point_and_angle() {
+        let p = on().position(Point3D::new(0.2, 0.3, -0.8));
+        assert_eq!((p.x, p.y, p.z), (0.0, 0.5, -1.0));
+        assert!(close(on().angle(0.3), 0.25));
+        let off = Snap::default();
+        assert_eq!(off.position(Point3D::new(0.2, 0.3, 0.4)).x, 0.2);
+        assert_eq!(off.angle(0.3), 0.3);
+    }
+
+    #[test]
+    fn steps_to_the_next_lines_along_the_delta() {
+        let snap = on();
+        // Off the grid, a small step reaches the next line either way.
+        let p = snap.step_position(Point3D::new(0.2, 0.2, 0.2), Point3D::new(0.1, -0.1, 0.0));
+        assert_eq!((p.x, p.y, p.z), (0.5, 0.0, 0.2));
+        // On a line, it moves a whole step.
+        assert!(close(snap.step_position(Point3D::new(0.5, 0.0, 0.0), Point3D::new(0.01, 0.0, 0.0)).x, 1.0));
+        // A line a rounding error away counts as the one it is on.
+        assert!(close(snap.step_position(Point3D::new(0.4999999, 0.0, 0.0), Point3D::new(0.01, 0.0, 0.0)).x, 1.0));
+        // Deltas of several grid steps move that many lines.
+        assert!(close(snap.step_position(Point3D::new(0.2, 0.0, 0.0), Point3D::new(1.6, 0.0, 0.0)).x, 1.5));
+        assert!(close(snap.step_position(Point3D::new(0.5, 0.0, 0.0), Point3D::new(-1.0, 0.0, 0.0)).x, -0.5));
+
+        assert!(close(snap.step_angle(0.1, 0.01), 0.25));
+        assert!(close(snap.step_angle(0.25, -0.6), -0.25));
+        let p = Snap::default().step_position(Point3D::new(0.2, 0.0, 0.0), Point3D::new(0.1, 0.0, 0.0));
+        assert!(close(p.x, 0.3));
+        assert!(close(Snap::default().step_angle(0.1, 0.01), 0.11));
+    }
+
+    #[test]
+    fn quantize_carries_the_remainder() {
+        let (applied, carry) = on().quantize(1.3, 0.5);
+        assert!(close(applied, 1.0) && close(carry, 0.3));
+        let (applied, carry) = on().quantize(-0.7, 0.5);
+        assert!(close(applied, -0.5) && close(carry, -0.2));
+        assert_eq!(on().quantize(0.2, 0.5), (0.0, 0.2));
+        assert_eq!(on().quantize(0.7, 0.0), (0.7, 0.0));
+        assert_eq!(Snap::default().quantize(0.7, 0.5), (0.7, 0.0));
+    }
+}
@@ -0,0 +1,619 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use winit::event::VirtualKeyCode;
+
+use cube::animation::{Keyframe, Timeline, SCRUB_STEP};
+use cube::config::{Action, Config, Input};
+use cube::drawing::{Frame, RenderMode};
+use cube::gizmo::{Drag, Gizmo, GizmoMode};
+use cube::history::{Edit, EditKind, History};
+use cube::hud::{draw_hud, FpsCounter};
+use cube::motion::{Clock, Motion};
+use cube::geometry::*;
+use cube::objec::{Cube, Objects, Plane, Pyramid, Sphere, Tessellation, Transformable, Triangle};
+use cube::physics::{self, Body};
+use cube::particles::Emitter;
+use cube::primitives::{Capsule, Cone, Cylinder, Disk, Torus};
+use cube::raytrace::{Progressive, Tracer};
+use cube::polyhedra::Polyhedron;
+use cube::recording::{record, turntable_center, Recording, Shot, DEFAULT_FPS};
+use cube::render::{draw_overlays, render_scene, Overlays};
+use cube::scene::Scene;
+use cube::transform::{Exact, Snap};
+use cube::gltf;
+
+use crate::cli::Options;
+
+/// Conway notation spawned by successive presses of V.
+const POLYHEDRA: [&str; 11] = ["T", "C", "O", "D", "I", "tI", "aC", "eD", "sC", "kD", "dtI"];
+
+/// Colors the `cycle_color` action steps through.
+const PALETTE: [[u8; 3]; 8] = [
+    [220, 60, 60], [230, 150, 50], [230, 210, 60], [100, 200, 50],
+    [60, 160, 220], [100, 90, 220], [200, 90, 200], [200, 200, 200],
+];
+
+/// How often a background recording is checked on when nothing else wakes the viewer.
+const RECORDING_POLL: Duration = Duration::from_millis(100);
+
+/// A file named after the scene with `extension`, numbered past any that already exist
+/// so exports never overwrite earlier ones.
+fn export_path(scene_path: &str, extension: &str) -> PathBuf {
+    let stem = Path::new(scene_path).with_extension("");
+    (1..)
+        .map(|n| match n {
+            1 => PathBuf::from(format!("{}.{}", stem.display(), extension)),
+            n => PathBuf::from(format!("{}-{}.{}", stem.display(), n, extension)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// When the viewer next has something to do without new input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wake {
+    /// Held keys, playback, the simulation or particles need a tick then.
+    At(Instant),
+    /// Ray tracing has more passes to refine the frame with.
+    Now,
+    /// Nothing changes until the next input.
+    Idle,
+}
+
+/// The scene being edited and everything the controls act on, shared by the
+/// window and the terminal front ends, which feed it input and show its frame.
+pub struct Viewer{
+    pub scene: Scene,
+    /// The last frame drawn, with the object IDs picking reads.
+    pub frame: Frame,
+    config: Config,
+    title: String,
+    mode: RenderMode,
+    /// Size of turntable recordings, and of the span the light follows the pointer across.
+    size: (u32, u32),
+    scene_path: String,
+    polyhedron_num: usize,
+    z_move: f32,
+    overlays: Overlays,
+    drag: Option<Drag>,
+    history: History,
+    clipboard: Vec<Objects>,
+    snap: Snap,
+    // Move, turn and scale actions whose key or button is down, by that input.
+    held: HashMap<Input, Action>,
+    motion: Motion,
+    clock: Clock,
+    was_ticking: bool,
+    timeline: Timeline,
+    simulating: bool,
+    // The objects as they were when the simulation was started, for `reset_physics`,
+    // and the history's layout then; it is dropped once objects are added, removed or reordered.
+    rest: Option<(u64, Vec<Objects>)>,
+    // Text typed after Enter, for an exact position, rotation or scale.
+    prompt: Option<String>,
+    // Frame pixel under the pointer, while it is over the frame.
+    cursor: Option<(f32, f32)>,
+    /// Modifiers held with the input being handled, and by held keys.
+    pub ctrl: bool,
+    pub shift: bool,
+    pub show_hud: bool,
+    /// Whether the HUD is drawn into the frame; front ends too coarse to read it show `status` instead.
+    pub hud_in_frame: bool,
+    fps: FpsCounter,
+    frame_rate: f32,
+    tracer: Tracer,
+    // The ray traced image being refined, while ray tracing instead of rasterizing.
+    tracing: Option<Progressive>,
+    // Whether the frame is out of date; it is redrawn by the next `update`.
+    dirty: bool,
+    // The turntable being written in the background, and the file it goes to.
+    recording: Option<(PathBuf, JoinHandle<io::Result<()>>)>,
+    // Failed saves, loads, exports and typed values, for the front end to show.
+    errors: Vec<String>,
+    /// Set by the `quit` action for the front end to close the viewer.
+    pub quit: bool,
+}
+
+impl Viewer{
+    pub fn new(options: &Options, scene: Scene) -> Self{
+        let mut frame = Frame::new(options.width, options.height);
+        frame.enable_ids();
+        let config = options.config.clone();
+        Viewer{
+            z_move: scene.light.z,
+            scene,
+            frame,
+            title: options.title.clone(),
+            mode: options.mode,
+            size: (options.width, options.height),
+            scene_path: options.scene.clone().unwrap_or_else(|| String::from("untitled.scene")),
+            polyhedron_num: 0,
+            overlays: Overlays {
+                grid: true,
+                axes: true,
+                orientation: true,
+                gizmo: Some(GizmoMode::Translate),
+                ..Overlays::default()
+            },
+            drag: None,
+            history: History::new(),
+            clipboard: Vec::new(),
+            snap: config.snap,
+            held: HashMap::new(),
+            motion: Motion::new(),
+            clock: Clock::new(),
+            was_ticking: false,
+            timeline: Timeline{ time: options.time.unwrap_or(0.0), ..Timeline::default() },
+            simulating: false,
+            rest: None,
+            prompt: None,
+            cursor: None,
+            ctrl: false,
+            shift: false,
+            show_hud: true,
+            hud_in_frame: true,
+            fps: FpsCounter::new(),
+            frame_rate: 0.0,
+            tracer: options.tracer.unwrap_or_default(),
+            tracing: options.tracer.map(Progressive::new),
+            dirty: true,
+            recording: None,
+            errors: Vec::new(),
+            quit: false,
+            config,
+        }
+    }
+
+    /// Draws into a frame of this size from now on.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.frame = Frame::new(width, height);
+        self.frame.enable_ids();
+        self.drag = None;
+        self.overlays.handle = None;
+        self.overlays.hover = None;
+        self.cursor = None;
+        self.dirty = true;
+    }
+
+    /// The errors since the last call, oldest first.
+    pub fn take_errors(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Whether keys are editing a typed value rather than acting on the scene.
+    pub fn prompting(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    /// A key going down: typed value editing while prompting, else whatever it is bound to.
+    pub fn key(&mut self, key: VirtualKeyCode) {
+        if self.prompt.is_none() {
+            self.press(Input::Key(key));
+            return;
+        }
+        // The characters themselves arrive through `type_char`.
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                match self.prompt.take().map(|text| Exact::parse(&text)) {
+                    Some(Ok(exact)) => {
+                        self.history.seal();
+                        self.history.execute(&mut self.scene, Edit::Set(exact));
+                        self.history.seal();
+                    }
+                    Some(Err(err)) => self.errors.push(err.to_string()),
+                    None => {}
+                }
+            }
+            VirtualKeyCode::Escape => self.prompt = None,
+            VirtualKeyCode::Back => {
+                if let Some(text) = &mut self.prompt {
+                    text.pop();
+                }
+            }
+            _ => {}
+        }
+        self.dirty = true;
+    }
+
+    /// Adds a typed character to the value being entered, if there is one.
+    pub fn type_char(&mut self, c: char) {
+        if let Some(text) = self.prompt.as_mut().filter(|_| !c.is_control()) {
+            text.push(c);
+        }
+    }
+
+    /// A key or button going down, carrying out the action bound to it with the current modifiers.
+    pub fn press(&mut self, input: Input) {
+        let Some(action) = self.config.bindings.action(input, self.ctrl, self.shift) else {
+            return;
+        };
+        if action.is_held() {
+            // Key repeat presses again; only the first press starts the motion.
+            if let Entry::Vacant(entry) = self.held.entry(input) {
+                self.motion.start(action, self.snap);
+                entry.insert(action);
+            }
+            return;
+        }
+        self.perform(action);
+        self.dirty = true;
+    }
+
+    /// A key or button going up. Whichever button started a drag, letting go of a button ends it.
+    pub fn release(&mut self, input: Input) {
+        self.held.remove(&input);
+        if matches!(input, Input::Mouse(_)) && self.drag.is_some() {
+            self.drag = None;
+            self.history.seal();
+            self.overlays.handle = None;
+            self.dirty = true;
+        }
+    }
+
+    /// Lets go of every held key, e.g. when their releases will not be reported.
+    pub fn release_all(&mut self) {
+        self.held.clear();
+    }
+
+    /// Moves the light nearer or further by this many wheel notches.
+    pub fn wheel(&mut self, lines: f32) {
+        self.z_move += lines * self.config.wheel_step;
+        self.z_move = self.z_move.clamp(-10.0, 10.0);
+    }
+
+    /// The pointer moved to frame pixel (`x`, `y`); off the frame, `inside` is false
+    /// and the pixel is the nearest one on it, so drags keep following the pointer.
+    pub fn point(&mut self, x: u32, y: u32, inside: bool) {
+        let (width, height) = (self.frame.width, self.frame.height);
+        let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+        self.cursor = inside.then_some((x, y));
+        let ray = self.scene.camera.ray(x, y, width, height);
+        match &mut self.drag {
+            Some(drag) => {
+                let kind = match drag.gizmo.mode {
+                    GizmoMode::Translate => EditKind::Move,
+                    GizmoMode::Rotate => EditKind::Rotate,
+                    GizmoMode::Scale => EditKind::Scale,
+                };
+                let selection = self.scene.selection();
+                self.history.modify(&mut self.scene, kind, &selection, |scene| drag.update(scene, &ray));
+                self.overlays.hover = None;
+            }
+            None => {
+                self.overlays.handle = self.overlays.gizmo.and_then(|mode| Gizmo::new(&self.scene, mode, width))
+                    .and_then(|g| g.handle_at(&self.scene.camera, x, y, width, height));
+                self.overlays.hover = self.frame.object_at(x as u32, y as u32).filter(|_| self.overlays.handle.is_none());
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Puts the light behind the pointer, at (`x`, `y`) as fractions of the view's
+    /// width and height. A keyed light follows its keys instead.
+    pub fn aim_light(&mut self, x: f32, y: f32) {
+        if self.scene.light_track.is_empty() {
+            let (width, height) = (self.size.0 as f32, self.size.1 as f32);
+            self.scene.light = Point3D::new(-(x - 0.5) * width, -(y - 0.5) * height, self.z_move);
+        }
+        self.dirty = true;
+    }
+
+    fn ticking(&self) -> bool {
+        !self.held.is_empty() || self.timeline.playing || self.simulating || self.scene.has_emitters()
+    }
+
+    /// Runs the ticks due by now and redraws the frame if it is out of date;
+    /// returns whether it was redrawn.
+    pub fn update(&mut self) -> bool {
+        self.finish_recording();
+        if self.held.is_empty() {
+            self.motion.stop();
+        }
+        let ticking = self.ticking();
+        if ticking && !self.was_ticking {
+            // Count from now, not from whenever the last tick ran.
+            self.clock.reset(Instant::now());
+        }
+        self.was_ticking = ticking;
+        if ticking {
+            let speeds = self.config.speeds.with_modifiers(self.shift, self.ctrl);
+            for _ in 0..self.clock.advance(Instant::now()) {
+                let dt = self.clock.dt();
+                for edit in self.motion.update(self.held.values().copied(), speeds, self.snap, dt) {
+                    self.history.execute(&mut self.scene, edit);
+                }
+                if self.timeline.playing {
+                    self.timeline.advance(dt, self.scene.animation_end());
+                    self.scene.pose(self.timeline.time);
+                } else {
+                    self.scene.run_emitters(dt);
+                }
+                if self.simulating {
+                    physics::step(&mut self.scene, &self.config.physics, dt);
+                }
+                self.dirty = true;
+            }
+        }
+        let refining = self.tracing.as_ref().is_some_and(|t| !t.done());
+        if !self.dirty && !refining {
+            return false;
+        }
+        match &mut self.tracing {
+            Some(tracing) => {
+                if self.dirty {
+                    tracing.reset();
+                }
+                tracing.refine(&self.scene, &mut self.frame);
+            }
+            None => render_scene(&self.scene, self.mode, &mut self.frame),
+        }
+        draw_overlays(&self.scene, self.overlays, &mut self.frame);
+        if self.show_hud {
+            self.frame_rate = self.fps.tick();
+            if self.hud_in_frame {
+                draw_hud(&self.scene, self.frame_rate, &self.timeline, self.simulating, &mut self.frame);
+            }
+        }
+        self.dirty = false;
+        true
+    }
+
+    /// When `update` next has work to do if no input arrives.
+    pub fn wake(&self) -> Wake {
+        // Held keys, playback, the simulation and particles need the next tick; anything else only changes on an input.
+        if self.ticking() {
+            Wake::At(self.clock.deadline())
+        } else if self.dirty || self.tracing.as_ref().is_some_and(|t| !t.done()) {
+            Wake::Now
+        } else if self.recording.is_some() {
+            Wake::At(Instant::now() + RECORDING_POLL)
+        } else {
+            Wake::Idle
+        }
+    }
+
+    /// Collects the background recording once it is done, keeping its error if it failed.
+    fn finish_recording(&mut self) {
+        if !self.recording.as_ref().is_some_and(|(_, handle)| handle.is_finished()) {
+            return;
+        }
+        if let Some((path, handle)) = self.recording.take() {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => self.errors.push(format!("{}: {}", path.display(), err)),
+                Err(_) => self.errors.push(format!("{}: recording failed", path.display())),
+            }
+        }
+    }
+
+    /// The title, showing whether snapping is on, a recording is running and the value being typed.
+    pub fn title(&self) -> String {
+        let mut text = self.title.clone();
+        if self.snap.enabled {
+            text.push_str(" [snap]");
+        }
+        if self.recording.is_some() {
+            text.push_str(" [recording]");
+        }
+        if let Some(prompt) = &self.prompt {
+            text.push_str(&format!(" > {}_  (pos x,y,z | rot x,y | scale s)", prompt));
+        }
+        text
+    }
+
+    /// The title followed by the HUD's figures on one line, for front ends that
+    /// cannot show the HUD drawn into the frame.
+    pub fn status(&self) -> String {
+        let mut text = self.title();
+        if !self.show_hud {
+            return text;
+        }
+        text.push_str(&format!("  |  {:.0} fps  {} objects", self.frame_rate, self.scene.objects.len()));
+        if let Some(tracing) = &self.tracing {
+            text.push_str(&format!("  pass {}/{}", tracing.pass(), self.tracer.passes()));
+        }
+        if self.simulating {
+            text.push_str("  physics");
+        }
+        let selection = self.scene.selection();
+        if let Some(&first) = selection.first() {
+            let object = &self.scene.objects[first];
+            let p = object.position();
+            text.push_str(&format!("  |  {}.{} pos {:.2} {:.2} {:.2} scale {:.2}", object.kind(), first, p.x, p.y, p.z, object.scale_factor()));
+            if selection.len() > 1 {
+                text.push_str(&format!(" (+{} more)", selection.len() - 1));
+            }
+        }
+        if self.scene.animation_end() > 0.0 || self.timeline.time > 0.0 {
+            let end = self.scene.animation_end().max(self.timeline.time);
+            let state = if self.timeline.playing { "play" } else { "pause" };
+            text.push_str(&format!("  |  {} {:.2}/{:.2}s", state, self.timeline.time, end));
+        }
+        text
+    }
+
+    fn perform(&mut self, action: Action) {
+        let config = &self.config;
+        let scene = &mut self.scene;
+        let history = &mut self.history;
+        let [p1, p2, p3] = config.spawn;
+        let (width, height) = (self.frame.width, self.frame.height);
+        match action {
+            // Started by `press` and carried out a tick at a time.
+            Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight | Action::MoveNear | Action::MoveFar
+            | Action::TurnUp | Action::TurnDown | Action::TurnLeft | Action::TurnRight | Action::Grow | Action::Shrink => {}
+            Action::Pick => {
+                if let Some((x, y)) = self.cursor {
+                    let ray = scene.camera.ray(x, y, width, height);
+                    // Handles sit on top of the objects, so they get the click first.
+                    let gizmo = self.overlays.gizmo.and_then(|mode| Gizmo::new(scene, mode, width));
+                    self.drag = gizmo.and_then(|g| g.handle_at(&scene.camera, x, y, width, height).and_then(|h| Drag::begin(g, h, &ray))).map(|d| d.with_snap(self.snap));
+                    // A drag is one undo step, however long it pauses.
+                    history.seal();
+                    if self.drag.is_none() {
+                        match self.frame.object_at(x as u32, y as u32) {
+                            Some(hit) => scene.select(hit, self.shift),
+                            None if !self.shift => scene.deselect_all(),
+                            None => {}
+                        }
+                    }
+                }
+                self.overlays.handle = self.drag.map(|d| d.handle);
+            }
+            Action::Undo => { history.undo(scene); }
+            Action::Redo => { history.redo(scene); }
+            Action::Copy => self.clipboard = scene.copy_selection(Point3D::new(0.0, 0.0, 0.0)),
+            Action::Paste => {
+                // Each paste lands one step further along, not on top of the last.
+                self.clipboard = self.clipboard.iter().cloned().map(|mut object| {
+                    object.move_world(config.paste_offset);
+                    object
+                }).collect();
+                history.execute(scene, Edit::Insert(self.clipboard.clone()));
+            }
+            Action::Duplicate => {
+                let copies = scene.copy_selection(config.paste_offset);
+                history.execute(scene, Edit::Insert(copies));
+            }
+            Action::Delete => history.execute(scene, Edit::Delete),
+            Action::OrderUp => history.execute(scene, Edit::Reorder(-1)),
+            Action::OrderDown => history.execute(scene, Edit::Reorder(1)),
+            Action::Select(index) => scene.select(index, false),
+            Action::SpawnCube => history.execute(scene, Edit::Spawn(Box::new(Objects::Cube(Cube::new(p1, p2, config.color(action)))))),
+            Action::SpawnPlane => history.execute(scene, Edit::Spawn(Box::new(Objects::Plane(Plane::new(p1, p2, config.color(action)))))),
+            Action::SpawnTriangle => history.execute(scene, Edit::Spawn(Box::new(Objects::Triangle(Triangle::new(p1, p2, p3, config.color(action)))))),
+            Action::SpawnPyramid => history.execute(scene, Edit::Spawn(Box::new(Objects::Pyramid(Pyramid::new(p1, p2, p3, config.color(action)))))),
+            Action::SpawnSphere => history.execute(scene, Edit::Spawn(Box::new(Objects::Sphere(Sphere::new(p1, 1.0, 8, 16, config.color(action)).with_tessellation(Tessellation::Auto))))),
+            Action::SpawnCylinder => history.execute(scene, Edit::Spawn(Box::new(Objects::Cylinder(Cylinder::new(p1, 0.5, 1.2, 24, config.color(action)))))),
+            Action::SpawnCone => history.execute(scene, Edit::Spawn(Box::new(Objects::Cone(Cone::new(p1, 0.6, 1.2, 24, config.color(action)))))),
+            Action::SpawnTorus => history.execute(scene, Edit::Spawn(Box::new(Objects::Torus(Torus::new(p1, 0.7, 0.25, 32, 16, config.color(action)))))),
+            Action::SpawnCapsule => history.execute(scene, Edit::Spawn(Box::new(Objects::Capsule(Capsule::new(p1, 0.4, 0.8, 24, 6, config.color(action)))))),
+            Action::SpawnDisk => history.execute(scene, Edit::Spawn(Box::new(Objects::Disk(Disk::new(p1, 0.8, 0.0, 32, config.color(action)))))),
+            Action::SpawnEmitter => history.execute(scene, Edit::Spawn(Box::new(Objects::Emitter(Emitter::new(p1, 40.0, 1.5, 3.0, config.color(action)))))),
+            Action::SpawnPolyhedron => {
+                if let Some(polyhedron) = Polyhedron::from_notation(POLYHEDRA[self.polyhedron_num]) {
+                    history.execute(scene, Edit::Spawn(Box::new(Objects::Mesh(polyhedron.to_mesh(p3, 0.8, config.color(action))))));
+                }
+                self.polyhedron_num = (self.polyhedron_num + 1) % POLYHEDRA.len();
+            }
+            Action::CycleColor => {
+                // Step the selection through the palette, starting after its first object's color.
+                if let Some(&first) = scene.selection().first() {
+                    let current = PALETTE.iter().position(|&c| c == scene.objects[first].color());
+                    let next = current.map_or(0, |i| (i + 1) % PALETTE.len());
+                    history.execute(scene, Edit::Color(PALETTE[next]));
+                }
+            }
+            Action::Deselect => scene.deselect_all(),
+            Action::ToggleBounds => self.overlays.bounds = !self.overlays.bounds,
+            Action::ToggleSnap => self.snap.enabled = !self.snap.enabled,
+            Action::ToggleGrid => self.overlays.grid = !self.overlays.grid,
+            Action::ToggleAxes => self.overlays.axes = !self.overlays.axes,
+            Action::ToggleOrientation => self.overlays.orientation = !self.overlays.orientation,
+            Action::ToggleHud => self.show_hud = !self.show_hud,
+            Action::EnterValue => self.prompt = Some(String::new()),
+            Action::GizmoTranslate => self.overlays.gizmo = Some(GizmoMode::Translate),
+            Action::GizmoRotate => self.overlays.gizmo = Some(GizmoMode::Rotate),
+            Action::GizmoScale => self.overlays.gizmo = Some(GizmoMode::Scale),
+            Action::Save => {
+                if let Err(err) = scene.save(&self.scene_path) {
+                    self.errors.push(format!("{}: {}", self.scene_path, err));
+                }
+            }
+            Action::Load => {
+                match Scene::load(&self.scene_path) {
+                    Ok(loaded) => {
+                        *scene = loaded;
+                        self.z_move = scene.light.z;
+                        history.clear();
+                        self.rest = None;
+                        self.simulating = false;
+                    }
+                    Err(err) => self.errors.push(format!("{}: {}", self.scene_path, err)),
+                }
+            }
+            Action::ExportGltf => {
+                let path = export_path(&self.scene_path, "glb");
+                if let Err(err) = gltf::save(&path, &scene.objects) {
+                    self.errors.push(format!("{}: {}", path.display(), err));
+                }
+            }
+            Action::PlayPause => self.timeline.toggle(scene.animation_end()),
+            Action::ScrubBack | Action::ScrubForward | Action::TimelineStart | Action::TimelineEnd => {
+                match action {
+                    Action::ScrubBack => self.timeline.scrub(-SCRUB_STEP),
+                    Action::ScrubForward => self.timeline.scrub(SCRUB_STEP),
+                    Action::TimelineStart => self.timeline.time = 0.0,
+                    _ => self.timeline.time = scene.animation_end(),
+                }
+                scene.pose(self.timeline.time);
+            }
+            Action::ToggleLoop => self.timeline.looping = !self.timeline.looping,
+            Action::KeySelection => history.execute(scene, Edit::Key(self.timeline.time, self.timeline.easing)),
+            Action::UnkeySelection => history.execute(scene, Edit::Unkey(self.timeline.time)),
+            Action::KeyLight => {
+                let light = scene.light;
+                scene.light_track.insert(Keyframe{ time: self.timeline.time, value: [light.x, light.y, light.z], easing: self.timeline.easing });
+            }
+            Action::CycleEasing => self.timeline.cycle_easing(),
+            Action::RecordTurntable => {
+                if let Some((path, _)) = &self.recording {
+                    self.errors.push(format!("{}: still recording", path.display()));
+                    return;
+                }
+                let shot = Shot::Turntable(turntable_center(scene, &scene.selection()));
+                let frames = (shot.duration(scene) * DEFAULT_FPS as f32).round() as u32;
+                let (width, height) = self.size;
+                let tracer = self.tracing.as_ref().map(|_| self.tracer);
+                let recording = Recording{ width, height, fps: DEFAULT_FPS, frames, start: 0.0, mode: self.mode, tracer };
+                // Rendered on its own thread from a copy, so the viewer stays responsive meanwhile.
+                let path = export_path(&self.scene_path, "gif");
+                let output = path.to_string_lossy().into_owned();
+                let scene = scene.clone();
+                let handle = thread::spawn(move || record(&scene, &shot, &recording, &output));
+                self.recording = Some((path, handle));
+            }
+            Action::TogglePhysics => {
+                self.simulating = !self.simulating;
+                if self.simulating && self.rest.is_none() {
+                    self.rest = Some((history.layout(), scene.objects.clone()));
+                }
+            }
+            Action::ResetPhysics => {
+                if let Some((_, start)) = self.rest.take() {
+                    self.simulating = false;
+                    // The snapshot only lasts while the layout is unchanged, so it lines up by index.
+                    let indices: Vec<usize> = (0..start.len()).collect();
+                    history.seal();
+                    history.modify(scene, EditKind::Move, &indices, |scene| {
+                        for (object, before) in scene.objects.iter_mut().zip(start) {
+                            let selected = object.is_selected();
+                            *object = before;
+                            if selected { object.select() } else { object.deselect() }
+                        }
+                    });
+                    history.seal();
+                }
+            }
+            Action::TogglePinned => {
+                if let Some(&first) = scene.selection().first() {
+                    let mass = if scene.objects[first].state().body.is_fixed() { Body::default().mass } else { 0.0 };
+                    history.execute(scene, Edit::Mass(mass));
+                }
+            }
+            Action::ToggleRayTrace => self.tracing = match self.tracing {
+                Some(_) => None,
+                None => Some(Progressive::new(self.tracer)),
+            },
+            Action::Quit => self.quit = true,
+        }
+        if self.rest.as_ref().is_some_and(|(layout, _)| *layout != self.history.layout()) {
+            self.rest = None;
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::io::{self, Write};
+
+use crate::drawing::Frame;
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream with full-range 4:2:0 chroma,
+/// which most video players and converters read directly.
+pub struct Y4mEncoder<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+}
+
+impl<W: Write> Y4mEncoder<W> {
+    /// Writes the stream header for `width`x`height` frames at `fps` frames per second.
+    pub fn new(mut out: W, width: u32, height: u32, fps: u32) -> io::Result<Self> {
+        writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg", width, height, fps)?;
+        Ok(Y4mEncoder{ out, width, height })
+    }
+
+    pub fn add_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        if frame.width != self.width || frame.height != self.height {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size differs from the stream's"));
+        }
+        let (w, h) = (self.width as usize, self.height as usize);
+        let rgb = |x: usize, y: usize| {
+            let i = (y * w + x) * 4;
+            [frame.color[i] as f32, frame.color[i + 1] as f32, frame.color[i + 2] as f32]
+        };
+
+        let mut luma = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let [r, g, b] = rgb(x, y);
+                luma.push(to_byte(0.299 * r + 0.587 * g + 0.114 * b));
+            }
+        }
+
+        // Each chroma sample averages a 2x2 block; odd sizes repeat the last row or column.
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        let mut cb = Vec::with_capacity(cw * ch);
+        let mut cr = Vec::with_capacity(cw * ch);
+        for cy in 0..ch {
+            for cx in 0..cw {
+                let mut sum = [0.0; 3];
+                for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let p = rgb((2 * cx + x).min(w - 1), (2 * cy + y).min(h - 1));
+                    for c in 0..3 {
+                        sum[c] += p[c] / 4.0;
+                    }
+                }
+                let [r, g, b] = sum;
+                cb.push(to_byte(128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b));
+                cr.push(to_byte(128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b));
+            }
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&luma)?;
+        self.out.write_all(&cb)?;
+        self.out.write_all(&cr)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn to_byte(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}